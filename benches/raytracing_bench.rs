@@ -1,13 +1,58 @@
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use ray::material::Material;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use ray::scenes::random_scene;
+use ray::{
+    Bvh, Camera, Environment, Filter, LightList, RenderSettings, Renderer, SamplerKind, Vec3,
+};
 
 fn criterion_benchmark(c: &mut Criterion) {
-    let input = 5u64;
-    c.bench_with_input(BenchmarkId::new("function_name", input), &input, |b, i| {
-        b.iter(|| {
-            // Code to benchmark using input `i` goes here
-        })
-    });
+    let list = random_scene(&mut StdRng::seed_from_u64(0));
+    let bvh = Bvh::new(random_scene(&mut StdRng::seed_from_u64(0)).objects);
+    let lights = LightList::new();
+    let mut group = c.benchmark_group("render_random_scene");
+    group.sample_size(10);
+    for samples_per_pixel in [1_usize, 4].iter() {
+        let settings = RenderSettings {
+            width: 64,
+            height: 36,
+            samples_per_pixel: *samples_per_pixel,
+            max_depth: 50,
+            seed: 0,
+            sampler: SamplerKind::default(),
+            filter: Filter::default(),
+            adaptive: None,
+        };
+        let camera = Camera::default(
+            Vec3::new(13.0, 2.0, 3.0),
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            20.0_f64,
+            settings.aspect_ratio(),
+            0.1,
+            10.0,
+        );
+        let renderer = Renderer::new(settings);
+        group.bench_with_input(
+            BenchmarkId::new("list", samples_per_pixel),
+            samples_per_pixel,
+            |b, _| {
+                b.iter(|| {
+                    renderer.render(black_box(&list), &lights, &Environment::Gradient, &camera)
+                })
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("bvh", samples_per_pixel),
+            samples_per_pixel,
+            |b, _| {
+                b.iter(|| {
+                    renderer.render(black_box(&bvh), &lights, &Environment::Gradient, &camera)
+                })
+            },
+        );
+    }
+    group.finish();
 }
 
 criterion_group!(benches, criterion_benchmark);
@@ -0,0 +1,131 @@
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+/// Axis-aligned bounding box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub fn new(min: Vec3, max: Vec3) -> Aabb {
+        Aabb { min, max }
+    }
+
+    /// A box containing nothing, the neutral element of [`Aabb::union`].
+    pub fn empty() -> Aabb {
+        Aabb {
+            min: Vec3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+            max: Vec3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+        }
+    }
+
+    pub fn from_points<'a, I: IntoIterator<Item = &'a Vec3>>(points: I) -> Aabb {
+        points
+            .into_iter()
+            .fold(Aabb::empty(), |acc, p| acc.union_point(p))
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: Vec3::new(
+                self.min.x.min(other.min.x),
+                self.min.y.min(other.min.y),
+                self.min.z.min(other.min.z),
+            ),
+            max: Vec3::new(
+                self.max.x.max(other.max.x),
+                self.max.y.max(other.max.y),
+                self.max.z.max(other.max.z),
+            ),
+        }
+    }
+
+    pub fn union_point(&self, p: &Vec3) -> Aabb {
+        self.union(&Aabb::new(*p, *p))
+    }
+
+    pub fn centroid(&self) -> Vec3 {
+        0.5 * (self.min + self.max)
+    }
+
+    pub fn diagonal(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    pub fn surface_area(&self) -> f64 {
+        let d = self.diagonal();
+        if d.x < 0.0 || d.y < 0.0 || d.z < 0.0 {
+            return 0.0;
+        }
+        2.0 * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+
+    /// Index of the axis along which the box is widest.
+    pub fn longest_axis(&self) -> usize {
+        let d = self.diagonal();
+        if d.x >= d.y && d.x >= d.z {
+            0
+        } else if d.y >= d.z {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// Slab test; `inverse_direction` is the componentwise reciprocal of the ray direction.
+    #[inline]
+    pub fn hit(&self, r: &Ray, inverse_direction: &Vec3, t_min: f64, t_max: f64) -> bool {
+        let mut t_enter = t_min;
+        let mut t_exit = t_max;
+        for axis in 0..3 {
+            let t0 = (self.min[axis] - r.origin[axis]) * inverse_direction[axis];
+            let t1 = (self.max[axis] - r.origin[axis]) * inverse_direction[axis];
+            let (t_near, t_far) = if t0 <= t1 { (t0, t1) } else { (t1, t0) };
+            // `max`/`min` ignore the NaN produced by 0 * inf when the origin lies on a slab.
+            t_enter = t_enter.max(t_near);
+            t_exit = t_exit.min(t_far);
+            if t_exit < t_enter {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Aabb;
+    use crate::ray::Ray;
+    use crate::vec3::Vec3;
+
+    fn inverse(v: &Vec3) -> Vec3 {
+        Vec3::new(1.0 / v.x, 1.0 / v.y, 1.0 / v.z)
+    }
+
+    #[test]
+    fn union_and_surface_area() {
+        let a = Aabb::new(Vec3::origin(), Vec3::new(1.0, 1.0, 1.0));
+        let b = Aabb::new(Vec3::new(1.0, 0.0, 0.0), Vec3::new(2.0, 1.0, 1.0));
+        let u = a.union(&b);
+        assert_eq!(u, Aabb::new(Vec3::origin(), Vec3::new(2.0, 1.0, 1.0)));
+        assert_eq!(u.surface_area(), 10.0);
+        assert_eq!(u.longest_axis(), 0);
+        assert_eq!(Aabb::empty().union(&a), a);
+        assert_eq!(Aabb::empty().surface_area(), 0.0);
+    }
+
+    #[test]
+    fn slab_test() {
+        let unit = Aabb::new(Vec3::new(-1.0, -1.0, -1.0), Vec3::new(1.0, 1.0, 1.0));
+        let towards = Ray::new(Vec3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0));
+        let away = Ray::new(Vec3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, -1.0));
+        let beside = Ray::new(Vec3::new(2.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0));
+
+        assert!(unit.hit(&towards, &inverse(&towards.direction), 0.0, f64::INFINITY));
+        assert!(!unit.hit(&towards, &inverse(&towards.direction), 0.0, 3.0));
+        assert!(!unit.hit(&away, &inverse(&away.direction), 0.0, f64::INFINITY));
+        assert!(!unit.hit(&beside, &inverse(&beside.direction), 0.0, f64::INFINITY));
+    }
+}
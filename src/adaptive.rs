@@ -0,0 +1,128 @@
+/// Stops sampling a pixel once its estimate is precise enough.
+///
+/// Every pixel takes at least `min_samples` samples, and at least two to estimate a variance.
+/// From then on, after every further batch of `min_samples`, the standard error of its mean
+/// luminance is estimated from the sample variance, and the pixel stops as soon as that error
+/// relative to the mean drops below `threshold`. Pixels that never get there stop at the maximum
+/// number of samples.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveSampling {
+    pub threshold: f64,
+    pub min_samples: usize,
+}
+
+impl AdaptiveSampling {
+    /// Whether a pixel with `statistics` can stop sampling.
+    // `usize::is_multiple_of` would need Rust 1.87.
+    #[allow(unknown_lints, clippy::manual_is_multiple_of)]
+    pub fn converged(&self, statistics: &PixelStatistics) -> bool {
+        let count = statistics.count();
+        count >= self.min_samples.max(2)
+            && count % self.min_samples.max(1) == 0
+            && statistics.relative_error() < self.threshold
+    }
+}
+
+/// Running mean and variance of the luminance samples of a pixel, updated with Welford's
+/// algorithm.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PixelStatistics {
+    count: usize,
+    mean: f64,
+    /// Sum of the squared differences from the mean.
+    m2: f64,
+}
+
+impl PixelStatistics {
+    pub fn add(&mut self, value: f64) {
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (value - self.mean);
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    /// Unbiased sample variance, zero for fewer than two samples.
+    pub fn variance(&self) -> f64 {
+        if self.count < 2 {
+            0.0
+        } else {
+            self.m2 / (self.count - 1) as f64
+        }
+    }
+
+    /// Standard error of the mean relative to the mean. Nearly black pixels are measured against
+    /// a small floor instead, so that they can converge too.
+    pub fn relative_error(&self) -> f64 {
+        if self.count == 0 {
+            return f64::INFINITY;
+        }
+        let standard_error = (self.variance() / self.count as f64).sqrt();
+        standard_error / self.mean.abs().max(1e-3)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AdaptiveSampling, PixelStatistics};
+
+    #[test]
+    fn welford_matches_two_pass_variance() {
+        let values = [0.5, 1.25, 0.0, 3.0, 2.5, 0.75, 1e-3, 1.5];
+        let mut statistics = PixelStatistics::default();
+        for value in &values {
+            statistics.add(*value);
+        }
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0);
+        assert_eq!(statistics.count(), values.len());
+        assert!((statistics.mean() - mean).abs() < 1e-12);
+        assert!((statistics.variance() - variance).abs() < 1e-12);
+        assert!((statistics.relative_error() - (variance / n).sqrt() / mean).abs() < 1e-12);
+    }
+
+    #[test]
+    fn stops_after_full_batches_below_the_threshold() {
+        let adaptive = AdaptiveSampling {
+            threshold: 0.01,
+            min_samples: 4,
+        };
+        let mut flat = PixelStatistics::default();
+        for _ in 0..3 {
+            flat.add(0.5);
+            assert!(!adaptive.converged(&flat));
+        }
+        flat.add(0.5);
+        assert!(adaptive.converged(&flat));
+        // Only checked at the end of a batch.
+        flat.add(0.5);
+        assert!(!adaptive.converged(&flat));
+
+        let mut noisy = PixelStatistics::default();
+        for i in 0..8 {
+            noisy.add(if i % 2 == 0 { 0.0 } else { 1.0 });
+        }
+        assert!(!adaptive.converged(&noisy));
+    }
+
+    #[test]
+    fn a_single_sample_never_converges() {
+        let adaptive = AdaptiveSampling {
+            threshold: 0.01,
+            min_samples: 1,
+        };
+        let mut statistics = PixelStatistics::default();
+        statistics.add(0.5);
+        assert!(!adaptive.converged(&statistics));
+        statistics.add(0.5);
+        assert!(adaptive.converged(&statistics));
+    }
+}
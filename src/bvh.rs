@@ -0,0 +1,372 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::ray::Ray;
+use crate::sample_rng::SampleRng;
+use crate::vec3::Vec3;
+
+/// Number of buckets the centroid range is split into when evaluating the surface area heuristic.
+const SAH_BUCKETS: usize = 16;
+/// Relative cost of visiting an interior node compared to intersecting one primitive.
+const TRAVERSAL_COST: f64 = 0.125;
+const MAX_PRIMITIVES_IN_LEAF: usize = 4;
+
+enum BvhNodeKind {
+    /// Primitives `first..first + count` of the reordered object list.
+    Leaf { first: usize, count: usize },
+    /// The first child directly follows its parent in the node array.
+    Interior { second_child: usize, axis: usize },
+}
+
+struct BvhNode {
+    bounds: Aabb,
+    kind: BvhNodeKind,
+}
+
+/// Bounding volume hierarchy over a set of objects, built with a binned surface area heuristic.
+///
+/// Objects without a bounding box are kept aside and tested against every ray.
+pub struct Bvh<T: Hittable> {
+    nodes: Vec<BvhNode>,
+    objects: Vec<T>,
+    unbounded: Vec<T>,
+}
+
+struct BuildPrimitive {
+    index: usize,
+    bounds: Aabb,
+    centroid: Vec3,
+}
+
+#[derive(Clone, Copy)]
+struct Bucket {
+    count: usize,
+    bounds: Aabb,
+}
+
+impl<T: Hittable> Bvh<T> {
+    pub fn new(objects: Vec<T>) -> Bvh<T> {
+        let mut primitives = Vec::with_capacity(objects.len());
+        let mut unbounded_indices = Vec::new();
+        for (index, object) in objects.iter().enumerate() {
+            match object.bounding_box() {
+                Some(bounds) => primitives.push(BuildPrimitive {
+                    index,
+                    bounds,
+                    centroid: bounds.centroid(),
+                }),
+                None => unbounded_indices.push(index),
+            }
+        }
+
+        let mut nodes = Vec::with_capacity(2 * primitives.len());
+        if !primitives.is_empty() {
+            build_recursive(&mut nodes, &mut primitives, 0);
+        }
+
+        // Move the objects into the order the leaves refer to them in.
+        let mut slots: Vec<Option<T>> = objects.into_iter().map(Some).collect();
+        let mut take = |index: usize| slots[index].take().expect("each object is used once");
+        let ordered = primitives.iter().map(|p| take(p.index)).collect();
+        let unbounded = unbounded_indices.into_iter().map(take).collect();
+
+        Bvh {
+            nodes,
+            objects: ordered,
+            unbounded,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.objects.len() + self.unbounded.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Builds the subtree over `primitives` and returns its node index.
+fn build_recursive(
+    nodes: &mut Vec<BvhNode>,
+    primitives: &mut [BuildPrimitive],
+    offset: usize,
+) -> usize {
+    let bounds = primitives
+        .iter()
+        .fold(Aabb::empty(), |acc, p| acc.union(&p.bounds));
+    let node_index = nodes.len();
+    let leaf = BvhNode {
+        bounds,
+        kind: BvhNodeKind::Leaf {
+            first: offset,
+            count: primitives.len(),
+        },
+    };
+
+    if primitives.len() <= 1 {
+        nodes.push(leaf);
+        return node_index;
+    }
+
+    let centroid_bounds = Aabb::from_points(primitives.iter().map(|p| &p.centroid));
+    let axis = centroid_bounds.longest_axis();
+    let axis_min = centroid_bounds.min[axis];
+    let axis_extent = centroid_bounds.max[axis] - axis_min;
+
+    if axis_extent <= 0.0 {
+        // All centroids coincide, no split can separate them.
+        nodes.push(leaf);
+        return node_index;
+    }
+
+    let bucket_of = |p: &BuildPrimitive| -> usize {
+        let relative = (p.centroid[axis] - axis_min) / axis_extent;
+        ((relative * SAH_BUCKETS as f64) as usize).min(SAH_BUCKETS - 1)
+    };
+
+    let mut buckets = [Bucket {
+        count: 0,
+        bounds: Aabb::empty(),
+    }; SAH_BUCKETS];
+    for p in primitives.iter() {
+        let bucket = &mut buckets[bucket_of(p)];
+        bucket.count += 1;
+        bucket.bounds = bucket.bounds.union(&p.bounds);
+    }
+
+    // Sweep from both sides so that every split is evaluated in linear time.
+    let mut cost_below = [0.0; SAH_BUCKETS - 1];
+    let mut accumulated = Aabb::empty();
+    let mut count = 0;
+    for split in 0..SAH_BUCKETS - 1 {
+        accumulated = accumulated.union(&buckets[split].bounds);
+        count += buckets[split].count;
+        cost_below[split] = count as f64 * accumulated.surface_area();
+    }
+    let mut best_split = 0;
+    let mut best_cost = f64::INFINITY;
+    let mut accumulated = Aabb::empty();
+    let mut count = 0;
+    for split in (0..SAH_BUCKETS - 1).rev() {
+        accumulated = accumulated.union(&buckets[split + 1].bounds);
+        count += buckets[split + 1].count;
+        let cost = cost_below[split] + count as f64 * accumulated.surface_area();
+        if cost < best_cost {
+            best_cost = cost;
+            best_split = split;
+        }
+    }
+
+    let surface_area = bounds.surface_area();
+    let split_cost = if surface_area > 0.0 {
+        TRAVERSAL_COST + best_cost / surface_area
+    } else {
+        TRAVERSAL_COST
+    };
+    let leaf_cost = primitives.len() as f64;
+    if primitives.len() <= MAX_PRIMITIVES_IN_LEAF && leaf_cost <= split_cost {
+        nodes.push(leaf);
+        return node_index;
+    }
+
+    let mut mid = partition(primitives, |p| bucket_of(p) <= best_split);
+    if mid == 0 || mid == primitives.len() {
+        // The heuristic could not separate the primitives, fall back to a median split.
+        mid = primitives.len() / 2;
+        primitives.select_nth_unstable_by(mid, |a, b| {
+            a.centroid[axis]
+                .partial_cmp(&b.centroid[axis])
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+    }
+
+    nodes.push(BvhNode {
+        bounds,
+        kind: BvhNodeKind::Interior {
+            second_child: 0,
+            axis,
+        },
+    });
+    let (below, above) = primitives.split_at_mut(mid);
+    build_recursive(nodes, below, offset);
+    let second = build_recursive(nodes, above, offset + mid);
+    nodes[node_index].kind = BvhNodeKind::Interior {
+        second_child: second,
+        axis,
+    };
+    node_index
+}
+
+/// Moves all elements satisfying `predicate` to the front and returns how many there are.
+fn partition<P: Fn(&BuildPrimitive) -> bool>(
+    primitives: &mut [BuildPrimitive],
+    predicate: P,
+) -> usize {
+    let mut first_false = 0;
+    for i in 0..primitives.len() {
+        if predicate(&primitives[i]) {
+            primitives.swap(i, first_false);
+            first_false += 1;
+        }
+    }
+    first_false
+}
+
+impl<T: Hittable> Bvh<T> {
+    /// Closest of the hits `hit_object` finds on the objects whose bounds `r` passes through,
+    /// called with the closest distance so far as its `t_max`.
+    fn closest_hit<'a, F>(
+        &'a self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        mut hit_object: F,
+    ) -> Option<HitRecord<'a>>
+    where
+        F: FnMut(&'a T, f64) -> Option<HitRecord<'a>>,
+    {
+        let mut res: Option<HitRecord> = None;
+        let mut closest_so_far = t_max;
+
+        for object in &self.unbounded {
+            if let Some(current_hit) = hit_object(object, closest_so_far) {
+                closest_so_far = current_hit.t;
+                res = Some(current_hit);
+            }
+        }
+
+        if self.nodes.is_empty() {
+            return res;
+        }
+
+        let inverse_direction = Vec3::new(
+            1.0 / r.direction.x,
+            1.0 / r.direction.y,
+            1.0 / r.direction.z,
+        );
+        let mut stack = Vec::with_capacity(64);
+        stack.push(0);
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            if !node
+                .bounds
+                .hit(r, &inverse_direction, t_min, closest_so_far)
+            {
+                continue;
+            }
+            match node.kind {
+                BvhNodeKind::Leaf { first, count } => {
+                    for object in &self.objects[first..first + count] {
+                        if let Some(current_hit) = hit_object(object, closest_so_far) {
+                            closest_so_far = current_hit.t;
+                            res = Some(current_hit);
+                        }
+                    }
+                }
+                BvhNodeKind::Interior { second_child, axis } => {
+                    // Visit the child closer to the ray origin first to shrink `closest_so_far`.
+                    if inverse_direction[axis] < 0.0 {
+                        stack.push(node_index + 1);
+                        stack.push(second_child);
+                    } else {
+                        stack.push(second_child);
+                        stack.push(node_index + 1);
+                    }
+                }
+            }
+        }
+        res
+    }
+}
+
+impl<T: Hittable> Hittable for Bvh<T> {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        self.closest_hit(r, t_min, t_max, |object, t_max| object.hit(r, t_min, t_max))
+    }
+
+    fn sample_hit(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        rng: &mut SampleRng,
+    ) -> Option<HitRecord<'_>> {
+        self.closest_hit(r, t_min, t_max, |object, t_max| {
+            object.sample_hit(r, t_min, t_max, rng)
+        })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        if !self.unbounded.is_empty() {
+            return None;
+        }
+        self.nodes.first().map(|root| root.bounds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::distributions::{Distribution, Uniform};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::Bvh;
+    use crate::color::Color;
+    use crate::hittable::{Hittable, HittableList};
+    use crate::material_variants::MaterialVariants;
+    use crate::ray::Ray;
+    use crate::sphere::Sphere;
+    use crate::vec3::Vec3;
+
+    fn random_spheres(count: usize, rng: &mut StdRng) -> Vec<Sphere> {
+        let position = Uniform::new(-10.0, 10.0);
+        let radius = Uniform::new(0.05, 0.5);
+        (0..count)
+            .map(|_| {
+                Sphere::new(
+                    Vec3::random_with_bounds_from(rng, -10.0, 10.0),
+                    radius.sample(rng)
+                        * if position.sample(rng) > 0.0 {
+                            1.0
+                        } else {
+                            -1.0
+                        },
+                    MaterialVariants::Lambertian(Color::new_white().into()),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn matches_linear_search() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut list = HittableList::new();
+        for sphere in random_spheres(500, &mut rng) {
+            list.add(Box::new(sphere));
+        }
+        let bvh = Bvh::new(random_spheres(500, &mut StdRng::seed_from_u64(7)));
+        assert_eq!(bvh.len(), 500);
+
+        for _ in 0..2000 {
+            let r = Ray::new(
+                Vec3::random_with_bounds_from(&mut rng, -12.0, 12.0),
+                Vec3::random_with_bounds_from(&mut rng, -1.0, 1.0),
+            );
+            let expected = list.hit(&r, 0.001, f64::INFINITY).map(|h| h.t);
+            let actual = bvh.hit(&r, 0.001, f64::INFINITY).map(|h| h.t);
+            assert_eq!(expected, actual);
+        }
+    }
+
+    #[test]
+    fn bounds_cover_all_objects() {
+        let spheres = random_spheres(100, &mut StdRng::seed_from_u64(3));
+        let expected = spheres
+            .iter()
+            .map(|s| s.bounding_box().unwrap())
+            .fold(crate::aabb::Aabb::empty(), |acc, b| acc.union(&b));
+        let bvh = Bvh::new(spheres);
+        assert_eq!(bvh.bounding_box(), Some(expected));
+        assert_eq!(Bvh::<Sphere>::new(Vec::new()).bounding_box(), None);
+    }
+}
@@ -1,7 +1,37 @@
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 use crate::util::degrees_to_radians;
 use crate::vec3::Vec3;
 
+/// Placement and lens parameters of a camera, independent of the output resolution.
+#[derive(Debug, Clone, Copy)]
+pub struct CameraSettings {
+    pub look_from: Vec3,
+    pub look_at: Vec3,
+    pub vup: Vec3,
+    pub vfov: f64,
+    pub aperture: f64,
+    pub focus_dist: f64,
+    /// Times the shutter opens and closes; rays are spread evenly over the interval.
+    pub shutter_open: f64,
+    pub shutter_close: f64,
+}
+
+impl CameraSettings {
+    pub fn build(&self, aspect_ratio: f64) -> Camera {
+        Camera::default(
+            self.look_from,
+            self.look_at,
+            self.vup,
+            self.vfov,
+            aspect_ratio,
+            self.aperture,
+            self.focus_dist,
+        )
+        .with_shutter(self.shutter_open, self.shutter_close)
+    }
+}
+
 pub struct Camera {
     origin: Vec3,
     lower_left_corner: Vec3,
@@ -10,6 +40,8 @@ pub struct Camera {
     u: Vec3,
     v: Vec3,
     lens_radius: f64,
+    shutter_open: f64,
+    shutter_close: f64,
 }
 
 impl Camera {
@@ -49,16 +81,32 @@ impl Camera {
             u,
             v,
             lens_radius,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
         }
     }
 
-    pub fn get_ray(&self, s: f64, t: f64) -> Ray {
-        let rd = self.lens_radius * Vec3::random_in_unit_disk();
+    /// Spreads the rays evenly over the times from `shutter_open` to `shutter_close`.
+    pub fn with_shutter(self, shutter_open: f64, shutter_close: f64) -> Camera {
+        Camera {
+            shutter_open,
+            shutter_close,
+            ..self
+        }
+    }
+
+    /// Ray through the viewport position `(s, t)`, with the lens position and time drawn from
+    /// `sampler`.
+    pub fn get_ray<S: Sampler + ?Sized>(&self, s: f64, t: f64, sampler: &mut S) -> Ray {
+        let rd = self.lens_radius * Vec3::random_in_unit_disk(sampler);
         let offset: Vec3 = self.u * rd.x + self.v * rd.y;
 
-        Ray::new(
+        let time = self.shutter_open + sampler.get_1d() * (self.shutter_close - self.shutter_open);
+
+        Ray::with_time(
             self.origin + offset,
             self.lower_left_corner + s * self.horizontal + t * self.vertical - self.origin - offset,
+            time,
         )
     }
 }
@@ -29,6 +29,11 @@ impl Color {
         self.z
     }
 
+    /// Relative luminance with the Rec. 709 weights.
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.x + 0.7152 * self.y + 0.0722 * self.z
+    }
+
     pub fn as_bytes(&self) -> [u8; 3] {
         [
             color_double_to_byte(self.x),
@@ -0,0 +1,178 @@
+use rand::Rng;
+
+use crate::aabb::Aabb;
+use crate::hittable::{Face, HitRecord, Hittable};
+use crate::material_variants::MaterialVariants;
+use crate::ray::Ray;
+use crate::sample_rng::SampleRng;
+
+/// Homogeneous participating medium filling a closed, convex `boundary`, such as fog or smoke.
+///
+/// A ray inside travels an exponentially distributed distance with mean `1 / density` before it
+/// scatters according to `phase`, which should be one of the phase function materials. Rays
+/// that leave the boundary first pass through unchanged, so the same boundary can also be used
+/// as a glass surface around the medium.
+///
+/// The free flight is drawn from the generator passed to `sample_hit`. Plain `hit` has none to
+/// draw from and passes straight through the medium.
+pub struct ConstantMedium<H: Hittable> {
+    pub boundary: H,
+    pub density: f64,
+    pub phase: MaterialVariants,
+}
+
+impl<H: Hittable> ConstantMedium<H> {
+    pub fn new(boundary: H, density: f64, phase: MaterialVariants) -> ConstantMedium<H> {
+        ConstantMedium {
+            boundary,
+            density,
+            phase,
+        }
+    }
+}
+
+impl<H: Hittable> Hittable for ConstantMedium<H> {
+    fn hit(&self, _r: &Ray, _t_min: f64, _t_max: f64) -> Option<HitRecord<'_>> {
+        None
+    }
+
+    fn sample_hit(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        rng: &mut SampleRng,
+    ) -> Option<HitRecord<'_>> {
+        // Find the whole segment inside the boundary, even if the ray starts within it.
+        let entry = self.boundary.hit(r, f64::NEG_INFINITY, f64::INFINITY)?.t;
+        let exit = self.boundary.hit(r, entry + 0.0001, f64::INFINITY)?.t;
+        let t_enter = entry.max(t_min);
+        let t_exit = exit.min(t_max);
+        if t_enter >= t_exit {
+            return None;
+        }
+
+        let length = r.direction.length();
+        let distance_inside = (t_exit - t_enter) * length;
+        // `1 - x` keeps the logarithm finite.
+        let free_flight = -(1.0 - rng.gen::<f64>()).ln() / self.density;
+        if free_flight > distance_inside {
+            return None;
+        }
+
+        let t = t_enter + free_flight / length;
+        Some(HitRecord {
+            p: r.at(t),
+            // There is no surface, phase functions only look at the ray directions.
+            normal: -r.direction / length,
+            t,
+            u: 0.0,
+            v: 0.0,
+            face: Face::Outside,
+            material: &self.phase,
+        })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.boundary.bounding_box()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::ConstantMedium;
+    use crate::bvh::Bvh;
+    use crate::color::Color;
+    use crate::cuboid::Cuboid;
+    use crate::hittable::Hittable;
+    use crate::material_variants::MaterialVariants;
+    use crate::ray::Ray;
+    use crate::sample_rng::sample_rng;
+    use crate::sphere::Sphere;
+    use crate::transform::Transform;
+    use crate::transformed::Transformed;
+    use crate::vec3::Vec3;
+
+    #[test]
+    fn transmittance_follows_beer_lambert() {
+        let slab = ConstantMedium::new(
+            Cuboid::new(
+                Vec3::new(-10.0, -10.0, 0.0),
+                Vec3::new(10.0, 10.0, 2.0),
+                MaterialVariants::Dielectric(1.0, 0.0),
+            ),
+            0.5,
+            MaterialVariants::Isotropic(Color::new_white().into()),
+        );
+        // The direction is not normalized, distances inside count in world units.
+        let r = Ray::new(Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 0.0, 3.0));
+        assert!(slab.hit(&r, 0.001, f64::INFINITY).is_none());
+        let mut rng = sample_rng(16, 0, 0);
+        let samples = 100_000;
+        let mut passed = 0;
+        for _ in 0..samples {
+            match slab.sample_hit(&r, 0.001, f64::INFINITY, &mut rng) {
+                Some(hit) => {
+                    assert!(hit.p.z >= 0.0 && hit.p.z <= 2.0);
+                    assert!(matches!(hit.material, MaterialVariants::Isotropic(_)));
+                }
+                None => passed += 1,
+            }
+        }
+        let transmittance = passed as f64 / samples as f64;
+        assert!(
+            (transmittance - (-1.0f64).exp()).abs() < 0.01,
+            "{}",
+            transmittance
+        );
+    }
+
+    #[test]
+    fn rays_starting_inside_scatter_ahead() {
+        let fog = ConstantMedium::new(
+            Sphere::new(
+                Vec3::origin(),
+                1.0,
+                MaterialVariants::Lambertian(Color::new_white().into()),
+            ),
+            1000.0,
+            MaterialVariants::HenyeyGreenstein(Color::new_white().into(), 0.5),
+        );
+        let mut rng = sample_rng(16, 0, 0);
+        let r = Ray::new(Vec3::new(0.5, 0.0, 0.0), Vec3::new(-1.0, 0.0, 0.0));
+        let hit = fog.sample_hit(&r, 0.001, f64::INFINITY, &mut rng).unwrap();
+        assert!(hit.t > 0.001 && hit.t < 0.1);
+        // The same ray scatters at a different distance with the next sample.
+        let next = fog.sample_hit(&r, 0.001, f64::INFINITY, &mut rng).unwrap();
+        assert_ne!(hit.t, next.t);
+        // Nothing is scattered before `t_min` or beyond `t_max`.
+        assert!(fog.sample_hit(&r, 2.0, f64::INFINITY, &mut rng).is_none());
+        assert!(fog.sample_hit(&r, 0.001, 0.001 + 1e-9, &mut rng).is_none());
+    }
+
+    #[test]
+    fn plain_hits_see_through_media_in_containers() {
+        let fog: Arc<dyn Hittable + Send> = Arc::new(ConstantMedium::new(
+            Sphere::new(
+                Vec3::origin(),
+                1.0,
+                MaterialVariants::Lambertian(Color::new_white().into()),
+            ),
+            1000.0,
+            MaterialVariants::Isotropic(Color::new_white().into()),
+        ));
+        let world = Bvh::new(vec![Transformed::new(
+            fog,
+            Transform::translate(Vec3::new(0.0, 0.0, -5.0)),
+        )]);
+        let r = Ray::new(Vec3::origin(), Vec3::new(0.0, 0.0, -1.0));
+        assert!(world.hit(&r, 0.001, f64::INFINITY).is_none());
+        let mut rng = sample_rng(16, 0, 0);
+        let hit = world
+            .sample_hit(&r, 0.001, f64::INFINITY, &mut rng)
+            .unwrap();
+        assert!(hit.t > 4.0 && hit.t < 4.1);
+    }
+}
@@ -0,0 +1,96 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material_variants::MaterialVariants;
+use crate::quad::Quad;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+/// Axis-aligned box made of six outward facing quads; rotate it with a
+/// [`Transformed`](crate::transformed::Transformed).
+#[derive(Clone)]
+pub struct Cuboid {
+    faces: Vec<Quad>,
+    bounds: Aabb,
+}
+
+impl Cuboid {
+    /// Box between the opposite corners `a` and `b`.
+    pub fn new(a: Vec3, b: Vec3, material: MaterialVariants) -> Cuboid {
+        let min = Vec3::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z));
+        let max = Vec3::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z));
+        let dx = Vec3::new(max.x - min.x, 0.0, 0.0);
+        let dy = Vec3::new(0.0, max.y - min.y, 0.0);
+        let dz = Vec3::new(0.0, 0.0, max.z - min.z);
+
+        let face = |corner: Vec3, u: Vec3, v: Vec3| Quad::new(corner, u, v, material.clone());
+        let faces = vec![
+            face(Vec3::new(min.x, min.y, max.z), dx, dy),
+            face(Vec3::new(max.x, min.y, max.z), -dz, dy),
+            face(Vec3::new(max.x, min.y, min.z), -dx, dy),
+            face(Vec3::new(min.x, min.y, min.z), dz, dy),
+            face(Vec3::new(min.x, max.y, max.z), dx, -dz),
+            face(Vec3::new(min.x, min.y, min.z), dx, dz),
+        ];
+        Cuboid {
+            faces,
+            bounds: Aabb::new(min, max),
+        }
+    }
+}
+
+impl Hittable for Cuboid {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let mut res: Option<HitRecord> = None;
+        let mut closest_so_far = t_max;
+        for face in &self.faces {
+            if let Some(current_hit) = face.hit(r, t_min, closest_so_far) {
+                closest_so_far = current_hit.t;
+                res = Some(current_hit);
+            }
+        }
+        res
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.bounds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Cuboid;
+    use crate::color::Color;
+    use crate::hittable::{Face, Hittable};
+    use crate::material_variants::MaterialVariants;
+    use crate::ray::Ray;
+    use crate::vec3::Vec3;
+
+    #[test]
+    fn faces_point_outwards() {
+        let cuboid = Cuboid::new(
+            Vec3::new(1.0, 2.0, 3.0),
+            Vec3::new(-1.0, -2.0, -3.0),
+            MaterialVariants::Lambertian(Color::new_white().into()),
+        );
+        let axes = [
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+        ];
+        for axis in axes.iter() {
+            for sign in &[-1.0, 1.0] {
+                let outward = *sign * *axis;
+                let r = Ray::new(10.0 * outward + Vec3::new(0.1, 0.2, 0.3), -outward);
+                let hit = cuboid.hit(&r, 0.0, f64::INFINITY).unwrap();
+                assert_eq!(hit.face, Face::Outside);
+                assert_eq!(hit.normal, outward);
+
+                // From the inside the same wall is seen from the back.
+                let r = Ray::new(Vec3::origin(), outward);
+                let hit = cuboid.hit(&r, 0.0, f64::INFINITY).unwrap();
+                assert_eq!(hit.face, Face::Inside);
+                assert_eq!(hit.normal, -outward);
+            }
+        }
+    }
+}
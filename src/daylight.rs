@@ -0,0 +1,419 @@
+use std::f64::consts::PI;
+
+use crate::color::Color;
+use crate::distribution::Distribution2D;
+use crate::environment::{lat_long_coordinates, lat_long_direction, lat_long_to_solid_angle};
+use crate::onb::Onb;
+use crate::sampler::Sampler;
+use crate::vec3::Vec3;
+
+/// Angular radius of the sun seen from the earth, in radians.
+const SUN_ANGULAR_RADIUS: f64 = 0.004_65;
+
+/// Luminance of the sun outside the atmosphere in cd/m²: an illuminance of 128 klux spread over
+/// the solid angle of its disk.
+const SOLAR_LUMINANCE: f64 = 1.88e9;
+
+/// Luminance in cd/m² that makes up one unit of radiance. A white diffuse surface facing a high
+/// sun on a clear day comes out at about one.
+const CANDELA_PER_UNIT: f64 = 30_000.0;
+
+/// Resolution of the latitude-longitude table the sky is sampled from.
+const TABLE_WIDTH: usize = 64;
+const TABLE_HEIGHT: usize = 32;
+
+/// Parameters of a [`Daylight`] sky.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DaylightSettings {
+    /// Angle of the sun above the horizon in degrees.
+    pub sun_elevation: f64,
+    /// Compass direction of the sun in degrees, 0 along -z and 90 along +x, matching the
+    /// layout of environment images.
+    pub sun_azimuth: f64,
+    /// Haziness of the atmosphere, from 2 for a very clear sky to 10 for a hazy one; values
+    /// outside the range the model was fitted for are clamped.
+    pub turbidity: f64,
+    /// Diffuse reflectance of the ground below the horizon.
+    pub ground_albedo: f64,
+    /// Scale for the radiance of sky, sun and ground.
+    pub intensity: f64,
+}
+
+impl Default for DaylightSettings {
+    /// A clear afternoon with the sun 45° high in front of the default camera.
+    fn default() -> DaylightSettings {
+        DaylightSettings {
+            sun_elevation: 45.0,
+            sun_azimuth: 0.0,
+            turbidity: 3.0,
+            ground_albedo: 0.3,
+            intensity: 1.0,
+        }
+    }
+}
+
+impl DaylightSettings {
+    pub fn build(&self) -> Daylight {
+        Daylight::new(self)
+    }
+}
+
+/// Analytic daylight after Preetham, Shirley and Smits, "A Practical Analytic Model for
+/// Daylight" (SIGGRAPH 1999), with the sun as a disk of its true angular size.
+///
+/// The sky's luminance and chromaticity are Perez distributions fitted to the turbidity and the
+/// position of the sun. The sun is the extraterrestrial sun dimmed by Rayleigh and aerosol
+/// scattering along its path through the atmosphere, evaluated at one wavelength per color
+/// channel. Below the horizon is a diffuse ground lit by both. When the sun sets, the sky is
+/// kept as it is at sunset while the sun disappears below the ground.
+///
+/// For light sampling, the sun's cone is sampled directly and the rest of the sky through a
+/// piecewise-constant table of its luminance.
+#[derive(Debug)]
+pub struct Daylight {
+    sun_direction: Vec3,
+    /// Perez coefficients A to E of Y, x and y.
+    perez: [[f64; 5]; 3],
+    /// Y, x and y at the zenith divided by their Perez function there, so that multiplying by
+    /// the Perez function gives the value in any direction.
+    zenith: [f64; 3],
+    /// Direction of the sun as far as the sky is concerned, which stays at the horizon after
+    /// sunset.
+    sky_sun_direction: Vec3,
+    intensity: f64,
+    sun_radiance: Color,
+    ground_radiance: Color,
+    cos_sun_radius: f64,
+    /// Chance with which light sampling picks the sun over the sky table.
+    sun_probability: f64,
+    sky_distribution: Distribution2D,
+}
+
+impl Daylight {
+    pub fn new(settings: &DaylightSettings) -> Daylight {
+        let turbidity = settings.turbidity.clamp(1.7, 10.0);
+        let elevation = settings.sun_elevation.to_radians();
+        let azimuth = settings.sun_azimuth.to_radians();
+        let sun_direction = lat_long_direction((azimuth / (2.0 * PI) + 0.5, 0.5 - elevation / PI));
+        // The model only holds for the sun above the horizon.
+        let theta_sun = (PI / 2.0 - elevation).clamp(0.0, PI / 2.0);
+
+        let t = turbidity;
+        let perez = [
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+        ];
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_sun);
+        let zenith_luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let chromaticity = |coefficients: [[f64; 4]; 3]| {
+            let cubic = |c: [f64; 4]| {
+                c[0] * theta_sun.powi(3) + c[1] * theta_sun.powi(2) + c[2] * theta_sun + c[3]
+            };
+            t * t * cubic(coefficients[0]) + t * cubic(coefficients[1]) + cubic(coefficients[2])
+        };
+        let zenith_x = chromaticity([
+            [0.00166, -0.00375, 0.00209, 0.0],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]);
+        let zenith_y = chromaticity([
+            [0.00275, -0.00610, 0.00317, 0.0],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]);
+        let mut zenith = [zenith_luminance, zenith_x, zenith_y];
+        for (value, coefficients) in zenith.iter_mut().zip(&perez) {
+            *value /= perez_function(coefficients, 1.0, theta_sun);
+        }
+
+        let cos_sun_radius = SUN_ANGULAR_RADIUS.cos();
+        let mut daylight = Daylight {
+            sun_direction,
+            perez,
+            zenith,
+            sky_sun_direction: lat_long_direction((azimuth / (2.0 * PI) + 0.5, theta_sun / PI)),
+            intensity: settings.intensity,
+            sun_radiance: sun_radiance(elevation, turbidity) * settings.intensity,
+            ground_radiance: Color::new_black(),
+            cos_sun_radius,
+            sun_probability: 0.0,
+            sky_distribution: Distribution2D::new(1, 1, &[1.0]),
+        };
+
+        // Tabulate the sky, light the ground with it and the sun, and then tabulate the ground.
+        let cell_center = |i: usize| {
+            (
+                ((i % TABLE_WIDTH) as f64 + 0.5) / TABLE_WIDTH as f64,
+                ((i / TABLE_WIDTH) as f64 + 0.5) / TABLE_HEIGHT as f64,
+            )
+        };
+        let cell_solid_angle =
+            |v: f64| 2.0 * PI * PI * (PI * v).sin() / (TABLE_WIDTH * TABLE_HEIGHT) as f64;
+        let mut sky_irradiance = Color::new_black();
+        let mut sky_power = 0.0;
+        let table: Vec<Color> = (0..TABLE_WIDTH * TABLE_HEIGHT)
+            .map(|i| {
+                let (u, v) = cell_center(i);
+                let direction = lat_long_direction((u, v));
+                if direction.y < 0.0 {
+                    return Color::new_black();
+                }
+                let radiance = daylight.sky_radiance(&direction);
+                sky_irradiance += radiance * direction.y * cell_solid_angle(v);
+                radiance
+            })
+            .collect();
+        let sun_solid_angle = 2.0 * PI * (1.0 - cos_sun_radius);
+        let sun_irradiance = daylight.sun_radiance * sun_solid_angle * sun_direction.y.max(0.0);
+        daylight.ground_radiance = settings.ground_albedo / PI * (sky_irradiance + sun_irradiance);
+
+        let weights: Vec<f64> = table
+            .iter()
+            .enumerate()
+            .map(|(i, radiance)| {
+                let (u, v) = cell_center(i);
+                let radiance = if lat_long_direction((u, v)).y < 0.0 {
+                    daylight.ground_radiance
+                } else {
+                    *radiance
+                };
+                sky_power += radiance.luminance() * cell_solid_angle(v);
+                radiance.luminance() * (PI * v).sin()
+            })
+            .collect();
+        daylight.sky_distribution = Distribution2D::new(TABLE_WIDTH, TABLE_HEIGHT, &weights);
+        if sun_direction.y > -SUN_ANGULAR_RADIUS {
+            let sun_power = daylight.sun_radiance.luminance() * sun_solid_angle;
+            daylight.sun_probability = (sun_power / (sun_power + sky_power)).clamp(0.1, 0.9);
+        }
+        daylight
+    }
+
+    pub fn sun_direction(&self) -> Vec3 {
+        self.sun_direction
+    }
+
+    pub fn radiance(&self, direction: &Vec3) -> Color {
+        let direction = direction.make_unit_vector();
+        if direction.y < 0.0 {
+            return self.ground_radiance;
+        }
+        let sky = self.sky_radiance(&direction);
+        if direction.dot(&self.sun_direction) >= self.cos_sun_radius {
+            sky + self.sun_radiance
+        } else {
+            sky
+        }
+    }
+
+    pub fn sample_direction(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        if sampler.get_1d() < self.sun_probability {
+            let (r1, r2) = sampler.get_2d();
+            let phi = 2.0 * PI * r1;
+            let z = 1.0 + r2 * (self.cos_sun_radius - 1.0);
+            let r = (1.0 - z * z).sqrt();
+            Onb::from_w(&self.sun_direction).local(r * phi.cos(), r * phi.sin(), z)
+        } else {
+            let ((u, v), _) = self.sky_distribution.sample(sampler.get_2d());
+            lat_long_direction((u, v))
+        }
+    }
+
+    pub fn pdf_value(&self, direction: &Vec3) -> f64 {
+        let direction = direction.make_unit_vector();
+        let sun_pdf = if direction.dot(&self.sun_direction) >= self.cos_sun_radius {
+            1.0 / (2.0 * PI * (1.0 - self.cos_sun_radius))
+        } else {
+            0.0
+        };
+        let (u, v) = lat_long_coordinates(&direction);
+        let sky_pdf = lat_long_to_solid_angle(self.sky_distribution.pdf((u, v)), v);
+        self.sun_probability * sun_pdf + (1.0 - self.sun_probability) * sky_pdf
+    }
+
+    /// Radiance of the sky without the sun for a unit `direction` above the horizon.
+    fn sky_radiance(&self, direction: &Vec3) -> Color {
+        let gamma = direction
+            .dot(&self.sky_sun_direction)
+            .clamp(-1.0, 1.0)
+            .acos();
+        let [luminance, x, y] = [0, 1, 2].map(|channel| {
+            self.zenith[channel] * perez_function(&self.perez[channel], direction.y, gamma)
+        });
+        // Luminance in kcd/m² and chromaticity to linear Rec. 709.
+        let luminance = luminance.max(0.0) * 1000.0 / CANDELA_PER_UNIT * self.intensity;
+        if y <= 0.0 {
+            return Color::new_black();
+        }
+        let big_x = x / y * luminance;
+        let big_z = (1.0 - x - y) / y * luminance;
+        Color::new(
+            (3.2406 * big_x - 1.5372 * luminance - 0.4986 * big_z).max(0.0),
+            (-0.9689 * big_x + 1.8758 * luminance + 0.0415 * big_z).max(0.0),
+            (0.0557 * big_x - 0.2040 * luminance + 1.0570 * big_z).max(0.0),
+        )
+    }
+}
+
+/// The Perez sky distribution F(θ, γ) for a direction with cos θ = `cos_theta` from the zenith
+/// and at an angle `gamma` from the sun.
+fn perez_function(coefficients: &[f64; 5], cos_theta: f64, gamma: f64) -> f64 {
+    let [a, b, c, d, e] = *coefficients;
+    let cos_gamma = gamma.cos();
+    (1.0 + a * (b / cos_theta.max(1e-3)).exp())
+        * (1.0 + c * (d * gamma).exp() + e * cos_gamma * cos_gamma)
+}
+
+/// Radiance of the sun disk seen from the ground at `elevation` radians above the horizon, for
+/// the red, green and blue wavelengths of 610, 550 and 465 nm.
+fn sun_radiance(elevation: f64, turbidity: f64) -> Color {
+    // Relative optical air mass after Kasten and Young.
+    let zenith_degrees = (90.0 - elevation.to_degrees()).min(90.0);
+    let cos_zenith = zenith_degrees.to_radians().cos();
+    let air_mass = 1.0 / (cos_zenith + 0.50572 * (96.07995 - zenith_degrees).powf(-1.6364));
+    // Ångström's turbidity coefficient for an exponent of 1.3.
+    let beta = 0.04608 * turbidity - 0.04586;
+    let transmittance = |wavelength_micrometers: f64| {
+        let rayleigh = 0.008735 * wavelength_micrometers.powf(-4.08);
+        let aerosol = beta * wavelength_micrometers.powf(-1.3);
+        (-(rayleigh + aerosol) * air_mass).exp()
+    };
+    SOLAR_LUMINANCE / CANDELA_PER_UNIT
+        * Color::new(
+            transmittance(0.61),
+            transmittance(0.55),
+            transmittance(0.465),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::{DaylightSettings, CANDELA_PER_UNIT};
+    use crate::vec3::Vec3;
+
+    #[test]
+    fn zenith_luminance_follows_the_model() {
+        let daylight = DaylightSettings {
+            sun_elevation: 30.0,
+            turbidity: 2.0,
+            ..Default::default()
+        }
+        .build();
+        // Y_z = (4.0453 T - 4.9710) tan χ - 0.2155 T + 2.4192 kcd/m², χ = (4/9 - T/120)(π - 2θ_s).
+        let chi = (4.0 / 9.0 - 2.0 / 120.0) * (PI - 2.0 * PI / 3.0);
+        let expected = (4.0453 * 2.0 - 4.9710) * chi.tan() - 0.2155 * 2.0 + 2.4192;
+        let zenith = daylight.radiance(&Vec3::new(0.0, 1.0, 0.0));
+        let luminance = zenith.luminance() * CANDELA_PER_UNIT / 1000.0;
+        assert!(
+            (luminance - expected).abs() < 1e-3 * expected,
+            "{}",
+            luminance
+        );
+        // Clear skies are blue.
+        assert!(zenith.z > zenith.y && zenith.y > zenith.x);
+    }
+
+    #[test]
+    fn sun_disk_has_its_angular_size_and_reddens_at_sunset() {
+        let noon = DaylightSettings {
+            sun_elevation: 90.0,
+            turbidity: 2.0,
+            ..Default::default()
+        }
+        .build();
+        let sun = noon.sun_direction();
+        assert!((sun - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-12);
+        let at = |angle: f64| Vec3::new(angle.sin(), angle.cos(), 0.0);
+        let inside = noon.radiance(&at(0.0046));
+        let outside = noon.radiance(&at(0.0047));
+        assert!(inside.y > 1000.0 * outside.y);
+        // Close to 100 klux of direct sunlight, about three units.
+        let solid_angle = 2.0 * PI * (1.0 - 0.00465_f64.cos());
+        let irradiance = (inside - outside).luminance() * solid_angle;
+        assert!(irradiance > 2.5 && irradiance < 4.0, "{}", irradiance);
+
+        let sunset = DaylightSettings {
+            sun_elevation: 3.0,
+            sun_azimuth: 90.0,
+            turbidity: 2.0,
+            ..Default::default()
+        }
+        .build();
+        let sun = sunset.sun_direction();
+        assert!(sun.x > 0.99 && sun.y > 0.0);
+        let color = sunset.radiance(&sun) - sunset.radiance(&(sun + Vec3::new(0.0, 0.01, 0.0)));
+        assert!(color.x / color.z > 2.0 * inside.x / inside.z);
+        // The sky around the sun is brighter than the sky opposite it.
+        let around = sunset.radiance(&Vec3::new(1.0, 0.2, 0.0)).luminance();
+        let opposite = sunset.radiance(&Vec3::new(-1.0, 0.2, 0.0)).luminance();
+        assert!(around > opposite);
+    }
+
+    #[test]
+    fn sampling_matches_the_radiance_integrated_over_the_sphere() {
+        let daylight = DaylightSettings {
+            sun_elevation: 20.0,
+            sun_azimuth: -60.0,
+            ..Default::default()
+        }
+        .build();
+        // The sky and ground by quadrature, skipping the sun, which is added analytically.
+        let sun = daylight.sun_direction();
+        let steps = 600;
+        let mut expected = 0.0;
+        for i in 0..steps {
+            for j in 0..2 * steps {
+                let z = 1.0 - 2.0 * (i as f64 + 0.5) / steps as f64;
+                let phi = PI * (j as f64 + 0.5) / steps as f64;
+                let r = (1.0 - z * z).sqrt();
+                let direction = Vec3::new(r * phi.cos(), z, r * phi.sin());
+                if direction.dot(&sun) < daylight.cos_sun_radius {
+                    expected += daylight.radiance(&direction).y;
+                }
+            }
+        }
+        expected *= 4.0 * PI / (2 * steps * steps) as f64;
+        let solid_angle = 2.0 * PI * (1.0 - daylight.cos_sun_radius);
+        expected += daylight.sun_radiance.y * solid_angle;
+
+        let mut rng = StdRng::seed_from_u64(9);
+        let samples = 100_000;
+        let estimate = (0..samples)
+            .map(|_| {
+                let direction = daylight.sample_direction(&mut rng);
+                daylight.radiance(&direction).y / daylight.pdf_value(&direction)
+            })
+            .sum::<f64>()
+            / samples as f64;
+        assert!(
+            (estimate - expected).abs() < 0.01 * expected,
+            "{} {}",
+            estimate,
+            expected
+        );
+    }
+}
@@ -1,17 +1,34 @@
 use crate::color::Color;
 use crate::hittable::Face;
-use crate::material::ScatterResult;
+use crate::material::{BsdfEvaluation, ScatterResult};
+use crate::microfacet::{Ggx, SPECULAR_ROUGHNESS};
+use crate::onb::Onb;
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 use crate::vec3::Vec3;
-use rand::distributions::{Distribution, Uniform};
 
-pub fn dielectric_scatter(
+/// Reflects or refracts off glass with index of refraction `ref_idx`. Smooth glass, with a
+/// `roughness` of 0, is a perfect mirror and window; rough glass scatters off GGX microfacets.
+pub fn dielectric_scatter<S: Sampler + ?Sized>(
     incoming_ray: &Ray,
     normal: &Vec3,
     point: &Vec3,
     face: Face,
     ref_idx: f64,
+    roughness: f64,
+    sampler: &mut S,
 ) -> ScatterResult {
+    if roughness >= SPECULAR_ROUGHNESS {
+        return rough_dielectric_scatter(
+            incoming_ray,
+            normal,
+            point,
+            relative_eta(face, ref_idx),
+            &Ggx::from_roughness(roughness),
+            sampler,
+        );
+    }
+
     let etai_over_etat = match face {
         Face::Inside => ref_idx,
         Face::Outside => 1.0 / ref_idx,
@@ -26,17 +43,17 @@ pub fn dielectric_scatter(
         return ScatterResult::Scattered {
             attenuation: Color::new_white(),
             scattered: Ray::new(*point, reflected),
+            pdf: None,
         };
     }
     let reflect_prob = schlick(cos_theta, etai_over_etat);
 
-    let mut rng = rand::thread_rng();
-    let uniform_dist = Uniform::new(0.0_f64, 1.0_f64);
-    if uniform_dist.sample(&mut rng) < reflect_prob {
+    if sampler.get_1d() < reflect_prob {
         let reflected = unit_direction.reflect(normal);
         return ScatterResult::Scattered {
             attenuation: Color::new_white(),
             scattered: Ray::new(*point, reflected),
+            pdf: None,
         };
     }
 
@@ -45,9 +62,137 @@ pub fn dielectric_scatter(
     ScatterResult::Scattered {
         attenuation: Color::new_white(),
         scattered: Ray::new(*point, refracted),
+        pdf: None,
+    }
+}
+
+/// Index of refraction of the side a ray refracts into over that of the side it comes from.
+fn relative_eta(face: Face, ref_idx: f64) -> f64 {
+    match face {
+        Face::Inside => 1.0 / ref_idx,
+        Face::Outside => ref_idx,
+    }
+}
+
+/// Samples a visible microfacet and then reflection or refraction through it in proportion to
+/// its Fresnel reflectance (Walter et al., "Microfacet Models for Refraction through Rough
+/// Surfaces", 2007). Either way the weight is G2 / G1.
+fn rough_dielectric_scatter<S: Sampler + ?Sized>(
+    incoming_ray: &Ray,
+    normal: &Vec3,
+    point: &Vec3,
+    eta: f64,
+    ggx: &Ggx,
+    sampler: &mut S,
+) -> ScatterResult {
+    let frame = Onb::from_w(normal);
+    let wo = frame.to_local(&-incoming_ray.direction.make_unit_vector());
+    if wo.z <= 0.0 {
+        return ScatterResult::Absorbed;
+    }
+    let m = ggx.sample_visible_normal(&wo, sampler.get_2d());
+    let cos_o = wo.dot(&m);
+    if cos_o <= 0.0 {
+        return ScatterResult::Absorbed;
+    }
+    let reflectance = fresnel_dielectric(cos_o, eta);
+    let normal_pdf = ggx.visible_normal_pdf(&wo, &m);
+
+    let (wi, pdf) = if sampler.get_1d() < reflectance {
+        let wi = 2.0 * cos_o * m - wo;
+        if wi.z <= 0.0 {
+            return ScatterResult::Absorbed;
+        }
+        (wi, reflectance * normal_pdf / (4.0 * cos_o))
+    } else {
+        let cos_t = (1.0 - (1.0 - cos_o * cos_o) / (eta * eta)).sqrt();
+        let wi = -wo / eta + (cos_o / eta - cos_t) * m;
+        if wi.z >= 0.0 {
+            return ScatterResult::Absorbed;
+        }
+        let cos_i = wi.dot(&m);
+        (
+            wi,
+            (1.0 - reflectance) * normal_pdf * -cos_i / (cos_i + cos_o / eta).powi(2),
+        )
+    };
+    ScatterResult::Scattered {
+        attenuation: Color::new_white() * (ggx.g2(&wo, &wi) / ggx.g1(&wo)),
+        scattered: Ray::new(*point, frame.local(wi.x, wi.y, wi.z)),
+        pdf: Some(pdf),
     }
 }
 
+/// The microfacet BSDF of rough glass times |cos θi|, for reflection into the hemisphere of
+/// the incoming ray and refraction into the other one.
+///
+/// Like smooth glass, refraction does not scale radiance by the squared ratio of the indices,
+/// which cancels for light that enters and leaves an object.
+pub fn dielectric_evaluate(
+    incoming_ray: &Ray,
+    normal: &Vec3,
+    direction: &Vec3,
+    face: Face,
+    ref_idx: f64,
+    roughness: f64,
+) -> BsdfEvaluation {
+    let nothing = BsdfEvaluation {
+        value: Color::new_black(),
+        pdf: 0.0,
+    };
+    let frame = Onb::from_w(normal);
+    let wo = frame.to_local(&-incoming_ray.direction.make_unit_vector());
+    let wi = frame.to_local(&direction.make_unit_vector());
+    if wo.z <= 0.0 || wi.z == 0.0 {
+        return nothing;
+    }
+    let eta = relative_eta(face, ref_idx);
+    let reflected = wi.z > 0.0;
+    // The microfacet normal that turns wo into wi.
+    let m = if reflected { wo + wi } else { wo + eta * wi };
+    if m.squared_length() == 0.0 {
+        return nothing;
+    }
+    let m = if m.z < 0.0 { -m } else { m }.make_unit_vector();
+    let (cos_o, cos_i) = (wo.dot(&m), wi.dot(&m));
+    if cos_o <= 0.0 || (cos_i > 0.0) != reflected {
+        return nothing;
+    }
+
+    let ggx = Ggx::from_roughness(roughness);
+    let reflectance = fresnel_dielectric(cos_o, eta);
+    let d = ggx.d(&m);
+    let (value, pdf) = if reflected {
+        (
+            reflectance * d * ggx.g2(&wo, &wi) / (4.0 * wo.z),
+            reflectance * ggx.g1(&wo) * d / (4.0 * wo.z),
+        )
+    } else {
+        let jacobian = -cos_i / (cos_i + cos_o / eta).powi(2);
+        (
+            (1.0 - reflectance) * d * ggx.g2(&wo, &wi) * cos_o * jacobian / wo.z,
+            (1.0 - reflectance) * ggx.g1(&wo) * d * cos_o * jacobian / wo.z,
+        )
+    };
+    BsdfEvaluation {
+        value: Color::new_white() * value,
+        pdf,
+    }
+}
+
+/// Fresnel reflectance of unpolarized light arriving at an angle with cosine `cos_theta` to the
+/// normal, where `eta` is the index of refraction of the far side over that of the near side.
+pub(crate) fn fresnel_dielectric(cos_theta: f64, eta: f64) -> f64 {
+    let sin2_t = (1.0 - cos_theta * cos_theta) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let parallel = (eta * cos_theta - cos_t) / (eta * cos_theta + cos_t);
+    let perpendicular = (cos_theta - eta * cos_t) / (cos_theta + eta * cos_t);
+    0.5 * (parallel * parallel + perpendicular * perpendicular)
+}
+
 fn refract(uv: &Vec3, n: &Vec3, etai_over_etat: f64) -> Vec3 {
     let cos_theta = -uv.dot(n);
 
@@ -62,3 +207,118 @@ fn schlick(cosine: f64, ref_idx: f64) -> f64 {
     let r0_squared = r0 * r0;
     r0_squared + (1.0 - r0_squared) * (1.0 - cosine).powi(5)
 }
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::{dielectric_evaluate, dielectric_scatter, fresnel_dielectric};
+    use crate::hittable::Face;
+    use crate::material::ScatterResult;
+    use crate::microfacet::tests::hemisphere;
+    use crate::onb::Onb;
+    use crate::ray::Ray;
+    use crate::vec3::Vec3;
+
+    #[test]
+    fn fresnel_reflectance_of_glass() {
+        assert!((fresnel_dielectric(1.0, 1.5) - 0.04).abs() < 1e-12);
+        assert!((fresnel_dielectric(0.0, 1.5) - 1.0).abs() < 1e-12);
+        // Brewster's angle reflects no parallel polarized light.
+        let brewster = 1.5_f64.atan().cos();
+        let cos_t = (1.0 - (1.0 - brewster * brewster) / 2.25).sqrt();
+        let perpendicular = (brewster - 1.5 * cos_t) / (brewster + 1.5 * cos_t);
+        let expected = 0.5 * perpendicular * perpendicular;
+        assert!((fresnel_dielectric(brewster, 1.5) - expected).abs() < 1e-12);
+        // Total internal reflection beyond the critical angle from inside.
+        assert_eq!(fresnel_dielectric(0.7, 1.0 / 1.5), 1.0);
+        assert!(fresnel_dielectric(0.8, 1.0 / 1.5) < 1.0);
+    }
+
+    #[test]
+    fn rough_glass_sampling_agrees_with_evaluation_and_conserves_energy() {
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+        let incoming = Ray::new(Vec3::new(-1.0, 1.0, 0.0), Vec3::new(1.0, -0.6, 0.2));
+        let frame = Onb::from_w(&normal);
+        let mut rng = StdRng::seed_from_u64(12);
+        for &face in &[Face::Outside, Face::Inside] {
+            for &roughness in &[0.3, 0.6] {
+                let samples = 50_000;
+                let (mut reflected, mut transmitted) = (0.0, 0.0);
+                for _ in 0..samples {
+                    if let ScatterResult::Scattered {
+                        attenuation,
+                        scattered,
+                        pdf,
+                    } = dielectric_scatter(
+                        &incoming,
+                        &normal,
+                        &Vec3::origin(),
+                        face,
+                        1.5,
+                        roughness,
+                        &mut rng,
+                    ) {
+                        let direction = scattered.direction;
+                        let evaluation = dielectric_evaluate(
+                            &incoming, &normal, &direction, face, 1.5, roughness,
+                        );
+                        let pdf = pdf.unwrap();
+                        assert!((evaluation.pdf - pdf).abs() < 1e-9 * pdf);
+                        assert!((evaluation.value / pdf - attenuation).length() < 1e-9);
+                        if direction.dot(&normal) > 0.0 {
+                            reflected += attenuation.x / samples as f64;
+                        } else {
+                            transmitted += attenuation.x / samples as f64;
+                        }
+                    }
+                }
+
+                // The same split integrated over both hemispheres.
+                let integrate = |side: f64| -> f64 {
+                    hemisphere()
+                        .map(|(w, dw)| {
+                            let direction = frame.local(w.x, w.y, side * w.z);
+                            let evaluation = dielectric_evaluate(
+                                &incoming, &normal, &direction, face, 1.5, roughness,
+                            );
+                            evaluation.value.x * dw
+                        })
+                        .sum()
+                };
+                let (expected_reflected, expected_transmitted) = (integrate(1.0), integrate(-1.0));
+                assert!((reflected - expected_reflected).abs() < 0.01);
+                assert!((transmitted - expected_transmitted).abs() < 0.01);
+                assert!(expected_reflected + expected_transmitted <= 1.0);
+                // Single scattering loses some light, most at grazing angles from inside.
+                assert!(expected_reflected + expected_transmitted > 0.7);
+                // Seen from outside most light enters; from inside, beyond the critical angle,
+                // most of it is reflected back.
+                match face {
+                    Face::Outside => assert!(expected_transmitted > 3.0 * expected_reflected),
+                    Face::Inside => assert!(expected_reflected > expected_transmitted),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn smooth_glass_is_specular() {
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+        let incoming = Ray::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.3, -1.0, 0.0));
+        let mut rng = StdRng::seed_from_u64(13);
+        for _ in 0..100 {
+            let result = dielectric_scatter(
+                &incoming,
+                &normal,
+                &Vec3::origin(),
+                Face::Outside,
+                1.5,
+                0.0,
+                &mut rng,
+            );
+            assert!(matches!(result, ScatterResult::Scattered { pdf: None, .. }));
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use crate::aabb::Aabb;
+use crate::hittable::{get_face_normal, HitRecord, Hittable};
+use crate::light::Light;
+use crate::material_variants::MaterialVariants;
+use crate::onb::Onb;
+use crate::quad::{area_solid_angle_pdf, intersect_plane};
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::vec3::Vec3;
+
+/// Flat disk facing along `normal`. The surface coordinates are polar: `u` is the angle as a
+/// fraction of a full turn and `v` the distance from the center relative to the radius.
+#[derive(Clone)]
+pub struct Disk {
+    pub center: Vec3,
+    pub radius: f64,
+    pub material: MaterialVariants,
+    basis: Onb,
+}
+
+impl Disk {
+    pub fn new(center: Vec3, normal: Vec3, radius: f64, material: MaterialVariants) -> Disk {
+        Disk {
+            center,
+            radius,
+            material,
+            basis: Onb::from_w(&normal),
+        }
+    }
+
+    pub fn normal(&self) -> Vec3 {
+        self.basis.w
+    }
+
+    pub fn area(&self) -> f64 {
+        std::f64::consts::PI * self.radius * self.radius
+    }
+}
+
+impl Hittable for Disk {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let t = intersect_plane(r, &self.center, &self.basis.w, t_min, t_max)?;
+        let p = r.at(t);
+        let local = self.basis.to_local(&(p - self.center));
+        let distance_squared = local.x * local.x + local.y * local.y;
+        if distance_squared > self.radius * self.radius {
+            return None;
+        }
+        let (face, normal) = get_face_normal(r, &self.basis.w);
+        Some(HitRecord {
+            p,
+            t,
+            u: (local.y.atan2(local.x) + std::f64::consts::PI) / (2.0 * std::f64::consts::PI),
+            v: distance_squared.sqrt() / self.radius,
+            normal,
+            face,
+            material: &self.material,
+        })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        // The extent along each axis is the radius times the sine of the angle to the normal.
+        let n = self.basis.w;
+        let extent = self.radius
+            * Vec3::new(
+                (1.0 - n.x * n.x).max(0.0).sqrt(),
+                (1.0 - n.y * n.y).max(0.0).sqrt(),
+                (1.0 - n.z * n.z).max(0.0).sqrt(),
+            );
+        Some(Aabb::new(self.center - extent, self.center + extent))
+    }
+}
+
+impl Light for Disk {
+    fn sample_direction(&self, origin: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        let p = self.radius * Vec3::random_in_unit_disk(sampler);
+        self.center + self.basis.local(p.x, p.y, 0.0) - *origin
+    }
+
+    fn pdf_value(&self, origin: &Vec3, direction: &Vec3) -> f64 {
+        match self.hit(&Ray::new(*origin, *direction), 0.001, f64::INFINITY) {
+            Some(hit) => area_solid_angle_pdf(direction, &self.basis.w, hit.t, self.area()),
+            None => 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Disk;
+    use crate::color::Color;
+    use crate::hittable::{Face, Hittable};
+    use crate::material_variants::MaterialVariants;
+    use crate::ray::Ray;
+    use crate::vec3::Vec3;
+
+    #[test]
+    fn hit_inside_radius_only() {
+        let disk = Disk::new(
+            Vec3::new(0.0, 0.0, -2.0),
+            Vec3::new(0.0, 0.0, 1.0),
+            1.0,
+            MaterialVariants::Lambertian(Color::new_white().into()),
+        );
+        let bounds = disk.bounding_box().unwrap();
+        assert_eq!(bounds.min, Vec3::new(-1.0, -1.0, -2.0));
+        assert_eq!(bounds.max, Vec3::new(1.0, 1.0, -2.0));
+
+        let r = Ray::new(Vec3::new(0.5, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = disk.hit(&r, 0.0, f64::INFINITY).unwrap();
+        assert_eq!(hit.face, Face::Outside);
+        assert!((hit.v - 0.5).abs() < 1e-12);
+
+        let r = Ray::new(Vec3::new(0.8, 0.8, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(disk.hit(&r, 0.0, f64::INFINITY).is_none());
+    }
+}
@@ -0,0 +1,179 @@
+/// Piecewise-constant density on [0, 1) proportional to `n` non-negative function values, each
+/// covering an interval of width 1/n. Sampling inverts its cumulative distribution.
+#[derive(Debug, Clone)]
+pub struct Distribution1D {
+    function: Vec<f64>,
+    /// `cdf[i]` is the probability of falling below `i / n`; `cdf[n]` is one.
+    cdf: Vec<f64>,
+    /// Integral of the function over [0, 1).
+    integral: f64,
+}
+
+impl Distribution1D {
+    /// A function that is zero everywhere gives the uniform density.
+    pub fn new(function: Vec<f64>) -> Distribution1D {
+        assert!(!function.is_empty());
+        let n = function.len();
+        let mut cdf = Vec::with_capacity(n + 1);
+        cdf.push(0.0);
+        for (i, value) in function.iter().enumerate() {
+            cdf.push(cdf[i] + value.max(0.0) / n as f64);
+        }
+        let integral = cdf[n];
+        for (i, c) in cdf.iter_mut().enumerate() {
+            *c = if integral > 0.0 {
+                *c / integral
+            } else {
+                i as f64 / n as f64
+            };
+        }
+        Distribution1D {
+            function,
+            cdf,
+            integral,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.function.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.function.is_empty()
+    }
+
+    pub fn integral(&self) -> f64 {
+        self.integral
+    }
+
+    /// Maps `u` in [0, 1) to a point in [0, 1) with the distribution, returning the point, its
+    /// density and the index of the interval it lies in.
+    pub fn sample(&self, u: f64) -> (f64, f64, usize) {
+        let n = self.len();
+        let index = (self.cdf.partition_point(|&c| c <= u).max(1) - 1).min(n - 1);
+        let width = self.cdf[index + 1] - self.cdf[index];
+        let offset = if width > 0.0 {
+            (u - self.cdf[index]) / width
+        } else {
+            0.0
+        };
+        let x = ((index as f64 + offset) / n as f64).min(1.0 - f64::EPSILON);
+        (x, self.density(index), index)
+    }
+
+    /// Density at a point in [0, 1).
+    pub fn pdf(&self, x: f64) -> f64 {
+        self.density(self.index(x))
+    }
+
+    fn index(&self, x: f64) -> usize {
+        ((x * self.len() as f64).max(0.0) as usize).min(self.len() - 1)
+    }
+
+    fn density(&self, index: usize) -> f64 {
+        if self.integral > 0.0 {
+            self.function[index].max(0.0) / self.integral
+        } else {
+            1.0
+        }
+    }
+}
+
+/// Piecewise-constant density on [0, 1)² proportional to a `width` × `height` grid of values,
+/// sampled by picking a row from the marginal distribution and then a column from that row.
+#[derive(Debug, Clone)]
+pub struct Distribution2D {
+    rows: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    /// `values` holds the rows one after another; y indexes the rows.
+    pub fn new(width: usize, height: usize, values: &[f64]) -> Distribution2D {
+        assert_eq!(values.len(), width * height);
+        let rows: Vec<_> = values
+            .chunks(width)
+            .map(|row| Distribution1D::new(row.to_vec()))
+            .collect();
+        let marginal = Distribution1D::new(rows.iter().map(Distribution1D::integral).collect());
+        Distribution2D { rows, marginal }
+    }
+
+    /// Maps `(u, v)` in [0, 1)² to a point `(x, y)` with the distribution and its density.
+    pub fn sample(&self, (u, v): (f64, f64)) -> ((f64, f64), f64) {
+        let (y, row_pdf, row) = self.marginal.sample(v);
+        let (x, column_pdf, _) = self.rows[row].sample(u);
+        ((x, y), row_pdf * column_pdf)
+    }
+
+    pub fn pdf(&self, (x, y): (f64, f64)) -> f64 {
+        let row = &self.rows[self.marginal.index(y)];
+        self.marginal.pdf(y) * row.pdf(x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::{Distribution1D, Distribution2D};
+
+    #[test]
+    fn samples_follow_the_function() {
+        let distribution = Distribution1D::new(vec![1.0, 0.0, 3.0, 4.0]);
+        assert_eq!(distribution.integral(), 2.0);
+        assert_eq!(distribution.pdf(0.1), 0.5);
+        assert_eq!(distribution.pdf(0.3), 0.0);
+        assert_eq!(distribution.pdf(0.99), 2.0);
+        // The first eighth of the unit interval maps onto the first quarter.
+        let (x, pdf, index) = distribution.sample(0.0625);
+        assert!((x - 0.125).abs() < 1e-12);
+        assert_eq!((pdf, index), (0.5, 0));
+        let (x, _, index) = distribution.sample(0.125 + 0.375 * 0.5);
+        assert!((x - 0.625).abs() < 1e-12);
+        assert_eq!(index, 2);
+        assert!(distribution.sample(0.999_999).0 < 1.0);
+
+        let uniform = Distribution1D::new(vec![0.0; 3]);
+        assert_eq!(uniform.pdf(0.5), 1.0);
+        assert!((uniform.sample(0.5).0 - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn sampled_densities_integrate_to_one_in_2d() {
+        let (width, height) = (5, 3);
+        let values: Vec<f64> = (0..width * height).map(|i| (i % 4) as f64).collect();
+        let distribution = Distribution2D::new(width, height, &values);
+        let cell =
+            |(x, y): (f64, f64)| (y * height as f64) as usize * width + (x * width as f64) as usize;
+
+        // Each cell's share of the samples matches its share of the values.
+        let total: f64 = values.iter().sum();
+        let mut counts = vec![0; width * height];
+        let mut rng = StdRng::seed_from_u64(8);
+        let samples = 200_000;
+        for _ in 0..samples {
+            let (point, pdf) = distribution.sample((rng.gen(), rng.gen()));
+            assert!(pdf > 0.0);
+            assert!((distribution.pdf(point) - pdf).abs() < 1e-12);
+            counts[cell(point)] += 1;
+        }
+        for (count, value) in counts.iter().zip(&values) {
+            let expected = value / total;
+            assert!((*count as f64 / samples as f64 - expected).abs() < 0.005);
+        }
+
+        let area = 1.0 / (width * height) as f64;
+        let integral: f64 = (0..width * height)
+            .map(|i| {
+                let center = (
+                    ((i % width) as f64 + 0.5) / width as f64,
+                    ((i / width) as f64 + 0.5) / height as f64,
+                );
+                distribution.pdf(center) * area
+            })
+            .sum();
+        assert!((integral - 1.0).abs() < 1e-12);
+    }
+}
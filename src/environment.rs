@@ -0,0 +1,430 @@
+use std::f64::consts::PI;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::color::Color;
+use crate::daylight::Daylight;
+use crate::distribution::Distribution2D;
+use crate::sampler::Sampler;
+use crate::vec3::Vec3;
+
+/// Radiance arriving from infinitely far away along rays that leave the scene without hitting
+/// anything.
+#[derive(Debug, Clone, Default)]
+pub enum Environment {
+    /// White at the horizon blending into light blue overhead.
+    #[default]
+    Gradient,
+    Constant(Color),
+    /// A high dynamic range image of the surroundings, importance sampled as a light.
+    Image(Arc<EnvironmentMap>),
+    /// Analytic sky and sun for a given time of day, importance sampled as a light.
+    Daylight(Arc<Daylight>),
+}
+
+impl Environment {
+    /// Radiance arriving from `direction`, which does not need to be normalized.
+    pub fn radiance(&self, direction: &Vec3) -> Color {
+        match self {
+            Environment::Gradient => gradient(direction),
+            Environment::Constant(color) => *color,
+            Environment::Image(map) => map.radiance(direction),
+            Environment::Daylight(daylight) => daylight.radiance(direction),
+        }
+    }
+
+    /// Whether next-event estimation samples the environment alongside the lights. Gradients
+    /// and constants are smooth enough to be found by following the scattered rays.
+    pub fn is_sampled(&self) -> bool {
+        matches!(self, Environment::Image(_) | Environment::Daylight(_))
+    }
+
+    /// Picks a direction towards the environment, `None` if it is not sampled.
+    pub fn sample_direction(&self, sampler: &mut dyn Sampler) -> Option<Vec3> {
+        match self {
+            Environment::Image(map) => Some(map.sample_direction(sampler)),
+            Environment::Daylight(daylight) => Some(daylight.sample_direction(sampler)),
+            _ => None,
+        }
+    }
+
+    /// Solid angle density with which [`Environment::sample_direction`] returns `direction`.
+    pub fn pdf_value(&self, direction: &Vec3) -> f64 {
+        match self {
+            Environment::Image(map) => map.pdf_value(direction),
+            Environment::Daylight(daylight) => daylight.pdf_value(direction),
+            _ => 0.0,
+        }
+    }
+}
+
+fn gradient(direction: &Vec3) -> Color {
+    let unit_direction = direction.make_unit_vector();
+    let t = 0.5 * unit_direction.y + 1.0_f64;
+    (1.0_f64 - t) * Color::new_white() + t * Color::new(0.5, 0.7, 1.0)
+}
+
+/// An equirectangular (latitude-longitude) environment image.
+///
+/// Rows run from straight up (+y) at the top to straight down at the bottom. Columns run around
+/// the vertical axis with the center of the image looking down -z and its right quarter down
+/// +x. Directions are sampled from a piecewise-constant distribution over the pixels that is
+/// proportional to their luminance and the solid angle they cover, so that small bright
+/// features such as the sun are found by light sampling.
+#[derive(Debug)]
+pub struct EnvironmentMap {
+    pub width: usize,
+    pub height: usize,
+    /// Rows from top to bottom.
+    pixels: Vec<Color>,
+    /// Rotation of the image around the vertical axis in radians, counterclockwise seen from
+    /// above.
+    rotation: f64,
+    distribution: Distribution2D,
+}
+
+impl EnvironmentMap {
+    pub fn from_pixels(width: usize, height: usize, pixels: Vec<Color>) -> EnvironmentMap {
+        assert!(width > 0 && height > 0);
+        assert_eq!(pixels.len(), width * height);
+        let weights: Vec<f64> = pixels
+            .iter()
+            .enumerate()
+            .map(|(i, pixel)| {
+                let theta = PI * ((i / width) as f64 + 0.5) / height as f64;
+                pixel.luminance().max(0.0) * theta.sin()
+            })
+            .collect();
+        EnvironmentMap {
+            width,
+            height,
+            pixels,
+            rotation: 0.0,
+            distribution: Distribution2D::new(width, height, &weights),
+        }
+    }
+
+    /// Loads a Radiance RGBE (.hdr) file.
+    pub fn load<P: AsRef<Path>>(path: P) -> std::io::Result<EnvironmentMap> {
+        let (width, height, pixels) = read_hdr(BufReader::new(File::open(path)?))?;
+        Ok(EnvironmentMap::from_pixels(width, height, pixels))
+    }
+
+    /// Turns the image by `degrees` around the vertical axis, counterclockwise seen from above.
+    pub fn with_rotation(mut self, degrees: f64) -> EnvironmentMap {
+        self.rotation = degrees.to_radians();
+        self
+    }
+
+    /// Scales the radiance of every pixel.
+    pub fn with_intensity(mut self, intensity: f64) -> EnvironmentMap {
+        for pixel in &mut self.pixels {
+            *pixel *= intensity;
+        }
+        self
+    }
+
+    pub fn radiance(&self, direction: &Vec3) -> Color {
+        let (u, v) = self.image_coordinates(direction);
+        let column = ((u * self.width as f64) as usize).min(self.width - 1);
+        let row = ((v * self.height as f64) as usize).min(self.height - 1);
+        self.pixels[row * self.width + column]
+    }
+
+    pub fn sample_direction(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        let ((u, v), _) = self.distribution.sample(sampler.get_2d());
+        lat_long_direction((u - self.rotation / (2.0 * PI), v))
+    }
+
+    pub fn pdf_value(&self, direction: &Vec3) -> f64 {
+        let (u, v) = self.image_coordinates(direction);
+        lat_long_to_solid_angle(self.distribution.pdf((u, v)), v)
+    }
+
+    /// Position of `direction` in the image, both coordinates in [0, 1].
+    fn image_coordinates(&self, direction: &Vec3) -> (f64, f64) {
+        let (u, v) = lat_long_coordinates(direction);
+        let u = u + self.rotation / (2.0 * PI);
+        (u - u.floor(), v)
+    }
+}
+
+/// Latitude-longitude position of `direction`, both coordinates in [0, 1]: u runs once around
+/// the vertical axis, from +z over -x to -z at one half and on over +x, and v runs from straight
+/// up to straight down.
+pub(crate) fn lat_long_coordinates(direction: &Vec3) -> (f64, f64) {
+    let unit = direction.make_unit_vector();
+    let u = unit.x.atan2(-unit.z) / (2.0 * PI) + 0.5;
+    let v = unit.y.clamp(-1.0, 1.0).acos() / PI;
+    (u - u.floor(), v)
+}
+
+/// The unit direction at a latitude-longitude position, see [`lat_long_coordinates`].
+pub(crate) fn lat_long_direction((u, v): (f64, f64)) -> Vec3 {
+    let phi = 2.0 * PI * (u - 0.5);
+    let theta = PI * v;
+    Vec3::new(
+        theta.sin() * phi.sin(),
+        theta.cos(),
+        -theta.sin() * phi.cos(),
+    )
+}
+
+/// Converts a density over latitude-longitude positions to solid angle: a unit area of
+/// positions at polar angle θ = πv spans 2π² sin θ steradians.
+pub(crate) fn lat_long_to_solid_angle(pdf: f64, v: f64) -> f64 {
+    let sin_theta = (PI * v).sin();
+    if sin_theta <= 0.0 {
+        return 0.0;
+    }
+    pdf / (2.0 * PI * PI * sin_theta)
+}
+
+/// Largest HDR image `read_hdr` accepts, a 16K latitude-longitude map, so that a corrupt header
+/// cannot make it allocate without bound.
+const MAX_HDR_PIXELS: usize = 16384 * 8192;
+
+fn invalid_data(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
+
+/// Reads a Radiance RGBE image with the usual `-Y height +X width` orientation, whose scanlines
+/// may be flat or run-length encoded. Returns the width, the height and the pixels row by row
+/// from the top.
+pub fn read_hdr<R: BufRead>(mut reader: R) -> std::io::Result<(usize, usize, Vec<Color>)> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    if !line.starts_with("#?") {
+        return Err(invalid_data("not a Radiance HDR file"));
+    }
+    // Header variables up to an empty line, then the resolution.
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(invalid_data("truncated HDR header"));
+        }
+        let variable = line.trim();
+        if variable.is_empty() {
+            break;
+        }
+        if let Some(format) = variable.strip_prefix("FORMAT=") {
+            if format != "32-bit_rle_rgbe" {
+                return Err(invalid_data("unsupported HDR pixel format"));
+            }
+        }
+    }
+    line.clear();
+    reader.read_line(&mut line)?;
+    let (height, width) = match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+        ["-Y", height, "+X", width] => match (height.parse::<usize>(), width.parse::<usize>()) {
+            (Ok(height), Ok(width)) => (height, width),
+            _ => return Err(invalid_data("invalid HDR resolution")),
+        },
+        _ => return Err(invalid_data("unsupported HDR orientation")),
+    };
+    let pixel_count = width
+        .checked_mul(height)
+        .filter(|&count| count > 0 && count <= MAX_HDR_PIXELS)
+        .ok_or_else(|| invalid_data("unsupported HDR resolution"))?;
+
+    let mut pixels = Vec::with_capacity(pixel_count);
+    let mut scanline = vec![0_u8; 4 * width];
+    for _ in 0..height {
+        read_scanline(&mut reader, &mut scanline)?;
+        pixels.extend(scanline.chunks(4).map(rgbe_to_color));
+    }
+    Ok((width, height, pixels))
+}
+
+/// Reads one scanline of RGBE pixels into `scanline`.
+fn read_scanline<R: Read>(reader: &mut R, scanline: &mut [u8]) -> std::io::Result<()> {
+    let width = scanline.len() / 4;
+    let mut start = [0_u8; 4];
+    reader.read_exact(&mut start)?;
+    let run_length_encoded =
+        (8..0x8000).contains(&width) && start[0] == 2 && start[1] == 2 && start[2] < 0x80;
+    if !run_length_encoded {
+        scanline[..4].copy_from_slice(&start);
+        return reader.read_exact(&mut scanline[4..]);
+    }
+    if ((start[2] as usize) << 8 | start[3] as usize) != width {
+        return Err(invalid_data("HDR scanline width mismatch"));
+    }
+    // The four channels follow one after another, each as runs of a repeated byte and literal
+    // byte sequences.
+    let mut channel = vec![0_u8; width];
+    for c in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let mut count = [0_u8; 1];
+            reader.read_exact(&mut count)?;
+            let (repeated, count) = if count[0] > 128 {
+                (true, count[0] as usize - 128)
+            } else {
+                (false, count[0] as usize)
+            };
+            if count == 0 || x + count > width {
+                return Err(invalid_data("invalid HDR run length"));
+            }
+            if repeated {
+                let mut value = [0_u8; 1];
+                reader.read_exact(&mut value)?;
+                channel[x..x + count].iter_mut().for_each(|b| *b = value[0]);
+            } else {
+                reader.read_exact(&mut channel[x..x + count])?;
+            }
+            x += count;
+        }
+        for (pixel, value) in scanline.chunks_mut(4).zip(&channel) {
+            pixel[c] = *value;
+        }
+    }
+    Ok(())
+}
+
+/// Decodes a shared-exponent RGBE pixel, taking the middle of each mantissa step.
+fn rgbe_to_color(rgbe: &[u8]) -> Color {
+    if rgbe[3] == 0 {
+        return Color::new_black();
+    }
+    let scale = 2.0_f64.powi(rgbe[3] as i32 - 136);
+    Color::new(
+        (rgbe[0] as f64 + 0.5) * scale,
+        (rgbe[1] as f64 + 0.5) * scale,
+        (rgbe[2] as f64 + 0.5) * scale,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+    use std::io::ErrorKind;
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::{read_hdr, EnvironmentMap};
+    use crate::color::Color;
+    use crate::framebuffer::Framebuffer;
+    use crate::output::write_hdr;
+    use crate::vec3::Vec3;
+
+    #[test]
+    fn reads_flat_and_run_length_encoded_scanlines() {
+        let pixels = vec![
+            Color::new(1.0, 0.5, 0.25),
+            Color::new_black(),
+            Color::new(100.0, 2.0, 0.0),
+            Color::new(0.01, 0.02, 0.03),
+        ];
+        let mut bytes = Vec::new();
+        write_hdr(&Framebuffer::from_pixels(2, 2, pixels.clone()), &mut bytes).unwrap();
+        let (width, height, read) = read_hdr(bytes.as_slice()).unwrap();
+        assert_eq!((width, height), (2, 2));
+        for (a, b) in pixels.iter().zip(&read) {
+            assert!((*a - *b).length() <= 0.01 * a.length());
+        }
+
+        // Eight pixels, each channel as one run or as literals.
+        let mut bytes = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 8\n".to_vec();
+        bytes.extend_from_slice(&[2, 2, 0, 8]);
+        bytes.extend_from_slice(&[128 + 8, 128]);
+        bytes.extend_from_slice(&[8, 0, 16, 32, 48, 64, 80, 96, 112]);
+        bytes.extend_from_slice(&[128 + 4, 0, 128 + 4, 255]);
+        bytes.extend_from_slice(&[128 + 8, 129]);
+        let (width, height, read) = read_hdr(bytes.as_slice()).unwrap();
+        assert_eq!((width, height), (8, 1));
+        assert_eq!(read[0], Color::new(128.5, 0.5, 0.5) / 128.0);
+        assert_eq!(read[7], Color::new(128.5, 112.5, 255.5) / 128.0);
+
+        assert!(read_hdr(&b"P6\n"[..]).is_err());
+    }
+
+    #[test]
+    fn rejects_empty_and_huge_resolutions() {
+        for resolution in &[
+            "-Y 0 +X 8",
+            "-Y 100000 +X 100000",
+            "-Y 4294967296 +X 4294967296",
+        ] {
+            let header = format!("#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n{}\n", resolution);
+            let error = read_hdr(header.as_bytes()).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidData, "{}", resolution);
+        }
+    }
+
+    #[test]
+    fn directions_map_onto_the_image() {
+        // Four columns: behind (+z), left (-x), front (-z) and right (+x) of the viewer.
+        let colors = [
+            Color::new_red(),
+            Color::new_green(),
+            Color::new_blue(),
+            Color::new_white(),
+        ];
+        let map = EnvironmentMap::from_pixels(4, 1, colors.to_vec());
+        assert_eq!(map.radiance(&Vec3::new(0.0, 0.0, -1.0)), colors[2]);
+        assert_eq!(map.radiance(&Vec3::new(1.0, 0.1, 0.0)), colors[3]);
+        assert_eq!(map.radiance(&Vec3::new(-1.0, -0.1, 0.0)), colors[1]);
+        assert_eq!(map.radiance(&Vec3::new(-0.01, 0.0, 1.0)), colors[0]);
+        let rotated = EnvironmentMap::from_pixels(4, 1, colors.to_vec()).with_rotation(90.0);
+        assert_eq!(rotated.radiance(&Vec3::new(-1.0, 0.0, 0.0)), colors[2]);
+    }
+
+    #[test]
+    fn sampling_prefers_the_sun_and_matches_the_density() {
+        let (width, height) = (16, 8);
+        let mut pixels = vec![Color::new(0.1, 0.1, 0.1); width * height];
+        pixels[2 * width + 5] = Color::new(1000.0, 1000.0, 1000.0);
+        let map = EnvironmentMap::from_pixels(width, height, pixels)
+            .with_rotation(30.0)
+            .with_intensity(2.0);
+
+        let mut rng = StdRng::seed_from_u64(5);
+        let samples = 20_000;
+        let mut towards_sun = 0;
+        let mut estimate = 0.0;
+        for _ in 0..samples {
+            let direction = map.sample_direction(&mut rng);
+            assert!((direction.length() - 1.0).abs() < 1e-12);
+            let radiance = map.radiance(&direction).x;
+            if radiance > 1000.0 {
+                towards_sun += 1;
+            }
+            estimate += radiance / map.pdf_value(&direction) / samples as f64;
+        }
+        assert!(towards_sun > samples * 9 / 10);
+
+        // The radiance integrated over the sphere, pixel by pixel.
+        let expected: f64 = (0..width * height)
+            .map(|i| {
+                let row = (i / width) as f64;
+                let band =
+                    (PI * row / height as f64).cos() - (PI * (row + 1.0) / height as f64).cos();
+                map.pixels[i].x * band * 2.0 * PI / width as f64
+            })
+            .sum();
+        assert!(
+            (estimate - expected).abs() < 0.01 * expected,
+            "{} {}",
+            estimate,
+            expected
+        );
+
+        // Uniform directions estimate the same integral through the density.
+        let mut integral = 0.0;
+        let steps = 400;
+        for i in 0..steps {
+            for j in 0..steps {
+                let z = 1.0 - 2.0 * (i as f64 + 0.5) / steps as f64;
+                let phi = 2.0 * PI * (j as f64 + 0.5) / steps as f64;
+                let r = (1.0 - z * z).sqrt();
+                let direction = Vec3::new(r * phi.cos(), r * phi.sin(), z);
+                integral += map.pdf_value(&direction) * 4.0 * PI / (steps * steps) as f64;
+            }
+        }
+        assert!((integral - 1.0).abs() < 0.01, "{}", integral);
+    }
+}
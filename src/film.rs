@@ -0,0 +1,200 @@
+use std::ops::Range;
+
+use crate::color::Color;
+use crate::filter::Filter;
+use crate::framebuffer::Framebuffer;
+
+/// Accumulates radiance samples into pixels with a reconstruction [`Filter`].
+///
+/// Positions are in continuous pixel coordinates: x runs to the right and y downwards, and the
+/// pixel in column `i` and row `j` covers [i, i + 1) × [j, j + 1). Every sample is splatted into
+/// all pixels whose center lies within the filter radius, weighted by the filter, and each pixel
+/// is divided by the sum of its weights in the end.
+///
+/// Samples are usually gathered in [`FilmTile`]s in parallel and merged afterwards.
+pub struct Film {
+    pub width: usize,
+    pub height: usize,
+    pub filter: Filter,
+    sums: Vec<Color>,
+    weights: Vec<f64>,
+    /// Number of samples taken in each pixel, wherever they were splatted.
+    samples: Vec<u32>,
+}
+
+impl Film {
+    pub fn new(width: usize, height: usize, filter: Filter) -> Film {
+        Film {
+            width,
+            height,
+            filter,
+            sums: vec![Color::new_black(); width * height],
+            weights: vec![0.0; width * height],
+            samples: vec![0; width * height],
+        }
+    }
+
+    /// Empty tile for the samples taken in the pixels `columns` × `rows`, covering all pixels
+    /// they can reach.
+    pub fn tile(&self, columns: Range<usize>, rows: Range<usize>) -> FilmTile {
+        // Number of pixels beyond the range whose centers a sample inside it can reach.
+        let reach = (self.filter.radius + 0.5).floor() as usize;
+        let x0 = columns.start.saturating_sub(reach);
+        let y0 = rows.start.saturating_sub(reach);
+        let x1 = (columns.end + reach).min(self.width);
+        let y1 = (rows.end + reach).min(self.height);
+        let size = (x1 - x0) * (y1 - y0);
+        FilmTile {
+            x0,
+            y0,
+            width: x1 - x0,
+            height: y1 - y0,
+            filter: self.filter,
+            sums: vec![Color::new_black(); size],
+            weights: vec![0.0; size],
+            samples: vec![0; size],
+        }
+    }
+
+    /// Adds the samples of `tile`. Merging the tiles in a fixed order gives the same image
+    /// however they were rendered.
+    pub fn merge(&mut self, tile: FilmTile) {
+        for y in 0..tile.height {
+            for x in 0..tile.width {
+                let index = (tile.y0 + y) * self.width + tile.x0 + x;
+                self.sums[index] += tile.sums[y * tile.width + x];
+                self.weights[index] += tile.weights[y * tile.width + x];
+                self.samples[index] += tile.samples[y * tile.width + x];
+            }
+        }
+    }
+
+    /// The filtered image. Pixels without any weight are black, and negative values that
+    /// filters with negative lobes can produce next to bright edges are clamped to zero.
+    pub fn to_framebuffer(&self) -> Framebuffer {
+        let pixels = self
+            .sums
+            .iter()
+            .zip(&self.weights)
+            .map(|(sum, weight)| {
+                if *weight == 0.0 {
+                    return Color::new_black();
+                }
+                let color = *sum / *weight;
+                Color::new(color.x.max(0.0), color.y.max(0.0), color.z.max(0.0))
+            })
+            .collect();
+        Framebuffer::from_pixels(self.width, self.height, pixels)
+    }
+
+    pub fn sample_counts(&self) -> &[u32] {
+        &self.samples
+    }
+
+    /// Grey image of the number of samples taken in each pixel, white for `max_samples`.
+    pub fn sample_count_image(&self, max_samples: usize) -> Framebuffer {
+        let pixels = self
+            .samples
+            .iter()
+            .map(|&count| Color::new_white() * (count as f64 / max_samples as f64))
+            .collect();
+        Framebuffer::from_pixels(self.width, self.height, pixels)
+    }
+}
+
+/// A part of a [`Film`] that samples can be added to independently of the rest.
+pub struct FilmTile {
+    x0: usize,
+    y0: usize,
+    width: usize,
+    height: usize,
+    filter: Filter,
+    sums: Vec<Color>,
+    weights: Vec<f64>,
+    samples: Vec<u32>,
+}
+
+impl FilmTile {
+    /// Splats `color` sampled at `(x, y)` into the pixels around it. Pixels outside the tile,
+    /// which lie outside the image, are skipped. The sample must lie within the pixels the tile
+    /// was made for.
+    pub fn add_sample(&mut self, (x, y): (f64, f64), color: Color) {
+        let (column, row) = (x.floor() as usize, y.floor() as usize);
+        self.samples[(row - self.y0) * self.width + (column - self.x0)] += 1;
+
+        let radius = self.filter.radius;
+        // Pixels in `start..end` whose center `i + 0.5` lies within the radius of `position`.
+        let reached = |position: f64, start: usize, end: usize| {
+            let first = (position - 0.5 - radius).ceil().max(start as f64);
+            let last = (position - 0.5 + radius).floor().min(end as f64 - 1.0);
+            first as usize..(last + 1.0).max(first) as usize
+        };
+        for j in reached(y, self.y0, self.y0 + self.height) {
+            for i in reached(x, self.x0, self.x0 + self.width) {
+                let weight = self.filter.evaluate(i as f64 + 0.5 - x, j as f64 + 0.5 - y);
+                let index = (j - self.y0) * self.width + (i - self.x0);
+                self.sums[index] += weight * color;
+                self.weights[index] += weight;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Film;
+    use crate::color::Color;
+    use crate::filter::{Filter, FilterKind, FILTERS};
+
+    #[test]
+    fn box_filter_averages_the_samples_of_each_pixel() {
+        let mut film = Film::new(3, 2, Filter::default());
+        let mut tile = film.tile(0..3, 0..2);
+        tile.add_sample((1.25, 0.5), Color::new(1.0, 0.0, 0.0));
+        tile.add_sample((1.75, 0.25), Color::new(0.0, 1.0, 0.0));
+        tile.add_sample((2.5, 1.5), Color::new(0.0, 0.0, 1.0));
+        film.merge(tile);
+        let framebuffer = film.to_framebuffer();
+        assert_eq!(framebuffer.get(1, 0), Color::new(0.5, 0.5, 0.0));
+        assert_eq!(framebuffer.get(2, 1), Color::new(0.0, 0.0, 1.0));
+        assert_eq!(framebuffer.get(0, 0), Color::new_black());
+        assert_eq!(film.sample_counts(), &[0, 2, 0, 0, 0, 1]);
+        assert_eq!(film.sample_count_image(2).get(1, 0), Color::new_white());
+    }
+
+    #[test]
+    fn wide_filters_reach_neighboring_tiles_and_keep_flat_images_flat() {
+        for name in &FILTERS {
+            let kind: FilterKind = name.parse().unwrap();
+            let mut film = Film::new(8, 6, Filter::new(kind, kind.default_radius()));
+            // One tile per row, each with a sample in the middle of every pixel.
+            let tiles: Vec<_> = (0..6)
+                .map(|row| {
+                    let mut tile = film.tile(0..8, row..row + 1);
+                    for column in 0..8 {
+                        let position = (column as f64 + 0.5, row as f64 + 0.5);
+                        tile.add_sample(position, Color::new(0.25, 0.5, 1.0));
+                    }
+                    tile
+                })
+                .collect();
+            for tile in tiles {
+                film.merge(tile);
+            }
+            for color in film.to_framebuffer().pixels {
+                assert!(
+                    (color - Color::new(0.25, 0.5, 1.0)).length() < 1e-12,
+                    "{}",
+                    name
+                );
+            }
+        }
+
+        // A single sample on a pixel border is shared by the pixels on both sides.
+        let mut film = Film::new(4, 1, Filter::new(FilterKind::Tent, 1.0));
+        let mut tile = film.tile(2..3, 0..1);
+        tile.add_sample((2.0, 0.5), Color::new_white());
+        film.merge(tile);
+        assert_eq!(film.weights, vec![0.0, 0.5, 0.5, 0.0]);
+    }
+}
@@ -0,0 +1,150 @@
+use std::str::FromStr;
+
+pub const FILTERS: [&str; 4] = ["box", "tent", "gaussian", "mitchell"];
+
+/// Shape of a pixel reconstruction filter, see [`Filter`].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FilterKind {
+    /// Constant over the whole radius.
+    #[default]
+    Box,
+    /// Falls off linearly to zero at the radius.
+    Tent,
+    /// Gaussian with a standard deviation of a third of the radius, shifted down to reach zero
+    /// at the radius.
+    Gaussian,
+    /// Mitchell–Netravali cubic with B = C = 1/3, stretched over the radius. Its negative lobes
+    /// sharpen edges slightly.
+    Mitchell,
+}
+
+impl FilterKind {
+    /// Radius at which the filter usually is used.
+    pub fn default_radius(self) -> f64 {
+        match self {
+            FilterKind::Box => 0.5,
+            FilterKind::Tent => 1.0,
+            FilterKind::Gaussian => 1.5,
+            FilterKind::Mitchell => 2.0,
+        }
+    }
+}
+
+impl FromStr for FilterKind {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<FilterKind, String> {
+        match name {
+            "box" => Ok(FilterKind::Box),
+            "tent" => Ok(FilterKind::Tent),
+            "gaussian" => Ok(FilterKind::Gaussian),
+            "mitchell" => Ok(FilterKind::Mitchell),
+            _ => Err(format!("unknown filter `{}`", name)),
+        }
+    }
+}
+
+/// Separable pixel reconstruction filter: the weight of a sample in a pixel, as a function of
+/// their offset in pixels. Samples further away than `radius` in x or y do not count.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Filter {
+    pub kind: FilterKind,
+    pub radius: f64,
+}
+
+impl Filter {
+    pub fn new(kind: FilterKind, radius: f64) -> Filter {
+        Filter { kind, radius }
+    }
+
+    /// Weight of a sample offset by `(x, y)` pixels from the pixel center.
+    pub fn evaluate(&self, x: f64, y: f64) -> f64 {
+        self.evaluate_1d(x) * self.evaluate_1d(y)
+    }
+
+    fn evaluate_1d(&self, x: f64) -> f64 {
+        let x = x.abs();
+        if x > self.radius {
+            return 0.0;
+        }
+        match self.kind {
+            FilterKind::Box => 1.0,
+            FilterKind::Tent => 1.0 - x / self.radius,
+            FilterKind::Gaussian => {
+                let sigma = self.radius / 3.0;
+                let gaussian = |x: f64| (-x * x / (2.0 * sigma * sigma)).exp();
+                gaussian(x) - gaussian(self.radius)
+            }
+            FilterKind::Mitchell => mitchell(2.0 * x / self.radius, 1.0 / 3.0, 1.0 / 3.0),
+        }
+    }
+}
+
+impl Default for Filter {
+    /// A box filter exactly one pixel wide, which averages the samples of each pixel.
+    fn default() -> Filter {
+        Filter::new(FilterKind::Box, FilterKind::Box.default_radius())
+    }
+}
+
+/// The Mitchell–Netravali cubic for `|x| < 2`.
+fn mitchell(x: f64, b: f64, c: f64) -> f64 {
+    let x = x.abs();
+    let value = if x < 1.0 {
+        (12.0 - 9.0 * b - 6.0 * c) * x * x * x
+            + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+            + (6.0 - 2.0 * b)
+    } else if x < 2.0 {
+        (-b - 6.0 * c) * x * x * x
+            + (6.0 * b + 30.0 * c) * x * x
+            + (-12.0 * b - 48.0 * c) * x
+            + (8.0 * b + 24.0 * c)
+    } else {
+        0.0
+    };
+    value / 6.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Filter, FilterKind, FILTERS};
+
+    #[test]
+    fn filters_peak_at_the_center_and_vanish_at_the_radius() {
+        for name in &FILTERS {
+            let kind: FilterKind = name.parse().unwrap();
+            let filter = Filter::new(kind, kind.default_radius());
+            let center = filter.evaluate(0.0, 0.0);
+            assert!(center > 0.0, "{}", name);
+            for &x in &[0.1, 0.3, 0.49, 0.8, 1.4, 1.9] {
+                assert!(filter.evaluate(x, 0.0) <= center, "{}", name);
+                assert_eq!(filter.evaluate(x, 0.0), filter.evaluate(-x, 0.0));
+                assert_eq!(filter.evaluate(x, 0.2), filter.evaluate(0.2, x));
+            }
+            if kind != FilterKind::Box {
+                assert!(
+                    filter.evaluate(filter.radius, 0.0).abs() < 1e-12,
+                    "{}",
+                    name
+                );
+            }
+            assert_eq!(filter.evaluate(0.0, filter.radius + 0.01), 0.0);
+        }
+        assert_eq!(Filter::default().evaluate(0.3, -0.4), 1.0);
+    }
+
+    #[test]
+    fn mitchell_has_negative_lobes_and_unit_area() {
+        let filter = Filter::new(FilterKind::Mitchell, 2.0);
+        assert!(filter.evaluate(1.5, 0.0) < 0.0);
+        // The cubic integrates to one over its support, and so does the product.
+        let steps = 400;
+        let coordinate = |i: usize| -2.0 + 4.0 * (i as f64 + 0.5) / steps as f64;
+        let area: f64 = (0..steps * steps)
+            .map(|i| filter.evaluate(coordinate(i % steps), coordinate(i / steps)))
+            .sum::<f64>()
+            * 16.0
+            / (steps * steps) as f64;
+        assert!((area - 1.0).abs() < 1e-4, "{}", area);
+    }
+}
@@ -0,0 +1,67 @@
+use std::path::Path;
+
+use crate::color::Color;
+use crate::output::write_image;
+
+/// Linear radiance values of a rendered image, stored row by row from top to bottom.
+pub struct Framebuffer {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Color>,
+}
+
+impl Framebuffer {
+    pub fn new(width: usize, height: usize) -> Framebuffer {
+        Framebuffer {
+            width,
+            height,
+            pixels: vec![Color::new_black(); width * height],
+        }
+    }
+
+    pub fn from_pixels(width: usize, height: usize, pixels: Vec<Color>) -> Framebuffer {
+        assert_eq!(pixels.len(), width * height);
+        Framebuffer {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Returns the pixel in column `x` and row `y`, counting rows from the top.
+    pub fn get(&self, x: usize, y: usize) -> Color {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, color: Color) {
+        self.pixels[y * self.width + x] = color;
+    }
+
+    /// Gamma corrects and quantizes the pixels to interleaved 8-bit RGB.
+    pub fn to_rgb8(&self) -> Vec<u8> {
+        self.pixels
+            .iter()
+            .flat_map(|p| p.gamma_correction(2.0).as_bytes().to_vec())
+            .collect()
+    }
+
+    /// Writes the image to `path`, see [`write_image`] for the supported formats.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        write_image(self, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Framebuffer;
+    use crate::color::Color;
+
+    #[test]
+    fn row_major_from_top() {
+        let mut framebuffer = Framebuffer::new(3, 2);
+        framebuffer.set(2, 1, Color::new_red());
+        assert_eq!(framebuffer.pixels[5], Color::new_red());
+        assert_eq!(framebuffer.get(2, 1), Color::new_red());
+        assert_eq!(framebuffer.get(0, 0), Color::new_black());
+    }
+}
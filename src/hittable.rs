@@ -1,24 +1,92 @@
+use crate::aabb::Aabb;
 use crate::material_variants::MaterialVariants;
 use crate::ray::Ray;
+use crate::sample_rng::SampleRng;
 use crate::vec3::Vec3;
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Face {
     Inside,
     Outside,
 }
 
-pub struct HitRecord {
+pub struct HitRecord<'a> {
     pub p: Vec3,
+    /// Faces the side the ray came from; inside a participating medium it is just the reversed
+    /// ray direction.
     pub normal: Vec3,
     pub t: f64,
+    /// Surface coordinates for texture lookups.
+    pub u: f64,
+    pub v: f64,
     pub face: Face,
-    pub material: MaterialVariants,
+    pub material: &'a MaterialVariants,
+}
+
+/// Orients `outward_normal` against the ray and reports which side of the surface was hit.
+#[inline]
+pub fn get_face_normal(r: &Ray, outward_normal: &Vec3) -> (Face, Vec3) {
+    let face = if r.direction.dot(outward_normal) < 0.0 {
+        Face::Outside
+    } else {
+        Face::Inside
+    };
+    let normal = match face {
+        Face::Outside => *outward_normal,
+        Face::Inside => -(*outward_normal),
+    };
+    (face, normal)
 }
 
 pub trait Hittable: Sync {
-    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord>;
+    /// Closest surface hit between `t_min` and `t_max`.
+    ///
+    /// Participating media scatter at random distances and have no generator to draw them from
+    /// here, so they are transparent to `hit`. Anything that should see media, like the
+    /// renderer's camera and shadow rays, has to trace with `sample_hit` instead.
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>>;
+
+    /// Like `hit`, but participating media draw their free flights from `rng`. Containers
+    /// forward it to their objects.
+    ///
+    /// How many numbers are drawn, and in which order, depends on the objects along the ray, so
+    /// `rng` should be a generator for this ray alone rather than the pixel's sampler, whose
+    /// dimensions would otherwise shift with the scene layout.
+    fn sample_hit(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        _rng: &mut SampleRng,
+    ) -> Option<HitRecord<'_>> {
+        self.hit(r, t_min, t_max)
+    }
+
+    /// Box enclosing the object, or `None` if it is unbounded (e.g. an infinite plane).
+    fn bounding_box(&self) -> Option<Aabb>;
+}
+
+impl<T: Hittable + ?Sized> Hittable for Box<T> {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        (**self).hit(r, t_min, t_max)
+    }
+
+    fn sample_hit(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        rng: &mut SampleRng,
+    ) -> Option<HitRecord<'_>> {
+        (**self).sample_hit(r, t_min, t_max, rng)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        (**self).bounding_box()
+    }
 }
 
+#[derive(Default)]
 pub struct HittableList {
     pub objects: Vec<Box<dyn Hittable>>,
 }
@@ -38,14 +106,16 @@ impl HittableList {
     pub fn add(&mut self, object: Box<dyn Hittable>) {
         self.objects.push(object);
     }
-}
 
-impl Hittable for HittableList {
-    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    /// Closest of the hits `hit_object` finds on each object before the given `t_max`.
+    fn closest_hit<'a, F>(&'a self, t_max: f64, mut hit_object: F) -> Option<HitRecord<'a>>
+    where
+        F: FnMut(&'a dyn Hittable, f64) -> Option<HitRecord<'a>>,
+    {
         let mut res: Option<HitRecord> = None;
         let mut closest_so_far = t_max;
         for object in &self.objects {
-            if let Some(current_hit) = object.hit(r, t_min, closest_so_far) {
+            if let Some(current_hit) = hit_object(object.as_ref(), closest_so_far) {
                 closest_so_far = current_hit.t;
                 res = Some(current_hit);
             }
@@ -53,3 +123,29 @@ impl Hittable for HittableList {
         res
     }
 }
+
+impl Hittable for HittableList {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        self.closest_hit(t_max, |object, t_max| object.hit(r, t_min, t_max))
+    }
+
+    fn sample_hit(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        rng: &mut SampleRng,
+    ) -> Option<HitRecord<'_>> {
+        self.closest_hit(t_max, |object, t_max| {
+            object.sample_hit(r, t_min, t_max, rng)
+        })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.objects
+            .iter()
+            .map(|object| object.bounding_box())
+            .try_fold(Aabb::empty(), |acc, bounds| Some(acc.union(&bounds?)))
+            .filter(|_| !self.objects.is_empty())
+    }
+}
@@ -1,13 +1,34 @@
 use crate::color::Color;
-use crate::material::ScatterResult;
+use crate::material::{BsdfEvaluation, ScatterResult};
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 use crate::vec3::Vec3;
 
 #[inline]
-pub fn lambertian_scatter(normal: &Vec3, point: &Vec3, albedo: &Color) -> ScatterResult {
-    let scatter_direction = *normal + Vec3::random_unit_vector();
+pub fn lambertian_scatter<S: Sampler + ?Sized>(
+    normal: &Vec3,
+    point: &Vec3,
+    albedo: &Color,
+    sampler: &mut S,
+) -> ScatterResult {
+    let mut scatter_direction = *normal + Vec3::random_unit_vector(sampler);
+    // The random unit vector may cancel out the normal.
+    if scatter_direction.squared_length() < 1e-16 {
+        scatter_direction = *normal;
+    }
+    let cosine = normal.dot(&scatter_direction.make_unit_vector());
     ScatterResult::Scattered {
         attenuation: *albedo,
         scattered: Ray::new(*point, scatter_direction),
+        pdf: Some(cosine.max(0.0) / std::f64::consts::PI),
+    }
+}
+
+#[inline]
+pub fn lambertian_evaluate(normal: &Vec3, direction: &Vec3, albedo: &Color) -> BsdfEvaluation {
+    let cosine = normal.dot(&direction.make_unit_vector()).max(0.0);
+    BsdfEvaluation {
+        value: *albedo * (cosine / std::f64::consts::PI),
+        pdf: cosine / std::f64::consts::PI,
     }
 }
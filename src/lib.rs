@@ -0,0 +1,80 @@
+pub mod aabb;
+pub mod adaptive;
+pub mod bvh;
+pub mod camera;
+pub mod color;
+pub mod conductor;
+pub mod constant_medium;
+pub mod cuboid;
+pub mod daylight;
+pub mod dielectric;
+pub mod disk;
+pub mod distribution;
+pub mod environment;
+pub mod film;
+pub mod filter;
+pub mod framebuffer;
+pub mod hittable;
+pub mod lambertian;
+pub mod light;
+pub mod material;
+pub mod material_variants;
+pub mod metal;
+pub mod microfacet;
+pub mod moving_sphere;
+pub mod obj;
+pub mod onb;
+pub mod output;
+pub mod perlin;
+pub mod phase;
+pub mod plane;
+pub mod principled;
+pub mod quad;
+pub mod ray;
+pub mod ray_color;
+pub mod renderer;
+pub mod sample_rng;
+pub mod sampler;
+pub mod scene_file;
+pub mod scenes;
+pub mod sphere;
+pub mod texture;
+pub mod tiles;
+pub mod transform;
+pub mod transformed;
+pub mod triangle;
+pub mod triangle_mesh;
+pub mod util;
+pub mod vec3;
+
+pub use aabb::Aabb;
+pub use adaptive::AdaptiveSampling;
+pub use bvh::Bvh;
+pub use camera::Camera;
+pub use color::Color;
+pub use conductor::Conductor;
+pub use constant_medium::ConstantMedium;
+pub use cuboid::Cuboid;
+pub use daylight::{Daylight, DaylightSettings};
+pub use disk::Disk;
+pub use environment::{Environment, EnvironmentMap};
+pub use film::{Film, FilmTile};
+pub use filter::{Filter, FilterKind};
+pub use framebuffer::Framebuffer;
+pub use hittable::{Hittable, HittableList};
+pub use light::{Light, LightList};
+pub use material_variants::MaterialVariants;
+pub use moving_sphere::MovingSphere;
+pub use plane::Plane;
+pub use principled::Principled;
+pub use quad::Quad;
+pub use ray::Ray;
+pub use renderer::{RenderSettings, Renderer};
+pub use sampler::{Sampler, SamplerKind};
+pub use sphere::Sphere;
+pub use texture::TextureVariants;
+pub use transform::{AnimatedTransform, Matrix4, Transform};
+pub use transformed::{Animated, Transformed};
+pub use triangle::Triangle;
+pub use triangle_mesh::TriangleMesh;
+pub use vec3::Vec3;
@@ -0,0 +1,59 @@
+use crate::hittable::Hittable;
+use crate::sampler::Sampler;
+use crate::vec3::Vec3;
+
+/// An emitting object that can be sampled directly for next-event estimation.
+pub trait Light: Hittable {
+    /// Picks a direction from `origin` towards the light; it does not need to be normalized.
+    fn sample_direction(&self, origin: &Vec3, sampler: &mut dyn Sampler) -> Vec3;
+
+    /// Solid angle density with which [`Light::sample_direction`] returns `direction` from
+    /// `origin`, zero if the direction misses the light.
+    fn pdf_value(&self, origin: &Vec3, direction: &Vec3) -> f64;
+}
+
+/// The lights of a scene, sampled uniformly.
+#[derive(Default)]
+pub struct LightList {
+    pub lights: Vec<Box<dyn Light>>,
+}
+
+impl LightList {
+    pub fn new() -> LightList {
+        LightList { lights: Vec::new() }
+    }
+
+    pub fn add(&mut self, light: Box<dyn Light>) {
+        self.lights.push(light);
+    }
+
+    pub fn len(&self) -> usize {
+        self.lights.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lights.is_empty()
+    }
+
+    /// Samples a direction towards one of the lights, `None` if there are none.
+    pub fn sample_direction(&self, origin: &Vec3, sampler: &mut dyn Sampler) -> Option<Vec3> {
+        if self.lights.is_empty() {
+            return None;
+        }
+        let count = self.lights.len();
+        let index = ((sampler.get_1d() * count as f64) as usize).min(count - 1);
+        Some(self.lights[index].sample_direction(origin, sampler))
+    }
+
+    /// Density of [`LightList::sample_direction`], the average over all lights.
+    pub fn pdf_value(&self, origin: &Vec3, direction: &Vec3) -> f64 {
+        if self.lights.is_empty() {
+            return 0.0;
+        }
+        self.lights
+            .iter()
+            .map(|light| light.pdf_value(origin, direction))
+            .sum::<f64>()
+            / self.lights.len() as f64
+    }
+}
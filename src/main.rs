@@ -1,35 +1,113 @@
-use std::fs::File;
-use std::io::prelude::*;
-use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use chrono::prelude::*;
+use rand::Rng;
+use structopt::StructOpt;
+
+use ray::color::Color;
+use ray::filter::FILTERS;
+use ray::output::ImageFormat;
+use ray::sampler::SAMPLERS;
+use ray::scene_file::load_scene;
+use ray::scenes::{builtin_scene, BUILTIN_SCENES};
+use ray::{
+    AdaptiveSampling, Bvh, DaylightSettings, Environment, EnvironmentMap, Filter, FilterKind,
+    Framebuffer, RenderSettings, Renderer, SamplerKind, Vec3,
+};
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "ray", about = "A small path tracer")]
+struct Options {
+    /// Image width in pixels
+    #[structopt(short = "W", long, default_value = "1920", validator = at_least_one)]
+    width: usize,
+
+    /// Image height in pixels
+    #[structopt(short = "H", long, default_value = "1080", validator = at_least_one)]
+    height: usize,
+
+    /// Number of samples per pixel, the maximum with adaptive sampling
+    #[structopt(short, long = "spp", default_value = "500", validator = at_least_one)]
+    samples_per_pixel: usize,
+
+    /// Sample adaptively: pixels stop once the standard error of their mean luminance relative
+    /// to the mean falls below this threshold, e.g. 0.01
+    #[structopt(long)]
+    adaptive_threshold: Option<f64>,
+
+    /// Samples every pixel takes before adaptive sampling checks its error, and between checks
+    #[structopt(long = "min-spp", default_value = "16")]
+    min_samples_per_pixel: usize,
+
+    /// Also write the number of samples per pixel as a grey image, white for the maximum
+    #[structopt(long, parse(from_os_str))]
+    spp_map: Option<PathBuf>,
+
+    /// Maximum number of bounces per path
+    #[structopt(short = "d", long, default_value = "50")]
+    max_depth: isize,
+
+    /// Sampler for the pixel, lens and path dimensions
+    #[structopt(long, default_value = "sobol", possible_values = &SAMPLERS)]
+    sampler: SamplerKind,
+
+    /// Pixel reconstruction filter
+    #[structopt(long, default_value = "box", possible_values = &FILTERS)]
+    filter: FilterKind,
+
+    /// Filter radius in pixels, defaults to 0.5 for box, 1 for tent, 1.5 for gaussian and 2 for
+    /// mitchell
+    #[structopt(long)]
+    filter_radius: Option<f64>,
+
+    /// Number of worker threads, defaults to the number of logical cores
+    #[structopt(short = "j", long)]
+    threads: Option<usize>,
+
+    /// Seed for the scene and the per-sample generators, random if omitted
+    #[structopt(long)]
+    seed: Option<u64>,
+
+    /// Output file (.png, .ppm, .hdr, .pfm or .exr), defaults to a timestamped PNG
+    #[structopt(short, long, parse(from_os_str))]
+    output: Option<PathBuf>,
+
+    /// Built-in scene to render
+    #[structopt(long, default_value = "random", possible_values = &BUILTIN_SCENES)]
+    scene: String,
+
+    /// Scene description file to render instead of a built-in scene
+    #[structopt(short = "f", long, parse(from_os_str), conflicts_with = "scene")]
+    scene_file: Option<PathBuf>,
+
+    /// Radiance HDR environment map in latitude-longitude layout to light the scene with,
+    /// replacing the scene's own environment
+    #[structopt(long, parse(from_os_str))]
+    environment: Option<PathBuf>,
+
+    /// Light the scene with an analytic daylight sky with the sun this many degrees above the
+    /// horizon, replacing the scene's own environment
+    #[structopt(long, conflicts_with = "environment", allow_hyphen_values = true)]
+    sun_elevation: Option<f64>,
+
+    /// Compass direction of the sun in degrees, 0 along -z and 90 along +x, defaults to 0
+    #[structopt(long, allow_hyphen_values = true, requires = "sun-elevation")]
+    sun_azimuth: Option<f64>,
+
+    /// Haziness of the daylight sky, from 2 for very clear to 10 for hazy, defaults to 3
+    #[structopt(long, requires = "sun-elevation")]
+    turbidity: Option<f64>,
+}
 
-// use indicatif::ProgressBar;
-use itertools::iproduct;
-use rand::distributions::{Distribution, Uniform};
-
-mod camera;
-mod color;
-mod dielectric;
-mod hittable;
-mod lambertian;
-mod material;
-mod material_variants;
-mod metal;
-mod ray;
-mod ray_color;
-mod sphere;
-mod util;
-mod vec3;
-
-use camera::Camera;
-use color::Color;
-use hittable::HittableList;
-use material_variants::MaterialVariants;
-use ray_color::ray_color;
-use rayon::prelude::*;
-use sphere::Sphere;
-use vec3::Vec3;
+/// Accepts counts of one or more.
+fn at_least_one(value: String) -> Result<(), String> {
+    match value.parse::<usize>() {
+        Ok(count) if count >= 1 => Ok(()),
+        Ok(_) => Err("must be at least 1".to_string()),
+        Err(error) => Err(error.to_string()),
+    }
+}
 
 #[allow(dead_code)]
 fn shade_normal(normal_vector: &Vec3) -> Color {
@@ -40,227 +118,111 @@ fn shade_normal(normal_vector: &Vec3) -> Color {
     )
 }
 
-fn write_ppm(width: usize, height: usize, pixels: &[Color]) -> std::io::Result<()> {
+fn default_output_path() -> PathBuf {
     let now = Local::now();
-
-    let file = File::create(format!(
-        "{}{}{}_{:02}{:02}{:02}_out.ppm",
+    PathBuf::from(format!(
+        "{}{}{}_{:02}{:02}{:02}_out.png",
         now.year(),
         now.month(),
         now.day(),
         now.hour(),
         now.minute(),
         now.second()
-    ))?;
-    let mut buf_writer = BufWriter::new(file);
-    let header = format!("P6 {} {} 255 ", width, height);
-    buf_writer.write_all(header.as_bytes())?;
-    for p in pixels {
-        buf_writer.write_all(&p.as_bytes())?;
-    }
-    Ok(())
+    ))
 }
 
 fn main() {
-    let width = 1920;
-    let height = 1080;
-    let aspect_ratio = (width as f64) / (height as f64);
-    // let total_pixels = width * height;
-
-    // let mut vec: Vec<Color> = Vec::with_capacity(width * height);
-    let coordinates_range = iproduct!((0..height).rev(), 0..width);
-    // let pb = ProgressBar::new(total_pixels as u64);
-    // pb.set_draw_delta((total_pixels / 100) as u64);
-
-    let look_from = Vec3::new(13.0, 2.0, 3.0);
-    let look_at = Vec3::new(0.0, 0.0, 0.0);
-
-    // Camera
-    let camera = Camera::default(
-        look_from,
-        look_at,
-        Vec3::new(0.0, 1.0, 0.0),
-        20.0_f64,
-        aspect_ratio,
-        0.1,
-        10.0,
-    );
+    let options = Options::from_args();
 
-    let scene = random_scene();
-
-    let samples_per_pixel: usize = 500;
-
-    println!("Writing a {}x{} image", width, height);
-    let coordinates_vec: Vec<(usize, usize)> = coordinates_range.collect();
-    let vec: Vec<Color> = coordinates_vec
-        .par_iter()
-        .map(|(j, i)| {
-            let mut rng = rand::thread_rng();
-            let uniform_dist = Uniform::new_inclusive(-0.5_f64, 0.5_f64);
-            (std::iter::repeat_with(|| {
-                (
-                    (*i as f64 + uniform_dist.sample(&mut rng)) / ((width - 1) as f64),
-                    (*j as f64 + uniform_dist.sample(&mut rng)) / ((height - 1) as f64),
-                )
-            })
-            .take(samples_per_pixel)
-            .map(|uv| camera.get_ray(uv.0, uv.1))
-            .map(|r| ray_color(&r, &scene, 50))
-            .fold(Vec3::origin(), |acc, c| acc + c)
-                / (samples_per_pixel as f64))
-                .gamma_correction(2.0)
-        })
-        .collect();
-
-    // for (j, i) in pb.wrap_iter(coordinates_range) {
-    //     let pixel_color = std::iter::repeat_with(|| {
-    //         (
-    //             (i as f64 + uniform_dist.sample(&mut rng)) / ((width - 1) as f64),
-    //             (j as f64 + uniform_dist.sample(&mut rng)) / ((height - 1) as f64),
-    //         )
-    //     })
-    //     .take(samples_per_pixel)
-    //     .map(|uv| camera.get_ray(uv.0, uv.1))
-    //     .map(|r| ray_color(&r, &scene, 50))
-    //     .fold(Vec3::origin(), |acc, c| acc + c)
-    //         / (samples_per_pixel as f64);
-    //
-    //     vec.push(pixel_color.gamma_correction(2.0));
-    // }
-
-    match write_ppm(width, height, &vec) {
-        Ok(_) => println!("Ok!"),
-        Err(_) => println!("nok..."),
+    if let Some(threads) = options.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+            .expect("failed to configure the thread pool");
     }
-}
 
-fn old_world() -> HittableList {
-    let material_ground = MaterialVariants::Lambertian(Color::new(0.8, 0.8, 0.0));
-    let material_center = MaterialVariants::Lambertian(Color::new(0.7, 0.3, 0.3));
-    let material_metal1 = MaterialVariants::Metal(Color::new(0.8, 0.8, 0.8), 0.0);
-    let material_metal2 = MaterialVariants::Metal(Color::new(0.8, 0.8, 0.8), 0.1);
-    let material_metal3 = MaterialVariants::Metal(Color::new(0.8, 0.6, 0.2), 0.1);
-
-    let material_dielectrical = MaterialVariants::Dielectric(1.5);
-
-    // Scene
-    let mut scene = HittableList::new();
-
-    scene.add(Box::new(Sphere::new(
-        Vec3::new(1.0, 0.5, -1.0),
-        0.1,
-        material_center,
-    )));
-
-    scene.add(Box::new(Sphere::new(
-        Vec3::new(-1.9, 0.0, -5.0),
-        1.0,
-        material_metal1,
-    )));
-    scene.add(Box::new(Sphere::new(
-        Vec3::new(-0.7, 0.5, -4.0),
-        0.2,
-        material_center,
-    )));
-
-    scene.add(Box::new(Sphere::new(
-        Vec3::new(0.9, 0.2, -5.0),
-        1.0,
-        material_metal3,
-    )));
-
-    scene.add(Box::new(Sphere::new(
-        Vec3::new(0.0, 0.0, -3.0),
-        0.5,
-        material_dielectrical,
-    )));
-    scene.add(Box::new(Sphere::new(
-        Vec3::new(0.0, 0.0, -3.0),
-        -0.4,
-        material_dielectrical,
-    )));
-
-    scene.add(Box::new(Sphere::new(
-        Vec3::new(0.0, 1.4, -3.0),
-        0.75,
-        material_dielectrical,
-    )));
-
-    scene.add(Box::new(Sphere::new(
-        Vec3::new(1.7, -0.2, -4.0),
-        0.5,
-        material_metal2,
-    )));
-
-    // Ground
-    scene.add(Box::new(Sphere::new(
-        Vec3::new(0.0, -202.0, -1.0),
-        200.0,
-        material_ground,
-    )));
-
-    scene
-}
-
-fn random_scene() -> HittableList {
-    let mut world: HittableList = HittableList::new();
-
-    let mut rng = rand::thread_rng();
-    let uniform_dist = Uniform::new_inclusive(0.0, 1.0);
-    let uniform_dist_0_5 = Uniform::new_inclusive(0.0, 0.5);
-
-    let material_ground = MaterialVariants::Lambertian(Color::new(0.5, 0.5, 0.5));
-    world.add(Box::new(Sphere::new(
-        Vec3::new(0.0, -1000.0, 0.0),
-        1000.0,
-        material_ground,
-    )));
-
-    for a in -11..11 {
-        for b in -11..11 {
-            let rand = uniform_dist.sample(&mut rng);
-            let center = Vec3::new(
-                a as f64 + 0.9 * uniform_dist.sample(&mut rng),
-                0.2,
-                b as f64 + 0.9 * uniform_dist.sample(&mut rng),
+    let output = options.output.unwrap_or_else(default_output_path);
+    for path in std::iter::once(&output).chain(&options.spp_map) {
+        if ImageFormat::from_path(path).is_none() {
+            eprintln!(
+                "Unsupported output format for {}, use .png, .ppm, .hdr, .pfm or .exr",
+                path.display()
             );
-
-            if (center - Vec3::new(4.0, 0.2, 0.0)).length() > 0.9 {
-                if rand < 0.6 {
-                    let albedo: Color =
-                        Vec3::random_with_bounds(0.2, 1.0) * Vec3::random_with_bounds(0.2, 1.0);
-                    let material = MaterialVariants::Lambertian(albedo);
-                    world.add(Box::new(Sphere::new(center, 0.2, material)));
-                } else if rand < 0.8 {
-                    let albedo: Color = Vec3::random_with_bounds(0.0, 0.5);
-                    let fuzz = uniform_dist_0_5.sample(&mut rng);
-                    let material = MaterialVariants::Metal(albedo, fuzz);
-                    world.add(Box::new(Sphere::new(center, 0.2, material)));
-                } else {
-                    let material = MaterialVariants::Dielectric(1.5);
-                    world.add(Box::new(Sphere::new(center, 0.2, material)));
-                }
-            }
+            std::process::exit(1);
         }
     }
 
-    world.add(Box::new(Sphere::new(
-        Vec3::new(0.0, 1.0, 0.0),
-        1.0,
-        MaterialVariants::Dielectric(1.5),
-    )));
-
-    world.add(Box::new(Sphere::new(
-        Vec3::new(-4.0, 1.0, 0.0),
-        1.0,
-        MaterialVariants::Lambertian(Color::new(0.4, 0.2, 1.0)),
-    )));
-
-    world.add(Box::new(Sphere::new(
-        Vec3::new(4.0, 1.0, 0.0),
-        1.0,
-        MaterialVariants::Metal(Color::new(0.7, 0.6, 0.5), 0.0),
-    )));
-
-    world
+    let seed = options.seed.unwrap_or_else(|| rand::thread_rng().gen());
+    let min_samples = options.min_samples_per_pixel;
+    let settings = RenderSettings {
+        width: options.width,
+        height: options.height,
+        samples_per_pixel: options.samples_per_pixel,
+        max_depth: options.max_depth,
+        seed,
+        sampler: options.sampler,
+        filter: Filter::new(
+            options.filter,
+            options
+                .filter_radius
+                .unwrap_or(options.filter.default_radius()),
+        ),
+        adaptive: options
+            .adaptive_threshold
+            .map(|threshold| AdaptiveSampling {
+                threshold,
+                min_samples,
+            }),
+    };
+
+    let mut scene = match &options.scene_file {
+        Some(path) => load_scene(path).unwrap_or_else(|error| {
+            eprintln!("Could not load {}: {}", path.display(), error);
+            std::process::exit(1);
+        }),
+        None => builtin_scene(&options.scene, seed).expect("scene name is validated by clap"),
+    };
+    if let Some(path) = &options.environment {
+        let map = EnvironmentMap::load(path).unwrap_or_else(|error| {
+            eprintln!("Could not load {}: {}", path.display(), error);
+            std::process::exit(1);
+        });
+        scene.environment = Environment::Image(Arc::new(map));
+    }
+    if let Some(sun_elevation) = options.sun_elevation {
+        let defaults = DaylightSettings::default();
+        let daylight = DaylightSettings {
+            sun_elevation,
+            sun_azimuth: options.sun_azimuth.unwrap_or(defaults.sun_azimuth),
+            turbidity: options.turbidity.unwrap_or(defaults.turbidity),
+            ..defaults
+        };
+        scene.environment = Environment::Daylight(Arc::new(daylight.build()));
+    }
+    let camera = scene.camera.build(settings.aspect_ratio());
+
+    println!(
+        "Writing a {}x{} image with seed {}",
+        settings.width, settings.height, seed
+    );
+    let world = Bvh::new(scene.world.objects);
+    let film = Renderer::new(settings).with_progress_bar().render_film(
+        &world,
+        &scene.lights,
+        &scene.environment,
+        &camera,
+    );
+
+    save(&film.to_framebuffer(), &output);
+    if let Some(path) = &options.spp_map {
+        save(&film.sample_count_image(settings.samples_per_pixel), path);
+    }
+    println!("Ok!");
+}
+
+fn save(framebuffer: &Framebuffer, path: &Path) {
+    if let Err(error) = framebuffer.save(path) {
+        eprintln!("Could not write {}: {}", path.display(), error);
+        std::process::exit(1);
+    }
 }
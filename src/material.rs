@@ -1,14 +1,50 @@
 use crate::color::Color;
-use crate::hittable::Face;
+use crate::hittable::HitRecord;
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 use crate::vec3::Vec3;
 
 pub enum ScatterResult {
     Absorbed,
-    Scattered { attenuation: Color, scattered: Ray },
+    /// `attenuation` already accounts for the sampling density. `pdf` is the solid angle density
+    /// of the sampled direction, `None` for specular bounces that cannot be evaluated by
+    /// [`Material::evaluate`].
+    Scattered {
+        attenuation: Color,
+        scattered: Ray,
+        pdf: Option<f64>,
+    },
 }
 
-pub trait Material: Copy {
-    fn scatter(&self, incoming_ray: &Ray, normal: &Vec3, point: &Vec3, face: Face)
-        -> ScatterResult;
+/// BSDF times cosine for a given pair of directions, with the density of sampling it.
+#[derive(Debug, Clone, Copy)]
+pub struct BsdfEvaluation {
+    pub value: Color,
+    pub pdf: f64,
+}
+
+pub trait Material {
+    /// Samples a scattered ray; all randomness is drawn from `sampler`.
+    fn scatter<S: Sampler + ?Sized>(
+        &self,
+        incoming_ray: &Ray,
+        hit_record: &HitRecord,
+        sampler: &mut S,
+    ) -> ScatterResult;
+
+    /// Evaluates scattering from `incoming_ray` into `direction`, `None` if the material is
+    /// purely specular.
+    fn evaluate(
+        &self,
+        _incoming_ray: &Ray,
+        _hit_record: &HitRecord,
+        _direction: &Vec3,
+    ) -> Option<BsdfEvaluation> {
+        None
+    }
+
+    /// Radiance emitted from the hit side of the surface.
+    fn emitted(&self, _hit_record: &HitRecord) -> Color {
+        Color::new_black()
+    }
 }
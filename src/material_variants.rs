@@ -1,36 +1,188 @@
-use crate::dielectric::dielectric_scatter;
-use crate::lambertian::lambertian_scatter;
-use crate::material::{Material, ScatterResult};
+use crate::conductor::{conductor_evaluate, conductor_scatter, Conductor};
+use crate::dielectric::{dielectric_evaluate, dielectric_scatter};
+use crate::lambertian::{lambertian_evaluate, lambertian_scatter};
+use crate::material::{BsdfEvaluation, Material, ScatterResult};
 use crate::metal::metal_scatter;
+use crate::microfacet::SPECULAR_ROUGHNESS;
+use crate::phase::{phase_evaluate, phase_scatter};
+use crate::principled::{principled_evaluate, principled_scatter, Principled};
 
 use crate::color::Color;
-use crate::hittable::Face;
+use crate::hittable::{Face, HitRecord};
 use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::texture::{Texture, TextureVariants};
 use crate::vec3::Vec3;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone)]
 pub enum MaterialVariants {
-    Metal(Color, f64),
-    Lambertian(Color),
-    Dielectric(f64),
+    Metal(TextureVariants, f64),
+    /// A metal with a complex index of refraction and a GGX microfacet surface.
+    Conductor(Conductor),
+    Lambertian(TextureVariants),
+    /// Glass with an index of refraction and a roughness, 0 for a smooth surface.
+    Dielectric(f64, f64),
+    /// Disney's principled BSDF, driven by a base color and a few sliders.
+    Principled(Principled),
+    /// Emits the given radiance from its front face and absorbs all incoming light.
+    Emissive(TextureVariants),
+    /// Scatters equally in all directions inside a participating medium.
+    Isotropic(TextureVariants),
+    /// Henyey–Greenstein scattering inside a participating medium with the given mean cosine.
+    HenyeyGreenstein(TextureVariants, f64),
+}
+
+impl MaterialVariants {
+    /// Whether objects with this material should be sampled as lights.
+    pub fn is_emissive(&self) -> bool {
+        matches!(self, MaterialVariants::Emissive(_))
+    }
+
+    /// Whether this is a phase function for
+    /// [`ConstantMedium`](crate::constant_medium::ConstantMedium) rather than a surface material.
+    pub fn is_phase_function(&self) -> bool {
+        matches!(
+            self,
+            MaterialVariants::Isotropic(_) | MaterialVariants::HenyeyGreenstein(..)
+        )
+    }
+}
+
+fn texture_value(texture: &TextureVariants, hit_record: &HitRecord) -> Color {
+    texture.value(hit_record.u, hit_record.v, &hit_record.p)
 }
 
 impl Material for MaterialVariants {
-    fn scatter(
+    fn scatter<S: Sampler + ?Sized>(
         &self,
         incoming_ray: &Ray,
-        normal: &Vec3,
-        point: &Vec3,
-        face: Face,
+        hit_record: &HitRecord,
+        sampler: &mut S,
     ) -> ScatterResult {
+        let HitRecord {
+            normal, p, face, ..
+        } = hit_record;
+        let result = match self {
+            MaterialVariants::Metal(albedo, fuzz) => metal_scatter(
+                incoming_ray,
+                normal,
+                p,
+                &texture_value(albedo, hit_record),
+                *fuzz,
+                sampler,
+            ),
+            MaterialVariants::Conductor(conductor) => {
+                conductor_scatter(incoming_ray, normal, p, conductor, sampler)
+            }
+            MaterialVariants::Lambertian(albedo) => {
+                lambertian_scatter(normal, p, &texture_value(albedo, hit_record), sampler)
+            }
+            MaterialVariants::Dielectric(ref_idx, roughness) => dielectric_scatter(
+                incoming_ray,
+                normal,
+                p,
+                *face,
+                *ref_idx,
+                *roughness,
+                sampler,
+            ),
+            MaterialVariants::Principled(principled) => principled_scatter(
+                incoming_ray,
+                normal,
+                p,
+                *face,
+                principled,
+                &texture_value(&principled.base_color, hit_record),
+                sampler,
+            ),
+            MaterialVariants::Emissive(_) => ScatterResult::Absorbed,
+            MaterialVariants::Isotropic(albedo) => phase_scatter(
+                incoming_ray,
+                p,
+                &texture_value(albedo, hit_record),
+                0.0,
+                sampler,
+            ),
+            MaterialVariants::HenyeyGreenstein(albedo, g) => phase_scatter(
+                incoming_ray,
+                p,
+                &texture_value(albedo, hit_record),
+                *g,
+                sampler,
+            ),
+        };
+        // Scattered rays continue at the same moment.
+        match result {
+            ScatterResult::Scattered {
+                attenuation,
+                scattered,
+                pdf,
+            } => ScatterResult::Scattered {
+                attenuation,
+                scattered: Ray::with_time(scattered.origin, scattered.direction, incoming_ray.time),
+                pdf,
+            },
+            ScatterResult::Absorbed => ScatterResult::Absorbed,
+        }
+    }
+
+    fn evaluate(
+        &self,
+        incoming_ray: &Ray,
+        hit_record: &HitRecord,
+        direction: &Vec3,
+    ) -> Option<BsdfEvaluation> {
         match self {
-            MaterialVariants::Metal(albedo, fuzz) => {
-                metal_scatter(incoming_ray, normal, point, albedo, *fuzz)
+            MaterialVariants::Lambertian(albedo) => Some(lambertian_evaluate(
+                &hit_record.normal,
+                direction,
+                &texture_value(albedo, hit_record),
+            )),
+            MaterialVariants::Conductor(conductor) if !conductor.is_specular() => Some(
+                conductor_evaluate(incoming_ray, &hit_record.normal, direction, conductor),
+            ),
+            MaterialVariants::Dielectric(ref_idx, roughness)
+                if *roughness >= SPECULAR_ROUGHNESS =>
+            {
+                Some(dielectric_evaluate(
+                    incoming_ray,
+                    &hit_record.normal,
+                    direction,
+                    hit_record.face,
+                    *ref_idx,
+                    *roughness,
+                ))
             }
-            MaterialVariants::Lambertian(albedo) => lambertian_scatter(normal, point, albedo),
-            MaterialVariants::Dielectric(ref_idx) => {
-                dielectric_scatter(incoming_ray, normal, point, face, *ref_idx)
+            MaterialVariants::Principled(principled) => Some(principled_evaluate(
+                incoming_ray,
+                &hit_record.normal,
+                direction,
+                hit_record.face,
+                principled,
+                &texture_value(&principled.base_color, hit_record),
+            )),
+            MaterialVariants::Isotropic(albedo) => Some(phase_evaluate(
+                incoming_ray,
+                direction,
+                &texture_value(albedo, hit_record),
+                0.0,
+            )),
+            MaterialVariants::HenyeyGreenstein(albedo, g) => Some(phase_evaluate(
+                incoming_ray,
+                direction,
+                &texture_value(albedo, hit_record),
+                *g,
+            )),
+            _ => None,
+        }
+    }
+
+    fn emitted(&self, hit_record: &HitRecord) -> Color {
+        match (self, hit_record.face) {
+            (MaterialVariants::Emissive(radiance), Face::Outside) => {
+                texture_value(radiance, hit_record)
             }
+            _ => Color::new_black(),
         }
     }
 }
@@ -0,0 +1,161 @@
+use std::f64::consts::PI;
+
+use crate::vec3::Vec3;
+
+/// Roughness below which a microfacet surface is treated as a perfectly smooth, specular one.
+pub const SPECULAR_ROUGHNESS: f64 = 1e-3;
+
+/// Isotropic GGX (Trowbridge–Reitz) distribution of microfacet normals with Smith
+/// masking-shadowing.
+///
+/// Directions are in a local frame with the macroscopic normal along +z.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ggx {
+    pub alpha: f64,
+}
+
+impl Ggx {
+    /// The distribution for a perceptual `roughness` in [0, 1], whose square is α; this spreads
+    /// the visible change in highlights more evenly over the range.
+    pub fn from_roughness(roughness: f64) -> Ggx {
+        let roughness = roughness.clamp(SPECULAR_ROUGHNESS, 1.0);
+        Ggx {
+            alpha: roughness * roughness,
+        }
+    }
+
+    /// Density D(m) of microfacet normals, per unit projected area of the surface.
+    pub fn d(&self, m: &Vec3) -> f64 {
+        if m.z <= 0.0 {
+            return 0.0;
+        }
+        let alpha2 = self.alpha * self.alpha;
+        let denominator = m.z * m.z * (alpha2 - 1.0) + 1.0;
+        alpha2 / (PI * denominator * denominator)
+    }
+
+    /// Smith's Λ(w), from which the masking functions are built.
+    fn lambda(&self, w: &Vec3) -> f64 {
+        let cos2 = w.z * w.z;
+        if cos2 <= 0.0 {
+            return f64::INFINITY;
+        }
+        let tan2 = (1.0 - cos2).max(0.0) / cos2;
+        0.5 * ((1.0 + self.alpha * self.alpha * tan2).sqrt() - 1.0)
+    }
+
+    /// Fraction of microfacets with normal `m` visible from `w`.
+    pub fn g1(&self, w: &Vec3) -> f64 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    /// Fraction of microfacets visible from both `wo` and `wi`, height-correlated.
+    pub fn g2(&self, wo: &Vec3, wi: &Vec3) -> f64 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    /// Samples a normal from the distribution of normals visible from `wo`, which must lie in
+    /// the upper hemisphere (Heitz, "Sampling the GGX Distribution of Visible Normals", 2018).
+    pub fn sample_visible_normal(&self, wo: &Vec3, (u1, u2): (f64, f64)) -> Vec3 {
+        // Stretch the view direction so the distribution becomes a hemisphere.
+        let vh = Vec3::new(self.alpha * wo.x, self.alpha * wo.y, wo.z).make_unit_vector();
+        let length_squared = vh.x * vh.x + vh.y * vh.y;
+        let t1 = if length_squared > 0.0 {
+            Vec3::new(-vh.y, vh.x, 0.0) / length_squared.sqrt()
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let t2 = vh.cross(&t1);
+        // Uniform point on the projected half disk.
+        let r = u1.sqrt();
+        let phi = 2.0 * PI * u2;
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1.0 + vh.z);
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * r * phi.sin();
+        let nh = p1 * t1 + p2 * t2 + (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt() * vh;
+        // Unstretch.
+        Vec3::new(self.alpha * nh.x, self.alpha * nh.y, nh.z.max(1e-9)).make_unit_vector()
+    }
+
+    /// Density with which [`Ggx::sample_visible_normal`] returns `m` seen from `wo`.
+    pub fn visible_normal_pdf(&self, wo: &Vec3, m: &Vec3) -> f64 {
+        if wo.z <= 0.0 {
+            return 0.0;
+        }
+        self.g1(wo) * wo.dot(m).max(0.0) * self.d(m) / wo.z
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::f64::consts::PI;
+
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::Ggx;
+    use crate::vec3::Vec3;
+
+    /// Directions on the upper hemisphere on a grid in polar and azimuthal angle, finely
+    /// spaced in the polar angle to resolve narrow lobes, with the solid angle of their cells.
+    pub(crate) fn hemisphere() -> impl Iterator<Item = (Vec3, f64)> {
+        let (thetas, phis) = (2000, 200);
+        (0..thetas * phis).map(move |i| {
+            let theta = 0.5 * PI * ((i / phis) as f64 + 0.5) / thetas as f64;
+            let phi = 2.0 * PI * ((i % phis) as f64 + 0.5) / phis as f64;
+            let direction = Vec3::new(
+                theta.sin() * phi.cos(),
+                theta.sin() * phi.sin(),
+                theta.cos(),
+            );
+            let solid_angle = theta.sin() * (0.5 * PI / thetas as f64) * (2.0 * PI / phis as f64);
+            (direction, solid_angle)
+        })
+    }
+
+    #[test]
+    fn projected_normals_cover_the_surface_once() {
+        for &roughness in &[0.2, 0.5, 1.0] {
+            let ggx = Ggx::from_roughness(roughness);
+            let area: f64 = hemisphere().map(|(m, dw)| ggx.d(&m) * m.z * dw).sum();
+            assert!((area - 1.0).abs() < 1e-3, "{} {}", roughness, area);
+
+            // The visible normals seen from any direction form a density, too.
+            let wo = Vec3::new(0.6, 0.0, 0.8);
+            let total: f64 = hemisphere()
+                .map(|(m, dw)| ggx.visible_normal_pdf(&wo, &m) * dw)
+                .sum();
+            assert!((total - 1.0).abs() < 1e-3, "{} {}", roughness, total);
+        }
+    }
+
+    #[test]
+    fn sampled_visible_normals_face_the_viewer_with_the_right_density() {
+        let ggx = Ggx::from_roughness(0.6);
+        let wo = Vec3::new(-0.3, 0.5, 0.2).make_unit_vector();
+        let mut rng = StdRng::seed_from_u64(10);
+        // E[f(m) / pdf(m)] over a box in direction space equals the box's solid angle.
+        let in_box = |m: &Vec3| m.x > 0.0 && m.y > 0.0 && m.z > 0.5;
+        let samples = 200_000;
+        let mut count = 0;
+        for _ in 0..samples {
+            let m = ggx.sample_visible_normal(&wo, (rng.gen(), rng.gen()));
+            assert!(m.z > 0.0 && m.dot(&wo) >= -1e-12);
+            assert!((m.length() - 1.0).abs() < 1e-9);
+            if in_box(&m) {
+                count += 1;
+            }
+        }
+        let expected: f64 = hemisphere()
+            .filter(|(m, _)| in_box(m))
+            .map(|(m, dw)| ggx.visible_normal_pdf(&wo, &m) * dw)
+            .sum();
+        let fraction = count as f64 / samples as f64;
+        assert!(
+            (fraction - expected).abs() < 0.005,
+            "{} {}",
+            fraction,
+            expected
+        );
+    }
+}
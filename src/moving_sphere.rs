@@ -0,0 +1,100 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material_variants::MaterialVariants;
+use crate::ray::Ray;
+use crate::sphere::hit_sphere;
+use crate::vec3::Vec3;
+
+/// Sphere moving in a straight line from `center0` at `time0` to `center1` at `time1`. It rests
+/// at the nearer end for times outside that interval.
+#[derive(Clone)]
+pub struct MovingSphere {
+    pub center0: Vec3,
+    pub center1: Vec3,
+    pub time0: f64,
+    pub time1: f64,
+    pub radius: f64,
+    pub material: MaterialVariants,
+}
+
+impl MovingSphere {
+    pub fn new(
+        center0: Vec3,
+        center1: Vec3,
+        time0: f64,
+        time1: f64,
+        radius: f64,
+        material: MaterialVariants,
+    ) -> MovingSphere {
+        MovingSphere {
+            center0,
+            center1,
+            time0,
+            time1,
+            radius,
+            material,
+        }
+    }
+
+    pub fn center(&self, time: f64) -> Vec3 {
+        if self.time1 <= self.time0 {
+            return self.center0;
+        }
+        let fraction = ((time - self.time0) / (self.time1 - self.time0)).clamp(0.0, 1.0);
+        self.center0 + fraction * (self.center1 - self.center0)
+    }
+}
+
+impl Hittable for MovingSphere {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        hit_sphere(
+            &self.center(r.time),
+            self.radius,
+            &self.material,
+            r,
+            t_min,
+            t_max,
+        )
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let extent = Vec3::new(self.radius.abs(), self.radius.abs(), self.radius.abs());
+        let at = |center: Vec3| Aabb::new(center - extent, center + extent);
+        Some(at(self.center0).union(&at(self.center1)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MovingSphere;
+    use crate::color::Color;
+    use crate::hittable::Hittable;
+    use crate::material_variants::MaterialVariants;
+    use crate::ray::Ray;
+    use crate::vec3::Vec3;
+
+    #[test]
+    fn position_follows_ray_time() {
+        let sphere = MovingSphere::new(
+            Vec3::new(0.0, 0.0, -2.0),
+            Vec3::new(0.0, 2.0, -2.0),
+            1.0,
+            2.0,
+            0.5,
+            MaterialVariants::Lambertian(Color::new_white().into()),
+        );
+        assert_eq!(sphere.center(0.0), Vec3::new(0.0, 0.0, -2.0));
+        assert_eq!(sphere.center(1.5), Vec3::new(0.0, 1.0, -2.0));
+        assert_eq!(sphere.center(3.0), Vec3::new(0.0, 2.0, -2.0));
+        let bounds = sphere.bounding_box().unwrap();
+        assert_eq!(bounds.min, Vec3::new(-0.5, -0.5, -2.5));
+        assert_eq!(bounds.max, Vec3::new(0.5, 2.5, -1.5));
+
+        let at =
+            |time: f64| Ray::with_time(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, -1.0), time);
+        assert!(sphere.hit(&at(1.0), 0.0, f64::INFINITY).is_none());
+        let hit = sphere.hit(&at(1.5), 0.0, f64::INFINITY).unwrap();
+        assert_eq!(hit.t, 1.5);
+        assert_eq!(hit.normal, Vec3::new(0.0, 0.0, 1.0));
+    }
+}
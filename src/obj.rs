@@ -0,0 +1,838 @@
+//! Wavefront OBJ and MTL import.
+//!
+//! Every material used by an OBJ file becomes one [`TriangleMesh`]. Supported statements are
+//! `v`, `vt`, `vn`, `f`, `usemtl` and `mtllib`; groups, objects and smoothing groups are ignored.
+//! Indices may be negative, in which case they count back from the most recent element.
+//!
+//! MTL materials are mapped onto [`MaterialVariants`]:
+//!
+//! * materials with a non-black emission `Ke` become `Emissive`,
+//! * materials using the PBR extension, with any of roughness `Pr`, metallic `Pm`, sheen `Ps`,
+//!   clearcoat `Pc` or clearcoat roughness `Pcr`, become `Principled` with the base color `Kd`
+//!   or `map_Kd` and, if it is greater than 1, the index of refraction `Ni`; only materials
+//!   with a refracting `illum` of 4, 6, 7 or 9 take a transmission of 1 - `d`, other values of
+//!   `d` are alpha cutouts and ignored, as are the PBR texture maps,
+//! * transparent materials (`d` < 1, `Tr` > 0 or `illum` 4, 6, 7 and 9) become `Dielectric` with
+//!   the refractive index `Ni`,
+//! * materials whose specular colour `Ks` outweighs the diffuse colour `Kd`, or that request
+//!   mirror reflections with `illum` 3 or 5, become `Metal` with the fuzz derived from `Ns`,
+//! * everything else becomes `Lambertian` with the albedo `Kd`, or the PNG image `map_Kd` if
+//!   one is given.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::color::Color;
+use crate::material_variants::MaterialVariants;
+use crate::principled::Principled;
+use crate::texture::{ImageTexture, TextureVariants};
+use crate::triangle_mesh::TriangleMesh;
+use crate::vec3::Vec3;
+
+/// What to do with vertices that have no normal.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MissingNormals {
+    /// Shade the affected meshes with their geometric normals.
+    Flat,
+    /// Give the vertices without a normal area-weighted averages of the adjacent face normals.
+    Smooth,
+}
+
+#[derive(Debug, Clone)]
+pub struct ObjOptions {
+    /// Split faces with more than three vertices into a triangle fan; otherwise they are an error.
+    pub triangulate: bool,
+    pub missing_normals: MissingNormals,
+    /// Used for faces without `usemtl` and for materials missing from the material libraries.
+    pub default_material: MaterialVariants,
+}
+
+impl Default for ObjOptions {
+    fn default() -> ObjOptions {
+        ObjOptions {
+            triangulate: true,
+            missing_normals: MissingNormals::Smooth,
+            default_material: MaterialVariants::Lambertian(Color::new(0.8, 0.8, 0.8).into()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io(PathBuf, std::io::Error),
+    Parse {
+        file: PathBuf,
+        line: usize,
+        message: String,
+    },
+}
+
+impl std::fmt::Display for ObjError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ObjError::Io(file, error) => write!(f, "{}: {}", file.display(), error),
+            ObjError::Parse {
+                file,
+                line,
+                message,
+            } => write!(f, "{}:{}: {}", file.display(), line, message),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {}
+
+/// Reads an OBJ file and the material libraries it references, resolved relative to it.
+pub fn load_obj<P: AsRef<Path>>(
+    path: P,
+    options: &ObjOptions,
+) -> Result<Vec<TriangleMesh>, ObjError> {
+    let path = path.as_ref();
+    let file = File::open(path).map_err(|error| ObjError::Io(path.to_path_buf(), error))?;
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+    parse_obj(BufReader::new(file), path, options, |name| {
+        let mtl_path = base_dir.join(name);
+        let file = File::open(&mtl_path).map_err(|error| ObjError::Io(mtl_path.clone(), error))?;
+        parse_mtl(BufReader::new(file), &mtl_path)
+    })
+}
+
+/// Parses OBJ data; `load_mtl` resolves the file names given to `mtllib`. `file` only labels
+/// errors.
+pub fn parse_obj<R, F>(
+    reader: R,
+    file: &Path,
+    options: &ObjOptions,
+    mut load_mtl: F,
+) -> Result<Vec<TriangleMesh>, ObjError>
+where
+    R: BufRead,
+    F: FnMut(&str) -> Result<HashMap<String, MaterialVariants>, ObjError>,
+{
+    let mut positions: Vec<Vec3> = Vec::new();
+    let mut uvs: Vec<(f64, f64)> = Vec::new();
+    let mut normals: Vec<Vec3> = Vec::new();
+    let mut materials: HashMap<String, MaterialVariants> = HashMap::new();
+    let mut builders: Vec<MeshBuilder> = Vec::new();
+    let mut builder_by_material: HashMap<Option<String>, usize> = HashMap::new();
+    let mut current_material: Option<String> = None;
+
+    for (line_index, line) in reader.lines().enumerate() {
+        let line_number = line_index + 1;
+        let error = |message: String| ObjError::Parse {
+            file: file.to_path_buf(),
+            line: line_number,
+            message,
+        };
+        let line = line.map_err(|e| ObjError::Io(file.to_path_buf(), e))?;
+        let mut tokens = statement_tokens(&line);
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let arguments: Vec<&str> = tokens.collect();
+
+        match keyword {
+            "v" => positions.push(parse_vec3(&arguments).map_err(error)?),
+            "vn" => normals.push(parse_vec3(&arguments).map_err(error)?),
+            "vt" => {
+                let u = parse_float(arguments.first()).map_err(error)?;
+                let v = match arguments.get(1) {
+                    Some(_) => parse_float(arguments.get(1)).map_err(error)?,
+                    None => 0.0,
+                };
+                uvs.push((u, v));
+            }
+            "f" => {
+                if arguments.len() < 3 {
+                    return Err(error("a face needs at least three vertices".to_string()));
+                }
+                if arguments.len() > 3 && !options.triangulate {
+                    return Err(error(format!(
+                        "face with {} vertices, enable triangulation to load polygons",
+                        arguments.len()
+                    )));
+                }
+                let corners = arguments
+                    .iter()
+                    .map(|corner| parse_corner(corner, positions.len(), uvs.len(), normals.len()))
+                    .collect::<Result<Vec<Corner>, String>>()
+                    .map_err(error)?;
+
+                let builder_index = *builder_by_material
+                    .entry(current_material.clone())
+                    .or_insert_with(|| {
+                        builders.push(MeshBuilder::new(current_material.clone()));
+                        builders.len() - 1
+                    });
+                let builder = &mut builders[builder_index];
+                let indices: Vec<usize> = corners.iter().map(|c| builder.vertex(c)).collect();
+                // Fan triangulation is exact for the convex polygons OBJ exporters produce.
+                for i in 1..indices.len() - 1 {
+                    builder
+                        .indices
+                        .push([indices[0], indices[i], indices[i + 1]]);
+                }
+            }
+            "usemtl" => {
+                current_material = Some(arguments.join(" "));
+            }
+            "mtllib" => {
+                for name in arguments {
+                    materials.extend(load_mtl(name)?);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(builders
+        .into_iter()
+        .filter(|builder| !builder.indices.is_empty())
+        .map(|builder| {
+            let material = builder
+                .material
+                .as_ref()
+                .and_then(|name| materials.get(name))
+                .cloned()
+                .unwrap_or_else(|| options.default_material.clone());
+            builder.build(&positions, &uvs, &normals, material, options)
+        })
+        .collect())
+}
+
+/// Parses an MTL material library; `file` labels errors and texture maps are resolved relative
+/// to it.
+pub fn parse_mtl<R: BufRead>(
+    reader: R,
+    file: &Path,
+) -> Result<HashMap<String, MaterialVariants>, ObjError> {
+    let base_dir = file.parent().unwrap_or_else(|| Path::new(""));
+    let mut images: HashMap<String, Arc<ImageTexture>> = HashMap::new();
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, MtlMaterial)> = None;
+
+    for (line_index, line) in reader.lines().enumerate() {
+        let error = |message: String| ObjError::Parse {
+            file: file.to_path_buf(),
+            line: line_index + 1,
+            message,
+        };
+        let line = line.map_err(|e| ObjError::Io(file.to_path_buf(), e))?;
+        let mut tokens = statement_tokens(&line);
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let arguments: Vec<&str> = tokens.collect();
+
+        if keyword == "newmtl" {
+            if let Some((name, material)) = current.take() {
+                materials.insert(name, material.to_material());
+            }
+            current = Some((arguments.join(" "), MtlMaterial::default()));
+            continue;
+        }
+
+        let material = match current.as_mut() {
+            Some((_, material)) => material,
+            None => continue,
+        };
+        match keyword {
+            "Kd" => material.diffuse = parse_vec3(&arguments).map_err(error)?,
+            "Ks" => material.specular = parse_vec3(&arguments).map_err(error)?,
+            "Ke" => material.emission = parse_vec3(&arguments).map_err(error)?,
+            "Ns" => material.shininess = parse_float(arguments.first()).map_err(error)?,
+            "Ni" => {
+                material.refractive_index = Some(parse_float(arguments.first()).map_err(error)?)
+            }
+            "d" => material.dissolve = parse_float(arguments.first()).map_err(error)?,
+            "Tr" => material.dissolve = 1.0 - parse_float(arguments.first()).map_err(error)?,
+            "Pr" => material.roughness = Some(parse_float(arguments.first()).map_err(error)?),
+            "Pm" => material.metallic = Some(parse_float(arguments.first()).map_err(error)?),
+            "Ps" => material.sheen = Some(parse_float(arguments.first()).map_err(error)?),
+            "Pc" => material.clearcoat = Some(parse_float(arguments.first()).map_err(error)?),
+            "Pcr" => {
+                material.clearcoat_roughness = Some(parse_float(arguments.first()).map_err(error)?)
+            }
+            "illum" => {
+                material.illumination_model = parse_float(arguments.first()).map_err(error)? as u32
+            }
+            "map_Kd" => {
+                // Options such as `-s` precede the file name, which must not contain spaces.
+                let name = match arguments.last() {
+                    Some(name) => name.to_string(),
+                    None => return Err(error("missing texture file name".to_string())),
+                };
+                let image = match images.get(&name) {
+                    Some(image) => Arc::clone(image),
+                    None => {
+                        let path = base_dir.join(&name);
+                        let image =
+                            Arc::new(ImageTexture::load(&path).map_err(|e| ObjError::Io(path, e))?);
+                        images.insert(name, Arc::clone(&image));
+                        image
+                    }
+                };
+                material.diffuse_map = Some(image);
+            }
+            _ => {}
+        }
+    }
+    if let Some((name, material)) = current.take() {
+        materials.insert(name, material.to_material());
+    }
+    Ok(materials)
+}
+
+/// Splits a line into whitespace separated tokens, dropping `#` comments.
+fn statement_tokens(line: &str) -> std::str::SplitWhitespace<'_> {
+    let statement = match line.find('#') {
+        Some(comment) => &line[..comment],
+        None => line,
+    };
+    statement.split_whitespace()
+}
+
+fn parse_float(token: Option<&&str>) -> Result<f64, String> {
+    let token = token.ok_or_else(|| "missing number".to_string())?;
+    token
+        .parse::<f64>()
+        .map_err(|_| format!("invalid number `{}`", token))
+}
+
+fn parse_vec3(arguments: &[&str]) -> Result<Vec3, String> {
+    Ok(Vec3::new(
+        parse_float(arguments.first())?,
+        parse_float(arguments.get(1))?,
+        parse_float(arguments.get(2))?,
+    ))
+}
+
+/// Zero-based attribute indices of one face corner.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct Corner {
+    position: usize,
+    uv: Option<usize>,
+    normal: Option<usize>,
+}
+
+/// Resolves a 1-based, possibly negative OBJ index against the number of elements read so far.
+fn resolve_index(token: &str, count: usize) -> Result<usize, String> {
+    let index: i64 = token
+        .parse()
+        .map_err(|_| format!("invalid index `{}`", token))?;
+    let resolved = if index < 0 {
+        count as i64 + index
+    } else {
+        index - 1
+    };
+    if index == 0 || resolved < 0 || resolved >= count as i64 {
+        return Err(format!("index {} out of range", index));
+    }
+    Ok(resolved as usize)
+}
+
+fn parse_corner(
+    token: &str,
+    position_count: usize,
+    uv_count: usize,
+    normal_count: usize,
+) -> Result<Corner, String> {
+    let mut parts = token.split('/');
+    let position = resolve_index(parts.next().unwrap_or(""), position_count)?;
+    let uv = match parts.next() {
+        Some(part) if !part.is_empty() => Some(resolve_index(part, uv_count)?),
+        _ => None,
+    };
+    let normal = match parts.next() {
+        Some(part) if !part.is_empty() => Some(resolve_index(part, normal_count)?),
+        _ => None,
+    };
+    Ok(Corner {
+        position,
+        uv,
+        normal,
+    })
+}
+
+/// Collects the faces of one material and de-duplicates their corners into mesh vertices.
+struct MeshBuilder {
+    material: Option<String>,
+    corners: Vec<Corner>,
+    corner_index: HashMap<Corner, usize>,
+    indices: Vec<[usize; 3]>,
+}
+
+impl MeshBuilder {
+    fn new(material: Option<String>) -> MeshBuilder {
+        MeshBuilder {
+            material,
+            corners: Vec::new(),
+            corner_index: HashMap::new(),
+            indices: Vec::new(),
+        }
+    }
+
+    fn vertex(&mut self, corner: &Corner) -> usize {
+        let corners = &mut self.corners;
+        *self.corner_index.entry(*corner).or_insert_with(|| {
+            corners.push(*corner);
+            corners.len() - 1
+        })
+    }
+
+    fn build(
+        self,
+        positions: &[Vec3],
+        uvs: &[(f64, f64)],
+        normals: &[Vec3],
+        material: MaterialVariants,
+        options: &ObjOptions,
+    ) -> TriangleMesh {
+        let mesh_positions = self.corners.iter().map(|c| positions[c.position]).collect();
+        let mut mesh = TriangleMesh::new(mesh_positions, self.indices, material);
+
+        if self.corners.iter().all(|c| c.uv.is_some()) {
+            mesh = mesh.with_uvs(self.corners.iter().map(|c| uvs[c.uv.unwrap()]).collect());
+        }
+        // A zero-length normal has no direction, so such corners count as missing one.
+        let supplied: Vec<Option<Vec3>> = self
+            .corners
+            .iter()
+            .map(|c| {
+                c.normal
+                    .map(|n| normals[n])
+                    .filter(|n| n.squared_length() > 0.0)
+                    .map(|n| n.make_unit_vector())
+            })
+            .collect();
+        if supplied.iter().all(Option::is_some) {
+            mesh = mesh.with_normals(supplied.into_iter().flatten().collect());
+        } else if options.missing_normals == MissingNormals::Smooth {
+            let smooth = smooth_normals(&self.corners, &mesh.indices, positions);
+            let normals = supplied
+                .iter()
+                .zip(&self.corners)
+                .map(|(normal, c)| normal.unwrap_or(smooth[&c.position]))
+                .collect();
+            mesh = mesh.with_normals(normals);
+        }
+        mesh
+    }
+}
+
+/// Area-weighted vertex normals of the triangles `indices` into `corners`, by position index, so
+/// that corners which only differ in their texture coordinates, e.g. along a UV seam, are shaded
+/// alike.
+fn smooth_normals(
+    corners: &[Corner],
+    indices: &[[usize; 3]],
+    positions: &[Vec3],
+) -> HashMap<usize, Vec3> {
+    let mut sums: HashMap<usize, Vec3> = HashMap::new();
+    for triangle in indices {
+        let [p0, p1, p2] = triangle.map(|i| positions[corners[i].position]);
+        // The unnormalized cross product is proportional to the triangle area.
+        let face_normal = (p1 - p0).cross(&(p2 - p0));
+        for i in triangle {
+            *sums
+                .entry(corners[*i].position)
+                .or_insert_with(Vec3::origin) += face_normal;
+        }
+    }
+    sums.into_iter()
+        .map(|(position, n)| {
+            let n = if n.squared_length() > 0.0 {
+                n.make_unit_vector()
+            } else {
+                n
+            };
+            (position, n)
+        })
+        .collect()
+}
+
+struct MtlMaterial {
+    diffuse: Color,
+    diffuse_map: Option<Arc<ImageTexture>>,
+    specular: Color,
+    emission: Color,
+    shininess: f64,
+    refractive_index: Option<f64>,
+    dissolve: f64,
+    illumination_model: u32,
+    roughness: Option<f64>,
+    metallic: Option<f64>,
+    sheen: Option<f64>,
+    clearcoat: Option<f64>,
+    clearcoat_roughness: Option<f64>,
+}
+
+impl Default for MtlMaterial {
+    fn default() -> MtlMaterial {
+        MtlMaterial {
+            diffuse: Color::new(0.8, 0.8, 0.8),
+            diffuse_map: None,
+            specular: Color::new_black(),
+            emission: Color::new_black(),
+            shininess: 0.0,
+            refractive_index: None,
+            dissolve: 1.0,
+            illumination_model: 2,
+            roughness: None,
+            metallic: None,
+            sheen: None,
+            clearcoat: None,
+            clearcoat_roughness: None,
+        }
+    }
+}
+
+/// `illum` values that ask for refraction.
+const REFRACTING_ILLUMINATION_MODELS: [u32; 4] = [4, 6, 7, 9];
+
+impl MtlMaterial {
+    fn to_material(&self) -> MaterialVariants {
+        if self
+            .emission
+            .red()
+            .max(self.emission.green())
+            .max(self.emission.blue())
+            > 0.0
+        {
+            return MaterialVariants::Emissive(self.emission.into());
+        }
+
+        let pbr = [
+            self.roughness,
+            self.metallic,
+            self.sheen,
+            self.clearcoat,
+            self.clearcoat_roughness,
+        ];
+        if pbr.iter().any(Option::is_some) {
+            let defaults = Principled::default();
+            // Exporters write `Ni` 0 or 1 when they have no index of refraction to give.
+            let ior = self.refractive_index.filter(|&ior| ior > 1.0);
+            let refracting = REFRACTING_ILLUMINATION_MODELS.contains(&self.illumination_model);
+            return MaterialVariants::Principled(Principled {
+                base_color: self.base_color(),
+                metallic: self.metallic.unwrap_or(defaults.metallic),
+                roughness: self.roughness.unwrap_or(defaults.roughness),
+                specular: ior.map_or(defaults.specular, |ior| {
+                    ((ior - 1.0) / (ior + 1.0)).powi(2) / 0.08
+                }),
+                sheen: self.sheen.unwrap_or(defaults.sheen),
+                clearcoat: self.clearcoat.unwrap_or(defaults.clearcoat),
+                clearcoat_gloss: self
+                    .clearcoat_roughness
+                    .map_or(defaults.clearcoat_gloss, |roughness| 1.0 - roughness),
+                transmission: if refracting {
+                    1.0 - self.dissolve
+                } else {
+                    defaults.transmission
+                },
+                ior: ior.unwrap_or(defaults.ior),
+                ..defaults
+            });
+        }
+
+        let transparent = self.dissolve < 1.0
+            || REFRACTING_ILLUMINATION_MODELS.contains(&self.illumination_model);
+        if transparent {
+            return MaterialVariants::Dielectric(self.refractive_index.unwrap_or(1.5), 0.0);
+        }
+
+        let luminance = |c: &Color| 0.2126 * c.red() + 0.7152 * c.green() + 0.0722 * c.blue();
+        let mirror = [3, 5].contains(&self.illumination_model);
+        if mirror || luminance(&self.specular) > luminance(&self.diffuse) {
+            // Map the Phong exponent to a roughness as in Walter et al. 2007.
+            let fuzz = (2.0 / (self.shininess + 2.0)).sqrt().min(1.0);
+            return MaterialVariants::Metal(self.specular.into(), fuzz);
+        }
+
+        MaterialVariants::Lambertian(self.base_color())
+    }
+
+    fn base_color(&self) -> TextureVariants {
+        match &self.diffuse_map {
+            Some(image) => TextureVariants::Image(Arc::clone(image)),
+            None => self.diffuse.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::Path;
+
+    use super::{parse_mtl, parse_obj, MissingNormals, ObjError, ObjOptions};
+    use crate::material_variants::MaterialVariants;
+    use crate::texture::TextureVariants;
+    use crate::vec3::Vec3;
+
+    const QUAD: &str = "
+        # a unit quad in the xy plane
+        v 0 0 0
+        v 1 0 0
+        v 1 1 0
+        v 0 1 0
+        f 1 2 3 4
+    ";
+
+    fn no_mtl(_: &str) -> Result<HashMap<String, MaterialVariants>, ObjError> {
+        Ok(HashMap::new())
+    }
+
+    #[test]
+    fn triangulates_polygons() {
+        let meshes = parse_obj(
+            QUAD.as_bytes(),
+            Path::new("quad.obj"),
+            &ObjOptions::default(),
+            no_mtl,
+        )
+        .unwrap();
+        assert_eq!(meshes.len(), 1);
+        assert_eq!(meshes[0].indices, vec![[0, 1, 2], [0, 2, 3]]);
+        // Missing normals are smoothed by default.
+        assert_eq!(meshes[0].normals.len(), 4);
+    }
+
+    #[test]
+    fn polygons_without_triangulation_are_an_error() {
+        let options = ObjOptions {
+            triangulate: false,
+            ..ObjOptions::default()
+        };
+        match parse_obj(QUAD.as_bytes(), Path::new("quad.obj"), &options, no_mtl) {
+            Err(ObjError::Parse { line, .. }) => assert_eq!(line, 7),
+            _ => panic!("expected a parse error"),
+        }
+    }
+
+    #[test]
+    fn negative_indices_and_attributes() {
+        let source = "
+            v 0 0 0
+            v 1 0 0
+            v 0 1 0
+            vt 0 0
+            vt 1 0
+            vt 0 1
+            vn 0 0 2
+            f -3/-3/-1 -2/-2/-1 -1/-1/-1
+        ";
+        let options = ObjOptions {
+            missing_normals: MissingNormals::Flat,
+            ..ObjOptions::default()
+        };
+        let meshes = parse_obj(source.as_bytes(), Path::new("t.obj"), &options, no_mtl).unwrap();
+        let mesh = &meshes[0];
+        assert_eq!(mesh.indices, vec![[0, 1, 2]]);
+        assert_eq!(mesh.uvs, vec![(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)]);
+        assert_eq!(mesh.normals[0].z, 1.0);
+    }
+
+    #[test]
+    fn smooth_normals_ignore_uv_seams() {
+        // Two perpendicular faces sharing an edge whose corners have different UVs on each side.
+        let source = "
+            v 0 0 0
+            v 1 0 0
+            v 0 1 0
+            v 0 0 1
+            vt 0 0
+            vt 1 0
+            vt 0 1
+            vt 0.5 0
+            vt 0.5 1
+            vt 1 1
+            f 1/1 2/2 3/3
+            f 1/4 4/5 2/6
+        ";
+        let meshes = parse_obj(
+            source.as_bytes(),
+            Path::new("seam.obj"),
+            &ObjOptions::default(),
+            no_mtl,
+        )
+        .unwrap();
+        let mesh = &meshes[0];
+        assert_eq!(mesh.positions.len(), 6);
+        let expected = Vec3::new(0.0, 1.0, 1.0).make_unit_vector();
+        let on_edge: Vec<_> = (0..6)
+            .filter(|&i| mesh.positions[i].y == 0.0 && mesh.positions[i].z == 0.0)
+            .collect();
+        assert_eq!(on_edge.len(), 4);
+        for i in on_edge {
+            assert!((mesh.normals[i] - expected).length() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn supplied_normals_are_kept_next_to_missing_ones() {
+        let source = "
+            v 0 0 0
+            v 1 0 0
+            v 1 1 0
+            v 0 1 0
+            vn 1 0 1
+            vn 0 0 0
+            f 1//1 2 3//2 4
+        ";
+        let meshes = parse_obj(
+            source.as_bytes(),
+            Path::new("mixed.obj"),
+            &ObjOptions::default(),
+            no_mtl,
+        )
+        .unwrap();
+        let normals = &meshes[0].normals;
+        assert!((normals[0] - Vec3::new(1.0, 0.0, 1.0).make_unit_vector()).length() < 1e-12);
+        // The zero-length normal is replaced like the missing ones.
+        for normal in &normals[1..] {
+            assert_eq!(*normal, Vec3::new(0.0, 0.0, 1.0));
+        }
+    }
+
+    #[test]
+    fn flat_shading_leaves_normals_empty() {
+        let options = ObjOptions {
+            missing_normals: MissingNormals::Flat,
+            ..ObjOptions::default()
+        };
+        let meshes = parse_obj(QUAD.as_bytes(), Path::new("quad.obj"), &options, no_mtl).unwrap();
+        assert!(meshes[0].normals.is_empty());
+    }
+
+    #[test]
+    fn out_of_range_index() {
+        let source = "v 0 0 0\nv 1 0 0\nf 1 2 3\n";
+        let result = parse_obj(
+            source.as_bytes(),
+            Path::new("t.obj"),
+            &ObjOptions::default(),
+            no_mtl,
+        );
+        assert!(matches!(result, Err(ObjError::Parse { line: 3, .. })));
+    }
+
+    #[test]
+    fn one_mesh_per_material() {
+        let mtl = "
+            newmtl red
+            Kd 1 0 0
+            newmtl mirror
+            Kd 0 0 0
+            Ks 0.9 0.9 0.9
+            Ns 998
+            newmtl glass
+            Ni 1.33
+            d 0.1
+            newmtl lamp
+            Ke 10 10 10
+        ";
+        let source = "
+            mtllib scene.mtl
+            v 0 0 0
+            v 1 0 0
+            v 0 1 0
+            usemtl red
+            f 1 2 3
+            usemtl glass
+            f 1 3 2
+            usemtl mirror
+            f 3 2 1
+            usemtl red
+            f 2 1 3
+            usemtl lamp
+            f 1 2 3
+        ";
+        let meshes = parse_obj(
+            source.as_bytes(),
+            Path::new("scene.obj"),
+            &ObjOptions::default(),
+            |name| {
+                assert_eq!(name, "scene.mtl");
+                parse_mtl(mtl.as_bytes(), Path::new(name))
+            },
+        )
+        .unwrap();
+        assert_eq!(meshes.len(), 4);
+        assert_eq!(meshes[0].len(), 2);
+        assert!(matches!(
+            &meshes[0].material,
+            MaterialVariants::Lambertian(TextureVariants::Solid(c)) if c.red() == 1.0
+        ));
+        assert!(matches!(meshes[1].material, MaterialVariants::Dielectric(n, _) if n == 1.33));
+        assert!(matches!(meshes[2].material, MaterialVariants::Metal(_, fuzz) if fuzz < 0.05));
+        assert!(matches!(
+            &meshes[3].material,
+            MaterialVariants::Emissive(TextureVariants::Solid(c)) if c.red() == 10.0
+        ));
+    }
+
+    #[test]
+    fn pbr_extension_becomes_principled() {
+        let mtl = "
+            newmtl brushed
+            Kd 0.9 0.6 0.3
+            Pr 0.35
+            Pm 1
+            newmtl lacquer
+            Kd 0.1 0.2 0.8
+            Ni 1.45
+            Pc 1
+            Pcr 0.2
+            Ps 0.5
+            d 0.75
+            illum 7
+            newmtl leaves
+            Kd 0.2 0.6 0.1
+            Pr 0.8
+            Ni 1
+            d 0.5
+            illum 2
+            newmtl unset
+            Pm 1
+            Ni 0
+        ";
+        let materials = parse_mtl(mtl.as_bytes(), Path::new("pbr.mtl")).unwrap();
+        match &materials["brushed"] {
+            MaterialVariants::Principled(p) => {
+                assert_eq!((p.metallic, p.roughness, p.clearcoat), (1.0, 0.35, 0.0));
+                assert!(matches!(&p.base_color, TextureVariants::Solid(c) if c.green() == 0.6));
+                assert!((p.specular - 0.5).abs() < 1e-12);
+            }
+            _ => panic!("expected a principled material"),
+        }
+        match &materials["lacquer"] {
+            MaterialVariants::Principled(p) => {
+                assert_eq!((p.metallic, p.roughness, p.sheen), (0.0, 0.5, 0.5));
+                assert_eq!((p.clearcoat, p.clearcoat_gloss), (1.0, 0.8));
+                assert_eq!((p.ior, p.transmission), (1.45, 0.25));
+            }
+            _ => panic!("expected a principled material"),
+        }
+        // An alpha cutout without a refracting `illum` is not glass, and `Ni` 0 or 1 means unset.
+        for name in &["leaves", "unset"] {
+            match &materials[*name] {
+                MaterialVariants::Principled(p) => {
+                    assert_eq!(
+                        (p.specular, p.ior, p.transmission),
+                        (0.5, 1.5, 0.0),
+                        "{}",
+                        name
+                    );
+                }
+                _ => panic!("expected a principled material"),
+            }
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use crate::vec3::Vec3;
+
+/// Orthonormal basis with `w` pointing along a given direction.
+#[derive(Debug, Clone, Copy)]
+pub struct Onb {
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl Onb {
+    /// Builds a basis around `n`, which does not need to be normalized.
+    pub fn from_w(n: &Vec3) -> Onb {
+        let w = n.make_unit_vector();
+        let a = if w.x.abs() > 0.9 {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let v = w.cross(&a).make_unit_vector();
+        let u = w.cross(&v);
+        Onb { u, v, w }
+    }
+
+    /// Transforms local coordinates to world space.
+    pub fn local(&self, a: f64, b: f64, c: f64) -> Vec3 {
+        a * self.u + b * self.v + c * self.w
+    }
+
+    /// Transforms a world space vector to local coordinates.
+    pub fn to_local(&self, p: &Vec3) -> Vec3 {
+        Vec3::new(p.dot(&self.u), p.dot(&self.v), p.dot(&self.w))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Onb;
+    use crate::vec3::Vec3;
+
+    #[test]
+    fn orthonormal_and_round_trip() {
+        for n in &[
+            Vec3::new(0.0, 0.0, 2.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(-0.3, 0.5, 0.2),
+        ] {
+            let onb = Onb::from_w(n);
+            assert!((onb.u.length() - 1.0).abs() < 1e-12);
+            assert!((onb.v.length() - 1.0).abs() < 1e-12);
+            assert!(onb.u.dot(&onb.v).abs() < 1e-12);
+            assert!(onb.u.dot(&onb.w).abs() < 1e-12);
+            let p = Vec3::new(0.3, -1.2, 2.0);
+            let local = onb.to_local(&p);
+            assert!((onb.local(local.x, local.y, local.z) - p).length() < 1e-12);
+        }
+    }
+}
@@ -0,0 +1,311 @@
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::BufWriter;
+use std::path::Path;
+
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImageFormat {
+    Ppm,
+    Png,
+    /// Radiance RGBE
+    Hdr,
+    /// Portable float map
+    Pfm,
+    /// OpenEXR with uncompressed 32-bit float channels
+    Exr,
+}
+
+impl ImageFormat {
+    /// Picks the format matching the extension of `path`, ignoring case.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<ImageFormat> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "ppm" => Some(ImageFormat::Ppm),
+            "png" => Some(ImageFormat::Png),
+            "hdr" => Some(ImageFormat::Hdr),
+            "pfm" => Some(ImageFormat::Pfm),
+            "exr" => Some(ImageFormat::Exr),
+            _ => None,
+        }
+    }
+
+    /// Whether the format stores linear radiance rather than gamma corrected 8-bit values.
+    pub fn is_high_dynamic_range(self) -> bool {
+        match self {
+            ImageFormat::Ppm | ImageFormat::Png => false,
+            ImageFormat::Hdr | ImageFormat::Pfm | ImageFormat::Exr => true,
+        }
+    }
+}
+
+pub fn write_ppm<W: Write>(framebuffer: &Framebuffer, writer: W) -> std::io::Result<()> {
+    let mut buf_writer = BufWriter::new(writer);
+    let header = format!("P6 {} {} 255 ", framebuffer.width, framebuffer.height);
+    buf_writer.write_all(header.as_bytes())?;
+    buf_writer.write_all(&framebuffer.to_rgb8())?;
+    buf_writer.flush()
+}
+
+pub fn write_png<W: Write>(framebuffer: &Framebuffer, writer: W) -> std::io::Result<()> {
+    let mut encoder = png::Encoder::new(
+        BufWriter::new(writer),
+        framebuffer.width as u32,
+        framebuffer.height as u32,
+    );
+    encoder.set_color(png::ColorType::RGB);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut png_writer = encoder.write_header()?;
+    png_writer.write_image_data(&framebuffer.to_rgb8())?;
+    Ok(())
+}
+
+/// Encodes a color as shared-exponent RGBE, see Greg Ward's "Real Pixels" in Graphics Gems II.
+fn color_to_rgbe(color: &Color) -> [u8; 4] {
+    let v = color.red().max(color.green()).max(color.blue());
+    if v < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+    let mut exponent = v.log2().floor() as i32 + 1;
+    // Guard against rounding in log2 so that the mantissa v / 2^exponent stays in [0.5, 1).
+    if v / 2.0_f64.powi(exponent) >= 1.0 {
+        exponent += 1;
+    }
+    let scale = 256.0 / 2.0_f64.powi(exponent);
+    [
+        (color.red().max(0.0) * scale) as u8,
+        (color.green().max(0.0) * scale) as u8,
+        (color.blue().max(0.0) * scale) as u8,
+        (exponent + 128) as u8,
+    ]
+}
+
+/// Writes uncompressed (flat) Radiance scanlines, which every RGBE reader accepts.
+pub fn write_hdr<W: Write>(framebuffer: &Framebuffer, writer: W) -> std::io::Result<()> {
+    let mut buf_writer = BufWriter::new(writer);
+    write!(
+        buf_writer,
+        "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+        framebuffer.height, framebuffer.width
+    )?;
+    for p in &framebuffer.pixels {
+        buf_writer.write_all(&color_to_rgbe(p))?;
+    }
+    buf_writer.flush()
+}
+
+/// Writes a little-endian portable float map. PFM stores rows from the bottom up.
+pub fn write_pfm<W: Write>(framebuffer: &Framebuffer, writer: W) -> std::io::Result<()> {
+    let mut buf_writer = BufWriter::new(writer);
+    write!(
+        buf_writer,
+        "PF\n{} {}\n-1.0\n",
+        framebuffer.width, framebuffer.height
+    )?;
+    for row in framebuffer.pixels.chunks(framebuffer.width).rev() {
+        for p in row {
+            for channel in &[p.red(), p.green(), p.blue()] {
+                buf_writer.write_all(&(*channel as f32).to_le_bytes())?;
+            }
+        }
+    }
+    buf_writer.flush()
+}
+
+fn write_exr_attribute<W: Write>(
+    writer: &mut W,
+    name: &str,
+    attribute_type: &str,
+    value: &[u8],
+) -> std::io::Result<()> {
+    writer.write_all(name.as_bytes())?;
+    writer.write_all(&[0])?;
+    writer.write_all(attribute_type.as_bytes())?;
+    writer.write_all(&[0])?;
+    writer.write_all(&(value.len() as i32).to_le_bytes())?;
+    writer.write_all(value)
+}
+
+/// Writes a single-part scanline OpenEXR file with uncompressed `FLOAT` R, G and B channels.
+pub fn write_exr<W: Write>(framebuffer: &Framebuffer, writer: W) -> std::io::Result<()> {
+    const FLOAT_PIXEL_TYPE: i32 = 2;
+    // Channels must be listed, and stored within a scanline, in alphabetical order.
+    const CHANNELS: [&str; 3] = ["B", "G", "R"];
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&[0x76, 0x2f, 0x31, 0x01]);
+    header.extend_from_slice(&2_i32.to_le_bytes());
+
+    let mut channel_list = Vec::new();
+    for name in &CHANNELS {
+        channel_list.extend_from_slice(name.as_bytes());
+        channel_list.push(0);
+        channel_list.extend_from_slice(&FLOAT_PIXEL_TYPE.to_le_bytes());
+        // pLinear and three reserved bytes, followed by the x and y sampling rates.
+        channel_list.extend_from_slice(&[0, 0, 0, 0]);
+        channel_list.extend_from_slice(&1_i32.to_le_bytes());
+        channel_list.extend_from_slice(&1_i32.to_le_bytes());
+    }
+    channel_list.push(0);
+
+    let mut window = Vec::new();
+    for bound in &[
+        0,
+        0,
+        framebuffer.width as i32 - 1,
+        framebuffer.height as i32 - 1,
+    ] {
+        window.extend_from_slice(&bound.to_le_bytes());
+    }
+
+    write_exr_attribute(&mut header, "channels", "chlist", &channel_list)?;
+    write_exr_attribute(&mut header, "compression", "compression", &[0])?;
+    write_exr_attribute(&mut header, "dataWindow", "box2i", &window)?;
+    write_exr_attribute(&mut header, "displayWindow", "box2i", &window)?;
+    write_exr_attribute(&mut header, "lineOrder", "lineOrder", &[0])?;
+    write_exr_attribute(
+        &mut header,
+        "pixelAspectRatio",
+        "float",
+        &1.0_f32.to_le_bytes(),
+    )?;
+    write_exr_attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8])?;
+    write_exr_attribute(
+        &mut header,
+        "screenWindowWidth",
+        "float",
+        &1.0_f32.to_le_bytes(),
+    )?;
+    header.push(0);
+
+    // Without compression every scanline is its own block: y coordinate, byte count, data.
+    let data_size = framebuffer.width * CHANNELS.len() * std::mem::size_of::<f32>();
+    let block_size = 2 * std::mem::size_of::<i32>() + data_size;
+    let offset_table_size = framebuffer.height * std::mem::size_of::<u64>();
+
+    let mut buf_writer = BufWriter::new(writer);
+    buf_writer.write_all(&header)?;
+    for y in 0..framebuffer.height {
+        let offset = header.len() + offset_table_size + y * block_size;
+        buf_writer.write_all(&(offset as u64).to_le_bytes())?;
+    }
+    for (y, row) in framebuffer.pixels.chunks(framebuffer.width).enumerate() {
+        buf_writer.write_all(&(y as i32).to_le_bytes())?;
+        buf_writer.write_all(&(data_size as i32).to_le_bytes())?;
+        for channel in &[Color::blue, Color::green, Color::red] {
+            for p in row {
+                buf_writer.write_all(&(channel(p) as f32).to_le_bytes())?;
+            }
+        }
+    }
+    buf_writer.flush()
+}
+
+/// Writes `framebuffer` to `path`, choosing the image format from the file extension.
+pub fn write_image<P: AsRef<Path>>(framebuffer: &Framebuffer, path: P) -> std::io::Result<()> {
+    let format = ImageFormat::from_path(&path).ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "unsupported image format, expected .ppm, .png, .hdr, .pfm or .exr",
+        )
+    })?;
+    let file = File::create(path)?;
+    match format {
+        ImageFormat::Ppm => write_ppm(framebuffer, file),
+        ImageFormat::Png => write_png(framebuffer, file),
+        ImageFormat::Hdr => write_hdr(framebuffer, file),
+        ImageFormat::Pfm => write_pfm(framebuffer, file),
+        ImageFormat::Exr => write_exr(framebuffer, file),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{color_to_rgbe, write_exr, write_pfm, write_png, write_ppm, ImageFormat};
+    use crate::color::Color;
+    use crate::framebuffer::Framebuffer;
+
+    #[test]
+    fn format_from_extension() {
+        assert_eq!(ImageFormat::from_path("out.png"), Some(ImageFormat::Png));
+        assert_eq!(
+            ImageFormat::from_path("dir/out.PPM"),
+            Some(ImageFormat::Ppm)
+        );
+        assert_eq!(ImageFormat::from_path("out.exr"), Some(ImageFormat::Exr));
+        assert_eq!(ImageFormat::from_path("out.bmp"), None);
+        assert_eq!(ImageFormat::from_path("out"), None);
+    }
+
+    #[test]
+    fn ppm_header_and_size() {
+        let framebuffer = Framebuffer::new(3, 2);
+        let mut bytes = Vec::new();
+        write_ppm(&framebuffer, &mut bytes).unwrap();
+        assert!(bytes.starts_with(b"P6 3 2 255 "));
+        assert_eq!(bytes.len(), "P6 3 2 255 ".len() + 3 * 2 * 3);
+    }
+
+    #[test]
+    fn png_round_trip() {
+        let mut framebuffer = Framebuffer::new(2, 2);
+        framebuffer.set(1, 0, Color::new_red());
+        let mut bytes = Vec::new();
+        write_png(&framebuffer, &mut bytes).unwrap();
+
+        let decoder = png::Decoder::new(bytes.as_slice());
+        let (info, mut reader) = decoder.read_info().unwrap();
+        let mut data = vec![0; info.buffer_size()];
+        reader.next_frame(&mut data).unwrap();
+        assert_eq!((info.width, info.height), (2, 2));
+        assert_eq!(&data[3..6], &[255, 0, 0]);
+        assert_eq!(&data[0..3], &[0, 0, 0]);
+    }
+
+    #[test]
+    fn rgbe_encoding() {
+        assert_eq!(color_to_rgbe(&Color::new_black()), [0, 0, 0, 0]);
+        assert_eq!(color_to_rgbe(&Color::new_white()), [128, 128, 128, 129]);
+        assert_eq!(
+            color_to_rgbe(&Color::new(4.0, 2.0, 0.5)),
+            [128, 64, 16, 131]
+        );
+    }
+
+    #[test]
+    fn pfm_keeps_radiance_above_one() {
+        let mut framebuffer = Framebuffer::new(1, 2);
+        framebuffer.set(0, 0, Color::new(8.0, 0.0, 0.0));
+        let mut bytes = Vec::new();
+        write_pfm(&framebuffer, &mut bytes).unwrap();
+        let header = b"PF\n1 2\n-1.0\n";
+        assert!(bytes.starts_with(header));
+        // The top row is stored last.
+        let last_row = &bytes[header.len() + 12..header.len() + 16];
+        assert_eq!(last_row, &8.0_f32.to_le_bytes());
+    }
+
+    #[test]
+    fn exr_layout() {
+        let framebuffer = Framebuffer::new(4, 3);
+        let mut bytes = Vec::new();
+        write_exr(&framebuffer, &mut bytes).unwrap();
+        assert_eq!(&bytes[0..4], &[0x76, 0x2f, 0x31, 0x01]);
+
+        let block_size = 8 + 4 * 3 * 4;
+        let data_start = bytes.len() - 3 * block_size;
+        let first_offset = u64::from_le_bytes([
+            bytes[data_start - 24],
+            bytes[data_start - 23],
+            bytes[data_start - 22],
+            bytes[data_start - 21],
+            bytes[data_start - 20],
+            bytes[data_start - 19],
+            bytes[data_start - 18],
+            bytes[data_start - 17],
+        ]);
+        assert_eq!(first_offset as usize, data_start);
+    }
+}
@@ -0,0 +1,100 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::vec3::Vec3;
+
+const POINT_COUNT: usize = 256;
+
+/// Gradient noise after Perlin, "Improving Noise", SIGGRAPH 2002, with random unit gradients
+/// instead of the fixed set of twelve.
+#[derive(Debug)]
+pub struct Perlin {
+    gradients: Vec<Vec3>,
+    permutation_x: Vec<usize>,
+    permutation_y: Vec<usize>,
+    permutation_z: Vec<usize>,
+}
+
+impl Perlin {
+    pub fn new<R: Rng + ?Sized>(rng: &mut R) -> Perlin {
+        let gradients = (0..POINT_COUNT)
+            .map(|_| Vec3::random_with_bounds_from(rng, -1.0, 1.0).make_unit_vector())
+            .collect();
+        let mut permutation = || {
+            let mut p: Vec<usize> = (0..POINT_COUNT).collect();
+            p.shuffle(rng);
+            p
+        };
+        Perlin {
+            gradients,
+            permutation_x: permutation(),
+            permutation_y: permutation(),
+            permutation_z: permutation(),
+        }
+    }
+
+    /// Smooth noise in [-1, 1] that repeats every 256 units.
+    pub fn noise(&self, p: &Vec3) -> f64 {
+        let fractional = |x: f64| x - x.floor();
+        let (u, v, w) = (fractional(p.x), fractional(p.y), fractional(p.z));
+        let (i, j, k) = (p.x.floor() as i64, p.y.floor() as i64, p.z.floor() as i64);
+
+        // Hermite smoothing hides the grid in the interpolation.
+        let smooth = |t: f64| t * t * (3.0 - 2.0 * t);
+        let (uu, vv, ww) = (smooth(u), smooth(v), smooth(w));
+
+        let mask = POINT_COUNT as i64 - 1;
+        let mut accumulated = 0.0;
+        for di in 0..2 {
+            for dj in 0..2 {
+                for dk in 0..2 {
+                    let gradient = self.gradients[self.permutation_x[((i + di) & mask) as usize]
+                        ^ self.permutation_y[((j + dj) & mask) as usize]
+                        ^ self.permutation_z[((k + dk) & mask) as usize]];
+                    let (fi, fj, fk) = (di as f64, dj as f64, dk as f64);
+                    let weight = Vec3::new(u - fi, v - fj, w - fk);
+                    accumulated += (fi * uu + (1.0 - fi) * (1.0 - uu))
+                        * (fj * vv + (1.0 - fj) * (1.0 - vv))
+                        * (fk * ww + (1.0 - fk) * (1.0 - ww))
+                        * gradient.dot(&weight);
+                }
+            }
+        }
+        accumulated
+    }
+
+    /// Sum of `depth` octaves of absolute noise with halving amplitude.
+    pub fn turbulence(&self, p: &Vec3, depth: usize) -> f64 {
+        let mut accumulated = 0.0;
+        let mut point = *p;
+        let mut weight = 1.0;
+        for _ in 0..depth {
+            accumulated += weight * self.noise(&point);
+            weight *= 0.5;
+            point *= 2.0;
+        }
+        accumulated.abs()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::Perlin;
+    use crate::vec3::Vec3;
+
+    #[test]
+    fn noise_is_bounded_and_zero_on_lattice() {
+        let perlin = Perlin::new(&mut StdRng::seed_from_u64(1));
+        assert_eq!(perlin.noise(&Vec3::new(3.0, -7.0, 12.0)), 0.0);
+        for i in 0..1000 {
+            let x = i as f64 * 0.137;
+            let p = Vec3::new(x, 0.5 * x - 3.0, 2.0 - x);
+            let n = perlin.noise(&p);
+            assert!((-1.0..=1.0).contains(&n));
+            assert!(perlin.turbulence(&p, 7) >= 0.0);
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use crate::color::Color;
+use crate::material::{BsdfEvaluation, ScatterResult};
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::vec3::Vec3;
+
+/// Henyey–Greenstein phase function per steradian, for the cosine of the angle between the
+/// propagation directions before and after scattering. `g` in (-1, 1) is the mean cosine:
+/// positive values scatter forward, negative values backward and zero is isotropic.
+#[inline]
+pub fn henyey_greenstein(cos_theta: f64, g: f64) -> f64 {
+    let denominator = 1.0 + g * g - 2.0 * g * cos_theta;
+    (1.0 - g * g) / (4.0 * std::f64::consts::PI * denominator * denominator.sqrt())
+}
+
+/// Samples the cosine of the scattering angle exactly in proportion to [`henyey_greenstein`].
+fn sample_cos_theta(g: f64, xi: f64) -> f64 {
+    if g.abs() < 1e-3 {
+        return 1.0 - 2.0 * xi;
+    }
+    let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * xi);
+    ((1.0 + g * g - s * s) / (2.0 * g)).clamp(-1.0, 1.0)
+}
+
+#[inline]
+pub fn phase_scatter<S: Sampler + ?Sized>(
+    incoming_ray: &Ray,
+    point: &Vec3,
+    albedo: &Color,
+    g: f64,
+    sampler: &mut S,
+) -> ScatterResult {
+    let (u1, u2) = sampler.get_2d();
+    let cos_theta = sample_cos_theta(g, u1);
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = 2.0 * std::f64::consts::PI * u2;
+    let direction = Onb::from_w(&incoming_ray.direction).local(
+        sin_theta * phi.cos(),
+        sin_theta * phi.sin(),
+        cos_theta,
+    );
+    // The sampling density equals the phase function, so only the albedo remains.
+    ScatterResult::Scattered {
+        attenuation: *albedo,
+        scattered: Ray::new(*point, direction),
+        pdf: Some(henyey_greenstein(cos_theta, g)),
+    }
+}
+
+#[inline]
+pub fn phase_evaluate(
+    incoming_ray: &Ray,
+    direction: &Vec3,
+    albedo: &Color,
+    g: f64,
+) -> BsdfEvaluation {
+    let cos_theta = incoming_ray
+        .direction
+        .make_unit_vector()
+        .dot(&direction.make_unit_vector());
+    let phase = henyey_greenstein(cos_theta, g);
+    BsdfEvaluation {
+        value: *albedo * phase,
+        pdf: phase,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::{henyey_greenstein, sample_cos_theta};
+
+    #[test]
+    fn normalized_with_mean_cosine_g() {
+        for &g in &[-0.7, 0.0, 0.3, 0.9] {
+            // Integrate over the cosine with the midpoint rule; the azimuth contributes 2π.
+            let steps = 200_000;
+            let (mut total, mut mean) = (0.0, 0.0);
+            for i in 0..steps {
+                let cos_theta = -1.0 + 2.0 * (i as f64 + 0.5) / steps as f64;
+                let weight = 2.0 * std::f64::consts::PI * henyey_greenstein(cos_theta, g) * 2.0
+                    / steps as f64;
+                total += weight;
+                mean += weight * cos_theta;
+            }
+            assert!((total - 1.0).abs() < 1e-3, "{} {}", g, total);
+            assert!((mean - g).abs() < 1e-3, "{} {}", g, mean);
+        }
+    }
+
+    #[test]
+    fn samples_have_mean_cosine_g() {
+        let mut rng = StdRng::seed_from_u64(3);
+        for &g in &[-0.5, 0.0, 0.8] {
+            let samples = 100_000;
+            let mean = (0..samples)
+                .map(|_| sample_cos_theta(g, rng.gen()))
+                .sum::<f64>()
+                / samples as f64;
+            assert!((mean - g).abs() < 0.01, "{} {}", g, mean);
+        }
+    }
+}
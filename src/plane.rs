@@ -0,0 +1,88 @@
+use crate::aabb::Aabb;
+use crate::hittable::{get_face_normal, HitRecord, Hittable};
+use crate::material_variants::MaterialVariants;
+use crate::onb::Onb;
+use crate::quad::intersect_plane;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+/// Infinite plane through `point`, facing along `normal`. The surface coordinates are distances
+/// from `point` along two fixed directions in the plane, so textures repeat with a period of 1.
+#[derive(Clone)]
+pub struct Plane {
+    pub point: Vec3,
+    pub material: MaterialVariants,
+    basis: Onb,
+}
+
+impl Plane {
+    pub fn new(point: Vec3, normal: Vec3, material: MaterialVariants) -> Plane {
+        Plane {
+            point,
+            material,
+            basis: Onb::from_w(&normal),
+        }
+    }
+
+    pub fn normal(&self) -> Vec3 {
+        self.basis.w
+    }
+}
+
+impl Hittable for Plane {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let t = intersect_plane(r, &self.point, &self.basis.w, t_min, t_max)?;
+        let p = r.at(t);
+        let local = self.basis.to_local(&(p - self.point));
+        let (face, normal) = get_face_normal(r, &self.basis.w);
+        Some(HitRecord {
+            p,
+            t,
+            u: local.x,
+            v: local.y,
+            normal,
+            face,
+            material: &self.material,
+        })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Plane;
+    use crate::color::Color;
+    use crate::hittable::{Face, Hittable};
+    use crate::material_variants::MaterialVariants;
+    use crate::ray::Ray;
+    use crate::vec3::Vec3;
+
+    #[test]
+    fn hit_from_both_sides() {
+        let plane = Plane::new(
+            Vec3::new(0.0, -1.0, 0.0),
+            Vec3::new(0.0, 2.0, 0.0),
+            MaterialVariants::Lambertian(Color::new_white().into()),
+        );
+        assert!(plane.bounding_box().is_none());
+
+        let down = Ray::new(Vec3::new(3.0, 1.0, 4.0), Vec3::new(0.0, -1.0, 0.0));
+        let hit = plane.hit(&down, 0.0, f64::INFINITY).unwrap();
+        assert_eq!(hit.t, 2.0);
+        assert_eq!(hit.face, Face::Outside);
+        assert_eq!(hit.normal, Vec3::new(0.0, 1.0, 0.0));
+        assert!(((hit.u * hit.u + hit.v * hit.v) - 25.0).abs() < 1e-12);
+
+        let up = Ray::new(Vec3::new(0.0, -3.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(
+            plane.hit(&up, 0.0, f64::INFINITY).unwrap().face,
+            Face::Inside
+        );
+
+        let parallel = Ray::new(Vec3::origin(), Vec3::new(1.0, 0.0, 0.0));
+        assert!(plane.hit(&parallel, 0.0, f64::INFINITY).is_none());
+    }
+}
@@ -0,0 +1,508 @@
+use std::f64::consts::PI;
+
+use crate::color::Color;
+use crate::dielectric::fresnel_dielectric;
+use crate::hittable::Face;
+use crate::material::{BsdfEvaluation, ScatterResult};
+use crate::microfacet::Ggx;
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::texture::TextureVariants;
+use crate::vec3::Vec3;
+
+/// Smallest roughness of the specular lobes, which are always sampled as microfacets.
+const MIN_ROUGHNESS: f64 = 0.01;
+
+/// Disney's principled BSDF (Burley, "Physically Based Shading at Disney", 2012, and "Extending
+/// the Disney BRDF to a BSDF with Integrated Subsurface Scattering", 2015). A few parameters,
+/// mostly in [0, 1], blend between diffuse, metallic, glossy, glassy, cloth and lacquered looks.
+#[derive(Debug, Clone)]
+pub struct Principled {
+    pub base_color: TextureVariants,
+    /// Blends from a dielectric to a metal with the base color as its specular color.
+    pub metallic: f64,
+    /// Perceptual roughness of the specular and transmission lobes, at least 0.01.
+    pub roughness: f64,
+    /// Specular reflectance of the dielectric at normal incidence, where 0.5 is 4%.
+    pub specular: f64,
+    /// Tints the dielectric's specular reflection towards the hue of the base color.
+    pub specular_tint: f64,
+    /// Strength of a retroreflective sheen at grazing angles, as on cloth.
+    pub sheen: f64,
+    pub sheen_tint: f64,
+    /// Strength of a second, white specular layer.
+    pub clearcoat: f64,
+    /// From a satin (0) to a glossy (1) clearcoat.
+    pub clearcoat_gloss: f64,
+    /// Blends from an opaque dielectric to glass tinted by the base color.
+    pub transmission: f64,
+    /// Index of refraction of the glass.
+    pub ior: f64,
+    /// Blends the diffuse lobe into a flatter approximation of subsurface scattering.
+    pub subsurface: f64,
+}
+
+impl Default for Principled {
+    fn default() -> Principled {
+        Principled {
+            base_color: Color::new(0.8, 0.8, 0.8).into(),
+            metallic: 0.0,
+            roughness: 0.5,
+            specular: 0.5,
+            specular_tint: 0.0,
+            sheen: 0.0,
+            sheen_tint: 0.5,
+            clearcoat: 0.0,
+            clearcoat_gloss: 1.0,
+            transmission: 0.0,
+            ior: 1.5,
+            subsurface: 0.0,
+        }
+    }
+}
+
+/// The BSDF at one point, with the base color looked up and the lobes weighted.
+///
+/// Directions are in a local frame with the normal along +z, on the side of the incoming ray.
+struct Lobes {
+    base_color: Color,
+    /// Weight of the diffuse lobe and of the opaque dielectric's specular reflection.
+    diffuse: f64,
+    metallic: f64,
+    /// Weight of the glass, which reflects and transmits through the specular microfacets.
+    glass: f64,
+    dielectric_specular: Color,
+    sheen: Color,
+    subsurface: f64,
+    roughness: f64,
+    ggx: Ggx,
+    eta: f64,
+    /// Tint of each crossing of the glass surface, so that light entering and leaving an object
+    /// is tinted by the base color.
+    transmittance: Color,
+    clearcoat: f64,
+    clearcoat_alpha: f64,
+}
+
+impl Lobes {
+    fn new(principled: &Principled, base_color: &Color, face: Face) -> Lobes {
+        let luminance = base_color.luminance();
+        let tint = if luminance > 0.0 {
+            *base_color / luminance
+        } else {
+            Color::new_white()
+        };
+        let mix = |a: Color, b: Color, t: f64| a * (1.0 - t) + b * t;
+        let roughness = principled.roughness.max(MIN_ROUGHNESS);
+        let transmittance = Color::new(
+            base_color.x.max(0.0).sqrt(),
+            base_color.y.max(0.0).sqrt(),
+            base_color.z.max(0.0).sqrt(),
+        );
+        let lobes = Lobes {
+            base_color: *base_color,
+            diffuse: (1.0 - principled.metallic) * (1.0 - principled.transmission),
+            metallic: principled.metallic,
+            glass: (1.0 - principled.metallic) * principled.transmission,
+            dielectric_specular: mix(Color::new_white(), tint, principled.specular_tint)
+                * (0.08 * principled.specular),
+            sheen: mix(Color::new_white(), tint, principled.sheen_tint)
+                * (principled.sheen
+                    * (1.0 - principled.metallic)
+                    * (1.0 - principled.transmission)),
+            subsurface: principled.subsurface,
+            roughness,
+            ggx: Ggx::from_roughness(roughness),
+            eta: principled.ior,
+            transmittance,
+            clearcoat: 0.25 * principled.clearcoat,
+            clearcoat_alpha: 0.1 + (0.001 - 0.1) * principled.clearcoat_gloss,
+        };
+        match face {
+            // Rays inside a transmissive object only see the glass from within.
+            Face::Inside if principled.transmission > 0.0 => Lobes {
+                diffuse: 0.0,
+                metallic: 0.0,
+                glass: 1.0,
+                sheen: Color::new_black(),
+                eta: 1.0 / principled.ior,
+                clearcoat: 0.0,
+                ..lobes
+            },
+            _ => lobes,
+        }
+    }
+
+    /// Reflectance of the specular microfacets turned by `cos_theta` from the incoming ray.
+    fn specular_reflectance(&self, cos_theta: f64) -> Color {
+        let weight = schlick_weight(cos_theta);
+        let schlick = |f0: Color| f0 + (Color::new_white() - f0) * weight;
+        schlick(self.base_color) * self.metallic
+            + schlick(self.dielectric_specular) * self.diffuse
+            + Color::new_white() * (self.glass * fresnel_dielectric(cos_theta, self.eta))
+    }
+
+    /// Transmittance of the specular microfacets turned by `cos_theta` from the incoming ray.
+    fn specular_transmittance(&self, cos_theta: f64) -> Color {
+        self.transmittance * (self.glass * (1.0 - fresnel_dielectric(cos_theta, self.eta)))
+    }
+
+    /// Probability of reflecting rather than refracting off a sampled microfacet.
+    fn reflection_probability(&self, cos_theta: f64) -> f64 {
+        let reflected = self.specular_reflectance(cos_theta).luminance();
+        let transmitted = self.specular_transmittance(cos_theta).luminance();
+        if reflected + transmitted > 0.0 {
+            reflected / (reflected + transmitted)
+        } else {
+            1.0
+        }
+    }
+
+    /// Probabilities of sampling the diffuse, specular and clearcoat lobes, roughly in
+    /// proportion to the light they scatter.
+    fn lobe_probabilities(&self, wo: &Vec3) -> [f64; 3] {
+        let weights = [
+            self.diffuse * self.base_color.luminance() + self.sheen.luminance(),
+            (self.specular_reflectance(wo.z) + self.specular_transmittance(wo.z)).luminance(),
+            self.clearcoat * (0.04 + 0.96 * schlick_weight(wo.z)),
+        ];
+        let total: f64 = weights.iter().sum();
+        if total > 0.0 {
+            weights.map(|weight| weight / total)
+        } else {
+            [0.0; 3]
+        }
+    }
+
+    fn sample<S: Sampler + ?Sized>(&self, wo: &Vec3, sampler: &mut S) -> Option<Vec3> {
+        let [diffuse, specular, _] = self.lobe_probabilities(wo);
+        let lobe = sampler.get_1d();
+        let (u1, u2) = sampler.get_2d();
+        if lobe < diffuse {
+            let r = u1.sqrt();
+            let phi = 2.0 * PI * u2;
+            return Some(Vec3::new(r * phi.cos(), r * phi.sin(), (1.0 - u1).sqrt()));
+        }
+        let m = if lobe < diffuse + specular {
+            self.ggx.sample_visible_normal(wo, (u1, u2))
+        } else {
+            let alpha2 = self.clearcoat_alpha * self.clearcoat_alpha;
+            let cos_theta = ((1.0 - alpha2.powf(1.0 - u1)) / (1.0 - alpha2)).sqrt();
+            let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+            let phi = 2.0 * PI * u2;
+            Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta)
+        };
+        let cos_o = wo.dot(&m);
+        if cos_o <= 0.0 {
+            return None;
+        }
+        if lobe >= diffuse + specular || sampler.get_1d() < self.reflection_probability(cos_o) {
+            return Some(2.0 * cos_o * m - *wo);
+        }
+        let sin2_t = (1.0 - cos_o * cos_o) / (self.eta * self.eta);
+        if sin2_t >= 1.0 {
+            return None;
+        }
+        Some(-*wo / self.eta + (cos_o / self.eta - (1.0 - sin2_t).sqrt()) * m)
+    }
+
+    /// The BSDF times |cos θi| and the density with which [`Lobes::sample`] returns `wi`.
+    fn evaluate(&self, wo: &Vec3, wi: &Vec3) -> (Color, f64) {
+        let [diffuse, specular, clearcoat] = self.lobe_probabilities(wo);
+        if wi.z > 0.0 {
+            let h = (*wo + *wi).make_unit_vector();
+            let cos_d = wi.dot(&h);
+            let (fl, fv) = (schlick_weight(wi.z), schlick_weight(wo.z));
+            let mut value = Color::new_black();
+            let mut pdf = diffuse * wi.z / PI;
+
+            if self.diffuse > 0.0 {
+                // Burley's diffuse with its retroreflection at grazing angles on rough surfaces,
+                // blended into the Hanrahan–Krueger inspired subsurface approximation.
+                let fd90 = 0.5 + 2.0 * self.roughness * cos_d * cos_d;
+                let retro = (1.0 + (fd90 - 1.0) * fl) * (1.0 + (fd90 - 1.0) * fv);
+                let fss90 = self.roughness * cos_d * cos_d;
+                let fss = (1.0 + (fss90 - 1.0) * fl) * (1.0 + (fss90 - 1.0) * fv);
+                let flat = 1.25 * (fss * (1.0 / (wi.z + wo.z) - 0.5) + 0.5);
+                let lambert = retro + (flat - retro) * self.subsurface;
+                value += self.base_color * (self.diffuse * lambert * wi.z / PI);
+            }
+            value += self.sheen * (schlick_weight(cos_d) * wi.z);
+
+            let d = self.ggx.d(&h);
+            value += self.specular_reflectance(cos_d) * (d * self.ggx.g2(wo, wi) / (4.0 * wo.z));
+            pdf +=
+                specular * self.reflection_probability(cos_d) * self.ggx.visible_normal_pdf(wo, &h)
+                    / (4.0 * cos_d);
+
+            if self.clearcoat > 0.0 {
+                let d = gtr1(h.z, self.clearcoat_alpha);
+                let coat = Ggx { alpha: 0.25 };
+                let fresnel = 0.04 + 0.96 * schlick_weight(cos_d);
+                value += Color::new_white()
+                    * (self.clearcoat * fresnel * d * coat.g1(wo) * coat.g1(wi) / (4.0 * wo.z));
+                pdf += clearcoat * d * h.z / (4.0 * cos_d);
+            }
+            (value, pdf)
+        } else if wi.z < 0.0 && self.glass > 0.0 {
+            // The microfacet normal that refracts wo into wi.
+            let m = *wo + self.eta * *wi;
+            if m.squared_length() == 0.0 {
+                return (Color::new_black(), 0.0);
+            }
+            let m = if m.z < 0.0 { -m } else { m }.make_unit_vector();
+            let (cos_o, cos_i) = (wo.dot(&m), wi.dot(&m));
+            if cos_o <= 0.0 || cos_i >= 0.0 {
+                return (Color::new_black(), 0.0);
+            }
+            let jacobian = -cos_i / (cos_i + cos_o / self.eta).powi(2);
+            let d = self.ggx.d(&m);
+            (
+                self.specular_transmittance(cos_o)
+                    * (d * self.ggx.g2(wo, wi) * cos_o * jacobian / wo.z),
+                specular
+                    * (1.0 - self.reflection_probability(cos_o))
+                    * self.ggx.visible_normal_pdf(wo, &m)
+                    * jacobian,
+            )
+        } else {
+            (Color::new_black(), 0.0)
+        }
+    }
+}
+
+/// Schlick's (1 - cos θ)⁵ by which Fresnel reflectance rises towards grazing angles.
+fn schlick_weight(cos_theta: f64) -> f64 {
+    (1.0 - cos_theta).clamp(0.0, 1.0).powi(5)
+}
+
+/// Berry's distribution of normals, GTR with γ = 1, whose long tail gives the clearcoat its
+/// haze.
+fn gtr1(cos_theta: f64, alpha: f64) -> f64 {
+    let alpha2 = alpha * alpha;
+    (alpha2 - 1.0) / (PI * alpha2.ln() * (1.0 + (alpha2 - 1.0) * cos_theta * cos_theta))
+}
+
+/// Samples one lobe in proportion to its estimated reflectance. The weight divides the whole
+/// BSDF by the combined density of all lobes, which keeps the variance low wherever lobes
+/// overlap.
+pub fn principled_scatter<S: Sampler + ?Sized>(
+    incoming_ray: &Ray,
+    normal: &Vec3,
+    point: &Vec3,
+    face: Face,
+    principled: &Principled,
+    base_color: &Color,
+    sampler: &mut S,
+) -> ScatterResult {
+    let frame = Onb::from_w(normal);
+    let wo = frame.to_local(&-incoming_ray.direction.make_unit_vector());
+    if wo.z <= 0.0 {
+        return ScatterResult::Absorbed;
+    }
+    let lobes = Lobes::new(principled, base_color, face);
+    let wi = match lobes.sample(&wo, sampler) {
+        Some(wi) => wi,
+        None => return ScatterResult::Absorbed,
+    };
+    let (value, pdf) = lobes.evaluate(&wo, &wi);
+    if pdf <= 0.0 {
+        return ScatterResult::Absorbed;
+    }
+    ScatterResult::Scattered {
+        attenuation: value / pdf,
+        scattered: Ray::new(*point, frame.local(wi.x, wi.y, wi.z)),
+        pdf: Some(pdf),
+    }
+}
+
+/// The principled BSDF times |cos θi|, for reflection into the hemisphere of the incoming ray
+/// and transmission into the other one.
+pub fn principled_evaluate(
+    incoming_ray: &Ray,
+    normal: &Vec3,
+    direction: &Vec3,
+    face: Face,
+    principled: &Principled,
+    base_color: &Color,
+) -> BsdfEvaluation {
+    let frame = Onb::from_w(normal);
+    let wo = frame.to_local(&-incoming_ray.direction.make_unit_vector());
+    let wi = frame.to_local(&direction.make_unit_vector());
+    if wo.z <= 0.0 {
+        return BsdfEvaluation {
+            value: Color::new_black(),
+            pdf: 0.0,
+        };
+    }
+    let (value, pdf) = Lobes::new(principled, base_color, face).evaluate(&wo, &wi);
+    BsdfEvaluation { value, pdf }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::{principled_evaluate, principled_scatter, Principled};
+    use crate::color::Color;
+    use crate::conductor::{conductor_evaluate, Conductor};
+    use crate::hittable::Face;
+    use crate::material::ScatterResult;
+    use crate::microfacet::tests::hemisphere;
+    use crate::onb::Onb;
+    use crate::ray::Ray;
+    use crate::vec3::Vec3;
+
+    fn materials() -> Vec<(&'static str, Principled, Face)> {
+        let glass = Principled {
+            transmission: 1.0,
+            roughness: 0.4,
+            ..Principled::default()
+        };
+        vec![
+            ("plastic", Principled::default(), Face::Outside),
+            (
+                "cloth",
+                Principled {
+                    roughness: 0.9,
+                    sheen: 1.0,
+                    subsurface: 0.7,
+                    ..Principled::default()
+                },
+                Face::Outside,
+            ),
+            (
+                "metal",
+                Principled {
+                    metallic: 1.0,
+                    roughness: 0.3,
+                    ..Principled::default()
+                },
+                Face::Outside,
+            ),
+            (
+                "car paint",
+                Principled {
+                    metallic: 0.5,
+                    clearcoat: 1.0,
+                    clearcoat_gloss: 0.0,
+                    specular_tint: 1.0,
+                    ..Principled::default()
+                },
+                Face::Outside,
+            ),
+            ("glass", glass.clone(), Face::Outside),
+            ("glass from inside", glass, Face::Inside),
+        ]
+    }
+
+    #[test]
+    fn sampling_agrees_with_evaluation_and_conserves_energy() {
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+        let incoming = Ray::new(Vec3::new(-1.0, 1.0, 0.0), Vec3::new(1.0, -1.5, 0.2));
+        let frame = Onb::from_w(&normal);
+        let base_color = Color::new(0.9, 0.6, 0.3);
+        let mut rng = StdRng::seed_from_u64(14);
+        for (name, material, face) in materials() {
+            let samples = 50_000;
+            let mut albedo = Color::new_black();
+            for _ in 0..samples {
+                if let ScatterResult::Scattered {
+                    attenuation,
+                    scattered,
+                    pdf,
+                } = principled_scatter(
+                    &incoming,
+                    &normal,
+                    &Vec3::origin(),
+                    face,
+                    &material,
+                    &base_color,
+                    &mut rng,
+                ) {
+                    let evaluation = principled_evaluate(
+                        &incoming,
+                        &normal,
+                        &scattered.direction,
+                        face,
+                        &material,
+                        &base_color,
+                    );
+                    let pdf = pdf.unwrap();
+                    assert!((evaluation.pdf - pdf).abs() < 1e-9 * pdf, "{}", name);
+                    assert!((evaluation.value / pdf - attenuation).length() < 1e-9);
+                    albedo += attenuation / samples as f64;
+                }
+            }
+
+            // Every lobe is sampled: the sampled light matches the integrated light, and the
+            // densities of all lobes together integrate to one, less the few samples reflected
+            // below the surface by steep microfacets.
+            let (mut expected, mut total_pdf) = (Color::new_black(), 0.0);
+            for &side in &[1.0, -1.0] {
+                for (w, dw) in hemisphere() {
+                    let direction = frame.local(w.x, w.y, side * w.z);
+                    let evaluation = principled_evaluate(
+                        &incoming,
+                        &normal,
+                        &direction,
+                        face,
+                        &material,
+                        &base_color,
+                    );
+                    expected += evaluation.value * dw;
+                    total_pdf += evaluation.pdf * dw;
+                }
+            }
+            assert!(
+                (albedo - expected).length() < 0.02,
+                "{} {:?} {:?}",
+                name,
+                albedo,
+                expected
+            );
+            assert!(
+                total_pdf < 1.01 && total_pdf > 0.95,
+                "{} {}",
+                name,
+                total_pdf
+            );
+            assert!(
+                expected.x < 1.05 && expected.x > 0.3,
+                "{} {:?}",
+                name,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn white_metal_matches_a_perfect_conductor() {
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+        let incoming = Ray::new(Vec3::new(-1.0, 1.0, 0.0), Vec3::new(1.0, -0.8, 0.3));
+        let metal = Principled {
+            metallic: 1.0,
+            roughness: 0.4,
+            ..Principled::default()
+        };
+        let mirror = Conductor::new(Color::new_white(), Color::new(1e4, 1e4, 1e4), 0.4);
+        for &direction in &[
+            Vec3::new(0.8, 1.0, 0.1),
+            Vec3::new(0.2, 0.5, -0.4),
+            Vec3::new(-0.6, 0.3, 0.0),
+        ] {
+            let principled = principled_evaluate(
+                &incoming,
+                &normal,
+                &direction,
+                Face::Outside,
+                &metal,
+                &Color::new_white(),
+            );
+            let conductor = conductor_evaluate(&incoming, &normal, &direction, &mirror);
+            assert!((principled.value - conductor.value).length() < 1e-6 * conductor.value.x);
+        }
+    }
+}
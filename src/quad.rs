@@ -0,0 +1,178 @@
+use crate::aabb::Aabb;
+use crate::hittable::{get_face_normal, HitRecord, Hittable};
+use crate::light::Light;
+use crate::material_variants::MaterialVariants;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::vec3::Vec3;
+
+/// Parallelogram spanned by the edges `u` and `v` from the corner `corner`. Its front face is the
+/// one `u × v` points to, and the surface coordinates run from 0 to 1 along both edges.
+#[derive(Clone)]
+pub struct Quad {
+    pub corner: Vec3,
+    pub u: Vec3,
+    pub v: Vec3,
+    pub material: MaterialVariants,
+    normal: Vec3,
+    /// `n / (n · n)` for the unnormalized normal `n`, used to find the surface coordinates.
+    w: Vec3,
+    area: f64,
+}
+
+impl Quad {
+    pub fn new(corner: Vec3, u: Vec3, v: Vec3, material: MaterialVariants) -> Quad {
+        let n = u.cross(&v);
+        Quad {
+            corner,
+            u,
+            v,
+            material,
+            normal: n.make_unit_vector(),
+            w: n / n.squared_length(),
+            area: n.length(),
+        }
+    }
+
+    pub fn normal(&self) -> Vec3 {
+        self.normal
+    }
+
+    pub fn area(&self) -> f64 {
+        self.area
+    }
+
+    fn contains(&self, p: &Vec3) -> Option<(f64, f64)> {
+        let planar = *p - self.corner;
+        let alpha = self.w.dot(&planar.cross(&self.v));
+        let beta = self.w.dot(&self.u.cross(&planar));
+        if (0.0..=1.0).contains(&alpha) && (0.0..=1.0).contains(&beta) {
+            Some((alpha, beta))
+        } else {
+            None
+        }
+    }
+}
+
+/// Ray parameter of the intersection with the plane through `point` with unit `normal`.
+pub fn intersect_plane(
+    r: &Ray,
+    point: &Vec3,
+    normal: &Vec3,
+    t_min: f64,
+    t_max: f64,
+) -> Option<f64> {
+    let denominator = normal.dot(&r.direction);
+    if denominator.abs() < 1e-12 {
+        return None;
+    }
+    let t = normal.dot(&(*point - r.origin)) / denominator;
+    if t <= t_min || t >= t_max {
+        return None;
+    }
+    Some(t)
+}
+
+/// Solid angle density of sampling a flat light of `area` uniformly, for a `direction` from
+/// `origin` that hits it at ray parameter `t`.
+pub fn area_solid_angle_pdf(direction: &Vec3, normal: &Vec3, t: f64, area: f64) -> f64 {
+    let length = direction.length();
+    let distance_squared = (t * length).powi(2);
+    let cosine = (direction.dot(normal) / length).abs();
+    if cosine == 0.0 || area == 0.0 {
+        return 0.0;
+    }
+    distance_squared / (cosine * area)
+}
+
+impl Hittable for Quad {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let t = intersect_plane(r, &self.corner, &self.normal, t_min, t_max)?;
+        let p = r.at(t);
+        let (u, v) = self.contains(&p)?;
+        let (face, normal) = get_face_normal(r, &self.normal);
+        Some(HitRecord {
+            p,
+            t,
+            u,
+            v,
+            normal,
+            face,
+            material: &self.material,
+        })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::from_points(&[
+            self.corner,
+            self.corner + self.u,
+            self.corner + self.v,
+            self.corner + self.u + self.v,
+        ]))
+    }
+}
+
+impl Light for Quad {
+    fn sample_direction(&self, origin: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        let (a, b) = sampler.get_2d();
+        self.corner + a * self.u + b * self.v - *origin
+    }
+
+    fn pdf_value(&self, origin: &Vec3, direction: &Vec3) -> f64 {
+        match self.hit(&Ray::new(*origin, *direction), 0.001, f64::INFINITY) {
+            Some(hit) => area_solid_angle_pdf(direction, &self.normal, hit.t, self.area),
+            None => 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::Quad;
+    use crate::color::Color;
+    use crate::hittable::{Face, Hittable};
+    use crate::light::Light;
+    use crate::material_variants::MaterialVariants;
+    use crate::ray::Ray;
+    use crate::vec3::Vec3;
+
+    fn quad() -> Quad {
+        Quad::new(
+            Vec3::new(-1.0, -1.0, 0.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(0.0, 4.0, 0.0),
+            MaterialVariants::Lambertian(Color::new_white().into()),
+        )
+    }
+
+    #[test]
+    fn hit_faces_and_uv() {
+        let quad = quad();
+        let front = Ray::new(Vec3::new(0.5, 0.0, 2.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = quad.hit(&front, 0.0, f64::INFINITY).unwrap();
+        assert_eq!(hit.face, Face::Outside);
+        assert_eq!(hit.normal, Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!((hit.u, hit.v), (0.75, 0.25));
+
+        let back = Ray::new(Vec3::new(0.5, 0.0, -2.0), Vec3::new(0.0, 0.0, 1.0));
+        let hit = quad.hit(&back, 0.0, f64::INFINITY).unwrap();
+        assert_eq!(hit.face, Face::Inside);
+        assert_eq!(hit.normal, Vec3::new(0.0, 0.0, -1.0));
+
+        let outside = Ray::new(Vec3::new(1.5, 0.0, 2.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(quad.hit(&outside, 0.0, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn light_pdf_of_distant_quad() {
+        let quad = quad();
+        let origin = Vec3::new(0.0, 1.0, 100.0);
+        let direction = quad.sample_direction(&origin, &mut StdRng::seed_from_u64(1));
+        let pdf = quad.pdf_value(&origin, &direction);
+        // Seen from far away the quad covers about area / distance² steradians.
+        assert!((pdf / (100.0 * 100.0 / 8.0) - 1.0).abs() < 0.01);
+    }
+}
@@ -4,11 +4,23 @@ use crate::Vec3;
 pub struct Ray {
     pub origin: Vec3,
     pub direction: Vec3,
+    /// Moment within the camera shutter interval the ray travels at; moving objects are hit
+    /// where they are at this time.
+    pub time: f64,
 }
 
 impl Ray {
+    /// A ray at time 0.
     pub fn new(origin: Vec3, direction: Vec3) -> Ray {
-        Ray { origin, direction }
+        Ray::with_time(origin, direction, 0.0)
+    }
+
+    pub fn with_time(origin: Vec3, direction: Vec3, time: f64) -> Ray {
+        Ray {
+            origin,
+            direction,
+            time,
+        }
     }
 
     pub fn at(&self, t: f64) -> Vec3 {
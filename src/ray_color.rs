@@ -1,91 +1,480 @@
+use std::cell::Cell;
+
 use crate::color::Color;
-use crate::hittable::Hittable;
+use crate::environment::Environment;
+use crate::hittable::{HitRecord, Hittable};
+use crate::light::LightList;
 use crate::material::{Material, ScatterResult};
 use crate::ray::Ray;
+use crate::sample_rng::ray_rng;
+use crate::sampler::Sampler;
+use crate::vec3::Vec3;
+
+thread_local! {
+    static RAYS_TRACED: Cell<u64> = const { Cell::new(0) };
+}
 
-fn sky_color(r: &Ray) -> Color {
-    let unit_direction = r.direction.make_unit_vector();
-    let t = 0.5 * unit_direction.y + 1.0_f64;
-    (1.0_f64 - t) * Color::new_white() + t * Color::new(0.5, 0.7, 1.0)
+/// Number of rays, including shadow rays, that [`ray_color`] has traced on the current thread.
+pub fn rays_traced() -> u64 {
+    RAYS_TRACED.with(|rays| rays.get())
 }
 
-pub fn ray_color<T: Hittable>(r: &Ray, world: &T, depth: isize) -> Color {
+fn count_ray() {
+    RAYS_TRACED.with(|rays| rays.set(rays.get() + 1));
+}
+
+/// Estimates the radiance arriving along `r`.
+///
+/// Emission is gathered both by sampling `lights`, and the environment if it supports it,
+/// directly at every non-specular hit and by following the scattered rays; the two estimates are
+/// combined with multiple importance sampling using the power heuristic. Scattering inside
+/// participating media is handled like a surface bounce whose material is a phase function. All
+/// random decisions are drawn from `sampler`, so the estimate is reproducible from its state.
+pub fn ray_color<T: Hittable, S: Sampler>(
+    r: &Ray,
+    world: &T,
+    lights: &LightList,
+    environment: &Environment,
+    depth: isize,
+    sampler: &mut S,
+) -> Color {
+    trace(r, world, lights, environment, depth, None, sampler)
+}
+
+/// Power heuristic weight for a sample drawn with density `pdf` against an `other` strategy.
+fn power_heuristic(pdf: f64, other: f64) -> f64 {
+    let pdf_squared = pdf * pdf;
+    pdf_squared / (pdf_squared + other * other)
+}
+
+/// `bsdf_pdf` is the density with which the previous, non-specular bounce sampled `r`; emission
+/// found along such rays has already been partly accounted for by light sampling.
+fn trace<T: Hittable, S: Sampler>(
+    r: &Ray,
+    world: &T,
+    lights: &LightList,
+    environment: &Environment,
+    depth: isize,
+    bsdf_pdf: Option<f64>,
+    sampler: &mut S,
+) -> Color {
     // If we've exceeded the ray bounce limit, no more light is gathered.
     if depth <= 0 {
         return Color::new_black();
     }
 
-    match world.hit(r, 0.001, f64::INFINITY) {
-        Some(hit_record) => {
-            match hit_record
-                .material
-                .scatter(r, &hit_record.normal, &hit_record.p, hit_record.face)
-            {
-                ScatterResult::Scattered {
-                    attenuation,
-                    scattered,
-                } => attenuation * ray_color(&scattered, world, depth - 1),
-                ScatterResult::Absorbed => Color::new_black(),
-            }
+    count_ray();
+    // Media along the ray draw from a generator of their own, seeded from one dimension taken
+    // before the traversal, so however many of them it meets the later dimensions stay aligned.
+    let mut rng = ray_rng(sampler.get_1d());
+    let hit_record = match world.sample_hit(r, 0.001, f64::INFINITY, &mut rng) {
+        Some(hit_record) => hit_record,
+        None => {
+            let radiance = environment.radiance(&r.direction);
+            return match bsdf_pdf {
+                Some(bsdf_pdf) => {
+                    let light_pdf = light_pdf(lights, environment, &r.origin, &r.direction);
+                    radiance * power_heuristic(bsdf_pdf, light_pdf)
+                }
+                None => radiance,
+            };
+        }
+    };
+
+    let mut emitted = hit_record.material.emitted(&hit_record);
+    if let Some(bsdf_pdf) = bsdf_pdf {
+        let light_pdf = light_pdf(lights, environment, &r.origin, &r.direction);
+        emitted *= power_heuristic(bsdf_pdf, light_pdf);
+    }
+
+    match hit_record.material.scatter(r, &hit_record, sampler) {
+        ScatterResult::Scattered {
+            attenuation,
+            scattered,
+            pdf,
+        } => {
+            let direct = match pdf {
+                Some(_) => sample_lights(r, &hit_record, world, lights, environment, sampler),
+                None => Color::new_black(),
+            };
+            emitted
+                + direct
+                + attenuation
+                    * trace(
+                        &scattered,
+                        world,
+                        lights,
+                        environment,
+                        depth - 1,
+                        pdf,
+                        sampler,
+                    )
         }
-        None => sky_color(&r),
+        ScatterResult::Absorbed => emitted,
     }
 }
 
+/// Picks a direction from `origin` towards one of the lights or, if it is sampled, the
+/// environment, all chosen with equal probability.
+fn sample_light_direction<S: Sampler>(
+    lights: &LightList,
+    environment: &Environment,
+    origin: &Vec3,
+    sampler: &mut S,
+) -> Option<Vec3> {
+    if !environment.is_sampled() {
+        return lights.sample_direction(origin, sampler);
+    }
+    let count = lights.len() + 1;
+    let index = ((sampler.get_1d() * count as f64) as usize).min(count - 1);
+    match lights.lights.get(index) {
+        Some(light) => Some(light.sample_direction(origin, sampler)),
+        None => environment.sample_direction(sampler),
+    }
+}
+
+/// Density of [`sample_light_direction`].
+fn light_pdf(
+    lights: &LightList,
+    environment: &Environment,
+    origin: &Vec3,
+    direction: &Vec3,
+) -> f64 {
+    if !environment.is_sampled() {
+        return lights.pdf_value(origin, direction);
+    }
+    let count = lights.len() as f64;
+    (count * lights.pdf_value(origin, direction) + environment.pdf_value(direction)) / (count + 1.0)
+}
+
+/// Next-event estimation: radiance reflected at `hit_record` from one direction towards the
+/// lights or the environment, weighted against the chance of finding the same emitter by BSDF
+/// sampling.
+fn sample_lights<T: Hittable, S: Sampler>(
+    r: &Ray,
+    hit_record: &HitRecord,
+    world: &T,
+    lights: &LightList,
+    environment: &Environment,
+    sampler: &mut S,
+) -> Color {
+    let direction = match sample_light_direction(lights, environment, &hit_record.p, sampler) {
+        Some(direction) => direction,
+        None => return Color::new_black(),
+    };
+    let light_pdf = light_pdf(lights, environment, &hit_record.p, &direction);
+    if light_pdf <= 0.0 {
+        return Color::new_black();
+    }
+    let evaluation = match hit_record.material.evaluate(r, hit_record, &direction) {
+        Some(evaluation) if evaluation.value != Color::new_black() => evaluation,
+        _ => return Color::new_black(),
+    };
+
+    let shadow_ray = Ray::with_time(hit_record.p, direction, r.time);
+    count_ray();
+    let mut rng = ray_rng(sampler.get_1d());
+    let radiance = match world.sample_hit(&shadow_ray, 0.001, f64::INFINITY, &mut rng) {
+        Some(light_hit) => light_hit.material.emitted(&light_hit),
+        None => environment.radiance(&direction),
+    };
+    evaluation.value * radiance * (power_heuristic(light_pdf, evaluation.pdf) / light_pdf)
+}
+
 #[cfg(test)]
 mod tests {
+    use std::f64::consts::PI;
+    use std::sync::Arc;
     use std::time::Instant;
 
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use crate::color::Color;
+    use crate::constant_medium::ConstantMedium;
+    use crate::environment::{Environment, EnvironmentMap};
+    use crate::hittable::HittableList;
+    use crate::light::LightList;
+    use crate::material_variants::MaterialVariants;
+    use crate::ray::Ray;
+    use crate::sampler::Sampler;
+    use crate::sphere::Sphere;
+    use crate::triangle::Triangle;
+    use crate::vec3::Vec3;
+
+    #[test]
+    fn counts_camera_and_shadow_rays() {
+        let mut scene = HittableList::new();
+        scene.add(Box::new(Sphere::new(
+            Vec3::new(0.0, 0.0, -2.0),
+            0.5,
+            MaterialVariants::Lambertian(Color::new(0.5, 0.5, 0.5).into()),
+        )));
+        let mut lights = LightList::new();
+        lights.add(Box::new(Sphere::new(
+            Vec3::new(0.0, 1.0, 0.0),
+            0.5,
+            MaterialVariants::Emissive(Color::new_white().into()),
+        )));
+        let black = Environment::Constant(Color::new_black());
+        let mut rng = StdRng::seed_from_u64(6);
+        let before = super::rays_traced();
+        // Depth 1 stops after the first bounce: one camera ray and one shadow ray.
+        let r = Ray::new(Vec3::origin(), Vec3::new(0.0, 0.0, -1.0));
+        super::ray_color(&r, &scene, &lights, &black, 1, &mut rng);
+        assert_eq!(super::rays_traced() - before, 2);
+    }
+
+    #[test]
+    fn emission_and_background() {
+        let mut scene = HittableList::new();
+        scene.add(Box::new(Sphere::new(
+            Vec3::new(0.0, 0.0, -2.0),
+            0.5,
+            MaterialVariants::Emissive(Color::new(4.0, 2.0, 1.0).into()),
+        )));
+        let black = Environment::Constant(Color::new_black());
+        let mut rng = StdRng::seed_from_u64(1);
+
+        let towards_light = Ray::new(Vec3::origin(), Vec3::new(0.0, 0.0, -1.0));
+        assert_eq!(
+            super::ray_color(
+                &towards_light,
+                &scene,
+                &LightList::new(),
+                &black,
+                10,
+                &mut rng
+            ),
+            Color::new(4.0, 2.0, 1.0)
+        );
+
+        let away = Ray::new(Vec3::origin(), Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(
+            super::ray_color(&away, &scene, &LightList::new(), &black, 10, &mut rng),
+            Color::new_black()
+        );
+
+        // The inside of the light does not emit.
+        let from_inside = Ray::new(Vec3::new(0.0, 0.0, -2.0), Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(
+            super::ray_color(
+                &from_inside,
+                &scene,
+                &LightList::new(),
+                &black,
+                10,
+                &mut rng
+            ),
+            Color::new_black()
+        );
+    }
+
+    #[test]
+    fn light_sampling_matches_bsdf_sampling() {
+        let lamp = Sphere::new(
+            Vec3::new(0.0, 2.0, 0.0),
+            0.5,
+            MaterialVariants::Emissive(Color::new(4.0, 4.0, 4.0).into()),
+        );
+        let mut scene = HittableList::new();
+        scene.add(Box::new(Triangle::new(
+            Vec3::new(-100.0, 0.0, -100.0),
+            Vec3::new(0.0, 0.0, 100.0),
+            Vec3::new(100.0, 0.0, -100.0),
+            MaterialVariants::Lambertian(Color::new(0.5, 0.5, 0.5).into()),
+        )));
+        scene.add(Box::new(lamp.clone()));
+        let mut lights = LightList::new();
+        lights.add(Box::new(lamp));
+        let black = Environment::Constant(Color::new_black());
+
+        let r = Ray::new(Vec3::new(0.0, 1.0, 1.0), Vec3::new(0.0, -1.0, -1.0));
+        let samples = 200_000;
+        let mut rng = StdRng::seed_from_u64(2);
+        let mut average = |lights: &LightList| {
+            (0..samples)
+                .map(|_| super::ray_color(&r, &scene, lights, &black, 2, &mut rng).x)
+                .sum::<f64>()
+                / samples as f64
+        };
+        let with_lights = average(&lights);
+        let without_lights = average(&LightList::new());
+        // The floor sees a lamp of solid angle 2π(1 - cos θ) straight above it.
+        let cos_theta_max = (1.0 - 0.25 / 4.0_f64).sqrt();
+        let expected = 0.5 * 4.0 * (1.0 - cos_theta_max * cos_theta_max);
+        assert!((with_lights - expected).abs() < 0.01 * expected);
+        assert!((without_lights - expected).abs() < 0.05 * expected);
+    }
+
+    #[test]
+    fn environment_sampling_finds_the_sun() {
+        let (width, height) = (32, 16);
+        let mut pixels = vec![Color::new(0.2, 0.3, 0.4); width * height];
+        pixels[4 * width + 20] = Color::new(500.0, 500.0, 500.0);
+        let map = EnvironmentMap::from_pixels(width, height, pixels).with_rotation(10.0);
+        let environment = Environment::Image(Arc::new(map));
+        let mut scene = HittableList::new();
+        scene.add(Box::new(Triangle::new(
+            Vec3::new(-100.0, 0.0, -100.0),
+            Vec3::new(0.0, 0.0, 100.0),
+            Vec3::new(100.0, 0.0, -100.0),
+            MaterialVariants::Lambertian(Color::new(0.5, 0.5, 0.5).into()),
+        )));
+
+        // Radiance reflected by the floor: the albedo over π times the irradiance from the sky.
+        let steps = 1000;
+        let mut irradiance = 0.0;
+        for i in 0..steps {
+            for j in 0..steps {
+                let cos_theta = (i as f64 + 0.5) / steps as f64;
+                let phi = 2.0 * PI * (j as f64 + 0.5) / steps as f64;
+                let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+                let direction = Vec3::new(sin_theta * phi.cos(), cos_theta, sin_theta * phi.sin());
+                irradiance += environment.radiance(&direction).x * cos_theta;
+            }
+        }
+        irradiance *= 2.0 * PI / (steps * steps) as f64;
+        let expected = 0.5 / PI * irradiance;
+
+        let r = Ray::new(Vec3::new(0.0, 1.0, 1.0), Vec3::new(0.0, -1.0, -1.0));
+        let samples = 20_000;
+        let mut rng = StdRng::seed_from_u64(7);
+        let lights = LightList::new();
+        let average = (0..samples)
+            .map(|_| super::ray_color(&r, &scene, &lights, &environment, 2, &mut rng).x)
+            .sum::<f64>()
+            / samples as f64;
+        assert!(
+            (average - expected).abs() < 0.02 * expected,
+            "{} {}",
+            average,
+            expected
+        );
+    }
+
+    /// Counts the dimensions drawn from a generator.
+    struct CountingSampler {
+        rng: StdRng,
+        dimensions: usize,
+    }
+
+    impl Sampler for CountingSampler {
+        fn get_1d(&mut self) -> f64 {
+            self.dimensions += 1;
+            self.rng.get_1d()
+        }
+
+        fn get_2d(&mut self) -> (f64, f64) {
+            self.dimensions += 2;
+            self.rng.get_2d()
+        }
+    }
+
+    #[test]
+    fn media_along_a_ray_take_one_dimension() {
+        // Media too thin to scatter in, in front of the environment.
+        let dimensions = |media: usize| {
+            let mut scene = HittableList::new();
+            for i in 0..media {
+                scene.add(Box::new(ConstantMedium::new(
+                    Sphere::new(
+                        Vec3::new(0.0, 0.0, -3.0 * (i + 1) as f64),
+                        1.0,
+                        MaterialVariants::Lambertian(Color::new_white().into()),
+                    ),
+                    1e-12,
+                    MaterialVariants::Isotropic(Color::new_white().into()),
+                )));
+            }
+            let mut sampler = CountingSampler {
+                rng: StdRng::seed_from_u64(8),
+                dimensions: 0,
+            };
+            let r = Ray::new(Vec3::origin(), Vec3::new(0.0, 0.0, -1.0));
+            let gradient = Environment::Gradient;
+            super::ray_color(&r, &scene, &LightList::new(), &gradient, 10, &mut sampler);
+            sampler.dimensions
+        };
+        assert_eq!(dimensions(0), 1);
+        assert_eq!(dimensions(5), 1);
+    }
+
+    #[test]
+    fn white_smoke_in_glass_conserves_energy() {
+        // A furnace test: without absorption every path ends in the uniform environment.
+        let glass = Sphere::new(Vec3::origin(), 1.0, MaterialVariants::Dielectric(1.5, 0.0));
+        let mut scene = HittableList::new();
+        scene.add(Box::new(glass.clone()));
+        scene.add(Box::new(ConstantMedium::new(
+            glass,
+            2.0,
+            MaterialVariants::HenyeyGreenstein(Color::new_white().into(), 0.6),
+        )));
+        let white = Environment::Constant(Color::new_white());
+
+        let r = Ray::new(Vec3::new(0.0, 0.0, 3.0), Vec3::new(0.1, 0.0, -1.0));
+        let samples = 10_000;
+        let mut rng = StdRng::seed_from_u64(3);
+        let average = (0..samples)
+            .map(|_| super::ray_color(&r, &scene, &LightList::new(), &white, 1000, &mut rng).x)
+            .sum::<f64>()
+            / samples as f64;
+        assert!((average - 1.0).abs() < 1e-3, "{}", average);
+    }
+
     #[test]
     #[ignore]
     fn stupid_benchmark() {
-        use crate::color::Color;
-        use crate::hittable::HittableList;
-        use crate::material_variants::MaterialVariants;
-        use crate::ray::Ray;
-        use crate::sphere::Sphere;
-        use crate::vec3::Vec3;
-
         let mut scene = HittableList::new();
         scene.add(Box::new(Sphere::new(
             Vec3::new(-0.05, 0.05, -1.0),
             0.5,
-            MaterialVariants::Dielectric(1.5),
+            MaterialVariants::Dielectric(1.5, 0.0),
         )));
         scene.add(Box::new(Sphere::new(
             Vec3::new(0.05, 0.05, -2.0),
             0.5,
-            MaterialVariants::Dielectric(2.5),
+            MaterialVariants::Dielectric(2.5, 0.0),
         )));
         scene.add(Box::new(Sphere::new(
             Vec3::new(-0.05, 0.05, -2.0),
             0.5,
-            MaterialVariants::Dielectric(2.5),
+            MaterialVariants::Dielectric(2.5, 0.0),
         )));
         scene.add(Box::new(Sphere::new(
             Vec3::new(0.05, 0.05, -3.0),
             0.5,
-            MaterialVariants::Dielectric(2.5),
+            MaterialVariants::Dielectric(2.5, 0.0),
         )));
         scene.add(Box::new(Sphere::new(
             Vec3::new(0.0, 0.0, -4.0),
             0.6,
-            MaterialVariants::Metal(Color::new(0.8, 0.6, 0.2), 0.1),
+            MaterialVariants::Metal(Color::new(0.8, 0.6, 0.2).into(), 0.1),
         )));
         // Large sphere behind
         scene.add(Box::new(Sphere::new(
             Vec3::new(0.0, 0.0, 1.0),
             10.0,
-            MaterialVariants::Metal(Color::new(0.8, 0.6, 0.2), 0.1),
+            MaterialVariants::Metal(Color::new(0.8, 0.6, 0.2).into(), 0.1),
         )));
 
         let ray = Ray::new(Vec3::origin(), Vec3::new(0.0, 0.0, 1.0));
         let num_iter = 1_000_000;
         let max_depth = 10;
         let tic = Instant::now();
+        let lights = LightList::new();
+        let mut rng = StdRng::seed_from_u64(4);
         let mut acc = Color::new_black();
         for _ in 0..num_iter {
-            acc += super::ray_color(&ray, &scene, max_depth);
+            acc += super::ray_color(
+                &ray,
+                &scene,
+                &lights,
+                &Environment::Gradient,
+                max_depth,
+                &mut rng,
+            );
         }
         let toc = Instant::now();
         println!(
@@ -0,0 +1,446 @@
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use indicatif::{ProgressBar, ProgressStyle};
+use rayon::prelude::*;
+
+use crate::adaptive::{AdaptiveSampling, PixelStatistics};
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::environment::Environment;
+use crate::film::{Film, FilmTile};
+use crate::filter::Filter;
+use crate::framebuffer::Framebuffer;
+use crate::hittable::Hittable;
+use crate::light::LightList;
+use crate::ray_color::{ray_color, rays_traced};
+use crate::sample_rng::sample_rng;
+use crate::sampler::{HaltonSampler, Sampler, SamplerKind, SobolSampler, StratifiedSampler};
+use crate::tiles::tiles;
+
+#[derive(Debug, Clone, Copy)]
+pub struct RenderSettings {
+    pub width: usize,
+    pub height: usize,
+    pub samples_per_pixel: usize,
+    pub max_depth: isize,
+    /// Together with the pixel and sample index, determines every random decision; the same
+    /// seed gives the same image regardless of the number of threads.
+    pub seed: u64,
+    pub sampler: SamplerKind,
+    pub filter: Filter,
+    /// Lets pixels stop before `samples_per_pixel` once they have converged.
+    pub adaptive: Option<AdaptiveSampling>,
+}
+
+impl RenderSettings {
+    pub fn aspect_ratio(&self) -> f64 {
+        (self.width as f64) / (self.height as f64)
+    }
+}
+
+impl Default for RenderSettings {
+    fn default() -> RenderSettings {
+        RenderSettings {
+            width: 1920,
+            height: 1080,
+            samples_per_pixel: 500,
+            max_depth: 50,
+            seed: 0,
+            sampler: SamplerKind::default(),
+            filter: Filter::default(),
+            adaptive: None,
+        }
+    }
+}
+
+pub struct Renderer {
+    pub settings: RenderSettings,
+    progress: ProgressBar,
+}
+
+/// Merges finished tiles into the film in the order they were handed out, so the sums do not
+/// depend on the scheduling.
+struct TileMerger {
+    film: Film,
+    finished: BTreeMap<usize, FilmTile>,
+    next: usize,
+}
+
+impl TileMerger {
+    fn add(&mut self, index: usize, tile: FilmTile) {
+        self.finished.insert(index, tile);
+        while let Some(tile) = self.finished.remove(&self.next) {
+            self.film.merge(tile);
+            self.next += 1;
+        }
+    }
+}
+
+impl Renderer {
+    pub fn new(settings: RenderSettings) -> Renderer {
+        Renderer {
+            settings,
+            progress: ProgressBar::hidden(),
+        }
+    }
+
+    /// Shows a progress bar on the terminal with the number of pixels done, the estimated time
+    /// left, the number of samples taken and the number of rays traced per second.
+    pub fn with_progress_bar(self) -> Renderer {
+        let progress = ProgressBar::new(0).with_style(
+            ProgressStyle::default_bar()
+                .template("{elapsed_precise} [{wide_bar}] {pos}/{len} pixels, {eta} left, {msg}"),
+        );
+        Renderer { progress, ..self }
+    }
+
+    /// Renders `world` as seen by `camera` and returns the filtered linear radiance per pixel.
+    /// `lights` are the emitters in `world` that are sampled directly.
+    pub fn render<T: Hittable>(
+        &self,
+        world: &T,
+        lights: &LightList,
+        environment: &Environment,
+        camera: &Camera,
+    ) -> Framebuffer {
+        self.render_film(world, lights, environment, camera)
+            .to_framebuffer()
+    }
+
+    /// Like [`Renderer::render`], but returns the film, which also knows how many samples each
+    /// pixel took.
+    ///
+    /// The image is split into tiles that are rendered in parallel, in the order of a Hilbert
+    /// curve.
+    pub fn render_film<T: Hittable>(
+        &self,
+        world: &T,
+        lights: &LightList,
+        environment: &Environment,
+        camera: &Camera,
+    ) -> Film {
+        let RenderSettings {
+            width,
+            height,
+            filter,
+            ..
+        } = self.settings;
+
+        let merger = Mutex::new(TileMerger {
+            film: Film::new(width, height, filter),
+            finished: BTreeMap::new(),
+            next: 0,
+        });
+        self.progress.set_length((width * height) as u64);
+        let start = Instant::now();
+        let samples = AtomicU64::new(0);
+        let rays = AtomicU64::new(0);
+        // Bridging hands the tiles out in order, so only the ones still in flight can hold up
+        // the merging.
+        tiles(width, height)
+            .enumerate()
+            .par_bridge()
+            .for_each(|(index, (columns, rows))| {
+                let mut tile = merger
+                    .lock()
+                    .unwrap()
+                    .film
+                    .tile(columns.clone(), rows.clone());
+                let rays_before = rays_traced();
+                let taken = self.render_tile(
+                    world,
+                    lights,
+                    environment,
+                    camera,
+                    (columns.clone(), rows.clone()),
+                    &mut tile,
+                );
+                let traced = rays_traced() - rays_before;
+                merger.lock().unwrap().add(index, tile);
+
+                let samples = samples.fetch_add(taken, Ordering::Relaxed) + taken;
+                let rays = rays.fetch_add(traced, Ordering::Relaxed) + traced;
+                self.progress.inc((columns.len() * rows.len()) as u64);
+                self.progress.set_message(&format!(
+                    "{} samples, {:.2} Mrays/s",
+                    samples,
+                    rays as f64 / start.elapsed().as_secs_f64() / 1e6
+                ));
+            });
+        self.progress.finish();
+
+        merger.into_inner().unwrap().film
+    }
+
+    /// Samples the pixels `columns` × `rows` into `tile` and returns the number of samples
+    /// taken.
+    fn render_tile<T: Hittable>(
+        &self,
+        world: &T,
+        lights: &LightList,
+        environment: &Environment,
+        camera: &Camera,
+        (columns, rows): (Range<usize>, Range<usize>),
+        tile: &mut FilmTile,
+    ) -> u64 {
+        let RenderSettings {
+            width,
+            samples_per_pixel,
+            seed,
+            sampler,
+            adaptive,
+            ..
+        } = self.settings;
+        let spp = samples_per_pixel as u64;
+        let mut taken = 0;
+        for row in rows {
+            for column in columns.clone() {
+                let pixel = (row * width + column) as u64;
+                let mut statistics = PixelStatistics::default();
+                for sample in 0..spp {
+                    let (position, color) = match sampler {
+                        SamplerKind::Independent => self.sample(
+                            world,
+                            lights,
+                            environment,
+                            camera,
+                            (column, row),
+                            &mut sample_rng(seed, pixel, sample),
+                        ),
+                        SamplerKind::Stratified => self.sample(
+                            world,
+                            lights,
+                            environment,
+                            camera,
+                            (column, row),
+                            &mut StratifiedSampler::new(seed, pixel, sample, spp),
+                        ),
+                        SamplerKind::Halton => self.sample(
+                            world,
+                            lights,
+                            environment,
+                            camera,
+                            (column, row),
+                            &mut HaltonSampler::new(seed, pixel, sample),
+                        ),
+                        SamplerKind::Sobol => self.sample(
+                            world,
+                            lights,
+                            environment,
+                            camera,
+                            (column, row),
+                            &mut SobolSampler::new(seed, pixel, sample),
+                        ),
+                    };
+                    tile.add_sample(position, color);
+                    statistics.add(color.luminance());
+                    if adaptive.is_some_and(|adaptive| adaptive.converged(&statistics)) {
+                        break;
+                    }
+                }
+                taken += statistics.count() as u64;
+            }
+        }
+        taken
+    }
+
+    /// Film position and radiance of one sample in pixel `(column, row)`, counting rows from
+    /// the top. The position within the pixel takes the first two dimensions of `sampler`, the
+    /// camera the next three.
+    fn sample<T: Hittable, S: Sampler>(
+        &self,
+        world: &T,
+        lights: &LightList,
+        environment: &Environment,
+        camera: &Camera,
+        (column, row): (usize, usize),
+        sampler: &mut S,
+    ) -> ((f64, f64), Color) {
+        let RenderSettings {
+            width,
+            height,
+            max_depth,
+            ..
+        } = self.settings;
+        let (dx, dy) = sampler.get_2d();
+        let (x, y) = (column as f64 + dx, row as f64 + dy);
+        // The viewport spans the image from edge to edge.
+        let u = x / width as f64;
+        let v = (height as f64 - y) / height as f64;
+        let r = camera.get_ray(u, v, sampler);
+        let color = ray_color(&r, world, lights, environment, max_depth, sampler);
+        ((x, y), color)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RenderSettings, Renderer};
+    use crate::adaptive::AdaptiveSampling;
+    use crate::bvh::Bvh;
+    use crate::color::Color;
+    use crate::environment::Environment;
+    use crate::film::Film;
+    use crate::filter::{Filter, FilterKind};
+    use crate::light::LightList;
+    use crate::sampler::{SamplerKind, SAMPLERS};
+    use crate::scenes::{builtin_scene, cornell_smoke};
+
+    #[test]
+    fn same_seed_gives_identical_images_on_any_number_of_threads() {
+        let scene = builtin_scene("random", 3).unwrap();
+        let world = Bvh::new(scene.world.objects);
+        let settings = RenderSettings {
+            width: 24,
+            height: 16,
+            samples_per_pixel: 4,
+            max_depth: 10,
+            seed: 42,
+            sampler: SamplerKind::default(),
+            filter: Filter::default(),
+            adaptive: None,
+        };
+        let camera = scene.camera.build(settings.aspect_ratio());
+        let render = |threads: usize, seed: u64| {
+            let renderer = Renderer::new(RenderSettings { seed, ..settings });
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap()
+                .install(|| {
+                    renderer.render(&world, &LightList::new(), &Environment::Gradient, &camera)
+                })
+                .pixels
+        };
+        let single = render(1, 42);
+        assert_eq!(single, render(4, 42));
+        assert_ne!(single, render(4, 43));
+
+        // Lights and media draw from the same samplers, and the tiles of a wide filter overlap.
+        let settings = RenderSettings {
+            filter: Filter::new(FilterKind::Mitchell, 2.0),
+            ..settings
+        };
+        let (world, lights) = cornell_smoke();
+        let black = Environment::Constant(Color::new_black());
+        for name in &SAMPLERS {
+            let sampler = name.parse().unwrap();
+            let render = |threads: usize| {
+                let renderer = Renderer::new(RenderSettings {
+                    sampler,
+                    ..settings
+                });
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(threads)
+                    .build()
+                    .unwrap()
+                    .install(|| renderer.render(&world, &lights, &black, &camera))
+                    .pixels
+            };
+            assert_eq!(render(1), render(3), "{}", name);
+        }
+    }
+
+    #[test]
+    fn images_one_pixel_wide_or_high_see_the_scene() {
+        let scene = builtin_scene("random", 3).unwrap();
+        let world = Bvh::new(scene.world.objects);
+        for &(width, height) in &[(1, 4), (4, 1), (1, 1)] {
+            let settings = RenderSettings {
+                width,
+                height,
+                samples_per_pixel: 4,
+                max_depth: 10,
+                seed: 1,
+                sampler: SamplerKind::default(),
+                filter: Filter::default(),
+                adaptive: None,
+            };
+            let camera = scene.camera.build(settings.aspect_ratio());
+            let pixels = Renderer::new(settings)
+                .render(&world, &LightList::new(), &Environment::Gradient, &camera)
+                .pixels;
+            assert_eq!(pixels.len(), width * height);
+            for pixel in &pixels {
+                assert!(pixel.x.is_finite() && pixel.length() > 0.0, "{:?}", pixel);
+            }
+        }
+    }
+
+    #[test]
+    fn samplers_agree_on_the_image() {
+        let scene = builtin_scene("random", 5).unwrap();
+        let world = Bvh::new(scene.world.objects);
+        let settings = RenderSettings {
+            width: 16,
+            height: 12,
+            samples_per_pixel: 64,
+            max_depth: 10,
+            seed: 7,
+            sampler: SamplerKind::Independent,
+            filter: Filter::default(),
+            adaptive: None,
+        };
+        let camera = scene.camera.build(settings.aspect_ratio());
+        let mean = |sampler: SamplerKind| {
+            let pixels = Renderer::new(RenderSettings {
+                sampler,
+                ..settings
+            })
+            .render(&world, &LightList::new(), &Environment::Gradient, &camera)
+            .pixels;
+            pixels.iter().fold(Color::new_black(), |acc, c| acc + *c) / pixels.len() as f64
+        };
+        let expected = mean(SamplerKind::Independent);
+        for name in &SAMPLERS {
+            let difference = mean(name.parse().unwrap()) - expected;
+            assert!(difference.length() < 0.03 * expected.length(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn adaptive_sampling_spends_samples_where_the_noise_is() {
+        let scene = builtin_scene("random", 5).unwrap();
+        let world = Bvh::new(scene.world.objects);
+        let settings = RenderSettings {
+            width: 16,
+            height: 12,
+            samples_per_pixel: 256,
+            max_depth: 10,
+            seed: 3,
+            sampler: SamplerKind::Sobol,
+            filter: Filter::default(),
+            adaptive: None,
+        };
+        let camera = scene.camera.build(settings.aspect_ratio());
+        let render = |adaptive| {
+            Renderer::new(RenderSettings {
+                adaptive,
+                ..settings
+            })
+            .render_film(&world, &LightList::new(), &Environment::Gradient, &camera)
+        };
+        let full = render(None);
+        let adaptive = render(Some(AdaptiveSampling {
+            threshold: 0.05,
+            min_samples: 16,
+        }));
+        assert!(full.sample_counts().iter().all(|&count| count == 256));
+        let counts = adaptive.sample_counts();
+        assert_eq!(*counts.iter().min().unwrap(), 16);
+        assert_eq!(*counts.iter().max().unwrap(), 256);
+        let total: u32 = counts.iter().sum();
+        assert!(total < 256 * 16 * 12 * 2 / 3, "{}", total);
+
+        let mean = |film: &Film| {
+            let pixels = film.to_framebuffer().pixels;
+            pixels.iter().fold(Color::new_black(), |acc, c| acc + *c) / pixels.len() as f64
+        };
+        let difference = mean(&adaptive) - mean(&full);
+        assert!(difference.length() < 0.01 * mean(&full).length());
+    }
+}
@@ -0,0 +1,55 @@
+use rand_pcg::Pcg32;
+
+/// Random number generator used for rendering.
+pub type SampleRng = Pcg32;
+
+/// Generator for sample `sample` of pixel `pixel` when rendering with `seed`.
+///
+/// Every sample starts from its own hashed state instead of sharing a generator, so the result
+/// does not depend on which thread renders which pixel, or in which order.
+pub fn sample_rng(seed: u64, pixel: u64, sample: u64) -> SampleRng {
+    let state = mix(mix(mix(seed) ^ pixel) ^ sample);
+    Pcg32::new(state, 0xa02b_dbf7_bb3c_0a7b)
+}
+
+/// Generator for the random decisions made along one ray, seeded from a single uniform sample.
+///
+/// Lets the renderer spend one fixed sampler dimension on a traversal that may draw any number
+/// of values, such as free flights through the media the ray happens to cross.
+pub fn ray_rng(u: f64) -> SampleRng {
+    Pcg32::new(mix(u.to_bits()), 0xa02b_dbf7_bb3c_0a7b)
+}
+
+/// Uniform number in [0, 1) from the top 53 bits of `bits`, which fill the mantissa.
+pub fn unit_float(bits: u64) -> f64 {
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// SplitMix64 finalizer: a bijective mix in which every input bit affects every output bit.
+pub fn mix(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::sample_rng;
+
+    #[test]
+    fn same_inputs_give_same_numbers() {
+        let draw = |seed, pixel, sample| {
+            let mut rng = sample_rng(seed, pixel, sample);
+            (0..4).map(|_| rng.gen::<u64>()).collect::<Vec<_>>()
+        };
+        assert_eq!(draw(1, 2, 3), draw(1, 2, 3));
+        assert_ne!(draw(1, 2, 3), draw(2, 2, 3));
+        assert_ne!(draw(1, 2, 3), draw(1, 3, 3));
+        assert_ne!(draw(1, 2, 3), draw(1, 2, 4));
+        // Swapping the pixel and the sample index must not repeat a stream.
+        assert_ne!(draw(1, 2, 3), draw(1, 3, 2));
+    }
+}
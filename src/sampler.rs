@@ -0,0 +1,421 @@
+use std::str::FromStr;
+
+use rand::{Rng, RngCore};
+
+use crate::sample_rng::{mix, unit_float};
+
+/// Source of the random numbers of one pixel sample, handed out dimension by dimension.
+///
+/// The pixel filter, the camera lens, the lights and the materials ask for their next one or two
+/// dimensions in the same order for every sample of a pixel, so a low-discrepancy sampler can
+/// spread each dimension evenly over the samples of the pixel.
+pub trait Sampler {
+    /// Next dimension, in [0, 1).
+    fn get_1d(&mut self) -> f64;
+
+    /// Next two dimensions, stratified with respect to each other.
+    fn get_2d(&mut self) -> (f64, f64);
+}
+
+/// Any random number generator is an independent sampler.
+impl<R: RngCore + ?Sized> Sampler for R {
+    fn get_1d(&mut self) -> f64 {
+        self.gen()
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        (self.gen(), self.gen())
+    }
+}
+
+pub const SAMPLERS: [&str; 4] = ["independent", "stratified", "halton", "sobol"];
+
+/// The samplers listed in [`SAMPLERS`].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SamplerKind {
+    /// Uniform random numbers, see [`crate::sample_rng::sample_rng`]
+    Independent,
+    /// [`StratifiedSampler`]
+    Stratified,
+    /// [`HaltonSampler`]
+    Halton,
+    /// [`SobolSampler`]
+    #[default]
+    Sobol,
+}
+
+impl FromStr for SamplerKind {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<SamplerKind, String> {
+        match name {
+            "independent" => Ok(SamplerKind::Independent),
+            "stratified" => Ok(SamplerKind::Stratified),
+            "halton" => Ok(SamplerKind::Halton),
+            "sobol" => Ok(SamplerKind::Sobol),
+            _ => Err(format!("unknown sampler `{}`", name)),
+        }
+    }
+}
+
+/// Largest `f64` below one.
+const ONE_MINUS_EPSILON: f64 = 1.0 - f64::EPSILON / 2.0;
+
+/// Hash that identifies a pixel when rendering with `seed`.
+fn pixel_hash(seed: u64, pixel: u64) -> u64 {
+    mix(mix(seed) ^ pixel)
+}
+
+/// Element `i` of a random permutation of `0..length` chosen by `hash` (Kensler, "Correlated
+/// Multi-Jittered Sampling"), without storing the permutation.
+fn permutation_element(mut i: u32, length: u32, hash: u32) -> u32 {
+    let mut w = length - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    // Permute within the next power of two and retry until the result lands inside the range.
+    loop {
+        i ^= hash;
+        i = i.wrapping_mul(0xe170_893d);
+        i ^= hash >> 16;
+        i ^= (i & w) >> 4;
+        i ^= hash >> 8;
+        i = i.wrapping_mul(0x0929_eb3f);
+        i ^= hash >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | hash >> 27);
+        i = i.wrapping_mul(0x6935_fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dc_b303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e50_1cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860_a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < length {
+            return (i.wrapping_add(hash)) % length;
+        }
+    }
+}
+
+/// Jittered sampling: every dimension is split into `samples_per_pixel` strata, or a grid of
+/// about as many cells for pairs of dimensions, and each sample of a pixel takes a different
+/// one.
+///
+/// Which stratum a sample gets is shuffled per pixel and dimension, so that the dimensions are
+/// not correlated with each other.
+#[derive(Debug, Clone)]
+pub struct StratifiedSampler {
+    pixel_hash: u64,
+    sample: u64,
+    samples_per_pixel: u64,
+    dimension: u64,
+}
+
+impl StratifiedSampler {
+    /// Sampler for sample `sample` of `samples_per_pixel` of pixel `pixel`; later samples
+    /// start over with the first strata.
+    pub fn new(seed: u64, pixel: u64, sample: u64, samples_per_pixel: u64) -> StratifiedSampler {
+        StratifiedSampler {
+            pixel_hash: pixel_hash(seed, pixel),
+            sample,
+            samples_per_pixel: samples_per_pixel.max(1),
+            dimension: 0,
+        }
+    }
+
+    /// Random stratum out of `count` and the hash for the jitter within it.
+    fn next_stratum(&mut self, count: u64) -> (u64, u64) {
+        let hash = mix(self.pixel_hash ^ self.dimension);
+        self.dimension += 1;
+        let stratum = permutation_element((self.sample % count) as u32, count as u32, hash as u32);
+        (stratum as u64, mix(hash ^ self.sample))
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn get_1d(&mut self) -> f64 {
+        let count = self.samples_per_pixel;
+        let (stratum, jitter) = self.next_stratum(count);
+        (stratum as f64 + unit_float(jitter)) / count as f64
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        // The smallest grid with at least one cell per sample.
+        let columns = (self.samples_per_pixel as f64).sqrt().ceil() as u64;
+        let rows = self.samples_per_pixel.div_ceil(columns);
+        let (stratum, jitter) = self.next_stratum(columns * rows);
+        (
+            ((stratum % columns) as f64 + unit_float(jitter)) / columns as f64,
+            ((stratum / columns) as f64 + unit_float(mix(jitter))) / rows as f64,
+        )
+    }
+}
+
+const PRIMES: [u64; 64] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131, 137, 139, 149, 151, 157, 163, 167, 173, 179, 181, 191, 193,
+    197, 199, 211, 223, 227, 229, 233, 239, 241, 251, 257, 263, 269, 271, 277, 281, 283, 293, 307,
+    311,
+];
+
+/// Radical inverse of `index` in `base` with every digit permuted depending on the digits before
+/// it, which is Owen scrambling.
+fn scrambled_radical_inverse(base: u64, mut index: u64, hash: u64) -> f64 {
+    let inverse_base = 1.0 / base as f64;
+    let mut inverse_base_power = 1.0;
+    let mut result = 0.0;
+    // Chained over the digits so far, which picks the permutation of the next one.
+    let mut prefix_hash = hash;
+    // Stop once further digits no longer change the result.
+    while 1.0 - (base - 1) as f64 * inverse_base_power < 1.0 {
+        inverse_base_power *= inverse_base;
+        let digit = permutation_element((index % base) as u32, base as u32, prefix_hash as u32);
+        result += digit as f64 * inverse_base_power;
+        prefix_hash = mix(prefix_hash ^ digit as u64);
+        index /= base;
+    }
+    f64::min(result, ONE_MINUS_EPSILON)
+}
+
+/// The Halton sequence, with one prime base per dimension, Owen scrambled per pixel.
+///
+/// Dimensions beyond the 64 tabulated primes, which only deep paths reach, fall back to
+/// independent random numbers.
+#[derive(Debug, Clone)]
+pub struct HaltonSampler {
+    pixel_hash: u64,
+    sample: u64,
+    dimension: usize,
+}
+
+impl HaltonSampler {
+    pub fn new(seed: u64, pixel: u64, sample: u64) -> HaltonSampler {
+        HaltonSampler {
+            pixel_hash: pixel_hash(seed, pixel),
+            sample,
+            dimension: 0,
+        }
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn get_1d(&mut self) -> f64 {
+        let hash = mix(self.pixel_hash ^ self.dimension as u64);
+        let value = match PRIMES.get(self.dimension) {
+            Some(&base) => scrambled_radical_inverse(base, self.sample, hash),
+            None => unit_float(mix(hash ^ self.sample)),
+        };
+        self.dimension += 1;
+        value
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        (self.get_1d(), self.get_1d())
+    }
+}
+
+/// Nested uniform scrambling of the bits of `x` taken as a binary fraction, after Burley,
+/// "Practical Hash-based Owen Scrambling", with the permutation by Vegdahl.
+fn owen_scramble(x: u32, seed: u32) -> u32 {
+    let mut x = x.reverse_bits();
+    x ^= x.wrapping_mul(0x3d20_adea);
+    x = x.wrapping_add(seed);
+    x = x.wrapping_mul((seed >> 16) | 1);
+    x ^= x.wrapping_mul(0x0552_6c56);
+    x ^= x.wrapping_mul(0x53a2_2864);
+    x.reverse_bits()
+}
+
+/// Second dimension of the Sobol sequence; its generator matrix is Pascal's triangle modulo two.
+fn sobol_second_dimension(index: u32) -> u32 {
+    let mut direction = 1u32 << 31;
+    let mut result = 0;
+    for bit in 0..32 {
+        if index & (1 << bit) != 0 {
+            result ^= direction;
+        }
+        direction ^= direction >> 1;
+    }
+    result
+}
+
+fn to_unit(x: u32) -> f64 {
+    x as f64 / (1u64 << 32) as f64
+}
+
+/// The first two dimensions of the Sobol sequence, Owen scrambled, reused for every request
+/// with a differently shuffled sample order ("padding").
+///
+/// The shuffle is itself an Owen scramble of the sample index, so the first `2^k` samples of a
+/// pixel still form a stratified set in every pair of dimensions.
+#[derive(Debug, Clone)]
+pub struct SobolSampler {
+    pixel_hash: u64,
+    sample: u32,
+    dimension: u64,
+}
+
+impl SobolSampler {
+    pub fn new(seed: u64, pixel: u64, sample: u64) -> SobolSampler {
+        SobolSampler {
+            pixel_hash: pixel_hash(seed, pixel),
+            sample: sample as u32,
+            dimension: 0,
+        }
+    }
+
+    /// Shuffled sample index and the hash for scrambling the values of the next request.
+    fn next_index(&mut self) -> (u32, u64) {
+        let hash = mix(self.pixel_hash ^ self.dimension);
+        self.dimension += 1;
+        (owen_scramble(self.sample, hash as u32), mix(hash))
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn get_1d(&mut self) -> f64 {
+        let (index, hash) = self.next_index();
+        // The first Sobol dimension is the van der Corput sequence.
+        to_unit(owen_scramble(index.reverse_bits(), hash as u32))
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let (index, hash) = self.next_index();
+        (
+            to_unit(owen_scramble(index.reverse_bits(), hash as u32)),
+            to_unit(owen_scramble(
+                sobol_second_dimension(index),
+                (hash >> 32) as u32,
+            )),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::sample_rng::sample_rng;
+
+    /// Number of points in each cell of a `columns` by `rows` grid.
+    fn cell_counts(points: &[(f64, f64)], columns: usize, rows: usize) -> Vec<usize> {
+        let mut counts = vec![0; columns * rows];
+        for (x, y) in points {
+            assert!((0.0..1.0).contains(x) && (0.0..1.0).contains(y));
+            counts[(y * rows as f64) as usize * columns + (x * columns as f64) as usize] += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn permutation_element_is_a_permutation() {
+        for length in 1..40 {
+            for hash in &[0, 1, 0xdead_beef] {
+                let mut elements: Vec<u32> = (0..length)
+                    .map(|i| permutation_element(i, length, *hash))
+                    .collect();
+                elements.sort();
+                assert_eq!(elements, (0..length).collect::<Vec<_>>());
+            }
+        }
+    }
+
+    #[test]
+    fn stratified_samples_take_one_stratum_each() {
+        let samplers: Vec<StratifiedSampler> = (0..16)
+            .map(|sample| StratifiedSampler::new(1, 7, sample, 16))
+            .collect();
+        let first: Vec<(f64, f64)> = samplers
+            .clone()
+            .iter_mut()
+            .map(|sampler| (sampler.get_1d(), 0.5))
+            .collect();
+        assert_eq!(cell_counts(&first, 16, 1), vec![1; 16]);
+        let second: Vec<(f64, f64)> = samplers
+            .clone()
+            .iter_mut()
+            .map(|sampler| {
+                sampler.get_1d();
+                sampler.get_2d()
+            })
+            .collect();
+        assert_eq!(cell_counts(&second, 4, 4), vec![1; 16]);
+    }
+
+    #[test]
+    fn halton_samples_are_stratified_in_their_bases() {
+        // The first 2 * 3 points are stratified on a grid of 2 by 3 cells, and the first 25
+        // points of the third and fourth dimensions in bases 5 and 7 each cover every fifth
+        // and seventh of the unit interval.
+        let points: Vec<(f64, f64)> = (0..6)
+            .map(|sample| HaltonSampler::new(3, 11, sample).get_2d())
+            .collect();
+        assert_eq!(cell_counts(&points, 2, 3), vec![1; 6]);
+        let later: Vec<(f64, f64)> = (0..35)
+            .map(|sample| {
+                let mut sampler = HaltonSampler::new(3, 11, sample);
+                sampler.get_2d();
+                sampler.get_2d()
+            })
+            .collect();
+        assert_eq!(cell_counts(&later, 5, 1), vec![7; 5]);
+        assert_eq!(cell_counts(&later, 1, 7), vec![5; 7]);
+    }
+
+    #[test]
+    fn sobol_samples_form_a_net_in_every_request() {
+        for request in 0..4 {
+            let points: Vec<(f64, f64)> = (0..16)
+                .map(|sample| {
+                    let mut sampler = SobolSampler::new(5, 13, sample);
+                    for _ in 0..request {
+                        sampler.get_2d();
+                    }
+                    sampler.get_2d()
+                })
+                .collect();
+            // Every elementary interval of area 1/16 holds one point.
+            for &(columns, rows) in &[(16, 1), (8, 2), (4, 4), (2, 8), (1, 16)] {
+                assert_eq!(cell_counts(&points, columns, rows), vec![1; 16]);
+            }
+        }
+    }
+
+    #[test]
+    fn low_discrepancy_samplers_converge_faster() {
+        // Integrates a smooth function over the first request of many pixels.
+        let f = |(x, y): (f64, f64)| (x * y * std::f64::consts::PI).sin();
+        let exact = 0.524_663;
+        let spp = 64;
+        let rms_error = |sampler: &dyn Fn(u64, u64) -> (f64, f64)| {
+            let pixels = 200;
+            let squared: f64 = (0..pixels)
+                .map(|pixel| {
+                    let estimate = (0..spp)
+                        .map(|sample| f(sampler(pixel, sample)))
+                        .sum::<f64>()
+                        / spp as f64;
+                    (estimate - exact).powi(2)
+                })
+                .sum();
+            (squared / pixels as f64).sqrt()
+        };
+        let independent = rms_error(&|pixel, sample| sample_rng(9, pixel, sample).get_2d());
+        let stratified =
+            rms_error(&|pixel, sample| StratifiedSampler::new(9, pixel, sample, spp).get_2d());
+        let halton = rms_error(&|pixel, sample| HaltonSampler::new(9, pixel, sample).get_2d());
+        let sobol = rms_error(&|pixel, sample| SobolSampler::new(9, pixel, sample).get_2d());
+        for error in &[stratified, halton, sobol] {
+            assert!(*error < 0.25 * independent, "{} {}", error, independent);
+        }
+        // Any generator works as an independent sampler.
+        let (x, y) = StdRng::seed_from_u64(1).get_2d();
+        assert!((0.0..1.0).contains(&x) && (0.0..1.0).contains(&y));
+    }
+}
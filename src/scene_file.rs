@@ -0,0 +1,1451 @@
+//! Loader for TOML scene descriptions.
+//!
+//! A scene file has a `[camera]` table, named texture and material tables under `[textures]` and
+//! `[materials]` and one array of tables per kind of object, e.g. `[[spheres]]`. Vectors and
+//! colors are written as arrays of three numbers. Wherever a material takes a color, the name of
+//! a texture may be given instead.
+//!
+//! ```toml
+//! [camera]
+//! look_from = [13.0, 2.0, 3.0]
+//! look_at = [0.0, 0.0, 0.0]
+//! vup = [0.0, 1.0, 0.0]   # optional, defaults to +y
+//! vfov = 20.0             # vertical field of view in degrees
+//! aperture = 0.1          # optional, defaults to 0 (pinhole)
+//! focus_dist = 10.0       # optional, defaults to |look_from - look_at|
+//! shutter = [0.0, 1.0]    # optional open and close times for motion blur, defaults to [0, 0]
+//!
+//! [environment]           # optional, defaults to the sky gradient; also read as [background]
+//! type = "image"          # or "gradient" (also "sky"), and "constant" with a `color`
+//! file = "studio.hdr"     # Radiance HDR in latitude-longitude layout, relative to the scene
+//!                         # file, the center looking down -z; sampled as a light
+//! intensity = 1.0         # optional radiance scale, defaults to 1
+//! rotation = 90.0         # optional, degrees counterclockwise about +y seen from above
+//!
+//! # Alternatively an analytic daylight sky with a sun, also sampled as a light. All fields are
+//! # optional and default to the values shown.
+//! # [environment]
+//! # type = "daylight"
+//! # sun_elevation = 45.0  # degrees above the horizon
+//! # sun_azimuth = 0.0     # degrees, 0 along -z and 90 along +x
+//! # turbidity = 3.0       # 2 for a very clear sky up to 10 for a hazy one
+//! # ground_albedo = 0.3
+//! # intensity = 1.0
+//!
+//! [textures.checks]
+//! type = "checker"        # 3D checker board
+//! size = 0.5              # edge length of the cells
+//! even = [0.2, 0.3, 0.1]  # color or texture name
+//! odd = [0.9, 0.9, 0.9]
+//!
+//! [textures.stone]
+//! type = "marble"         # or "perlin" and "turbulence"
+//! scale = 4.0             # optional, inverse feature size, defaults to 1
+//! seed = 7                # optional, defaults to 0
+//!
+//! [textures.earth]
+//! type = "image"
+//! file = "textures/earth.png" # PNG, relative to the scene file
+//!
+//! [materials.ground]
+//! type = "lambertian"
+//! albedo = "checks"
+//!
+//! [materials.brass]
+//! type = "metal"
+//! albedo = [0.7, 0.6, 0.5]
+//! fuzz = 0.1
+//!
+//! [materials.gold]
+//! type = "conductor"      # microfacet metal with a complex index of refraction
+//! metal = "gold"          # "gold", "copper", "aluminium" or "silver"; or instead
+//!                         # eta = [0.2, 0.9, 1.1] and k = [3.9, 2.5, 2.1] per channel
+//! roughness = 0.3         # optional, 0 (a mirror) to 1, defaults to 0
+//!
+//! [materials.glass]
+//! type = "dielectric"
+//! ref_idx = 1.5
+//! roughness = 0.2         # optional, 0 (clear) to 1 (frosted), defaults to 0
+//!
+//! [materials.paint]
+//! type = "principled"     # Disney's principled BSDF; every field is optional and defaults
+//!                         # to the value shown
+//! base_color = [0.8, 0.8, 0.8] # color or texture name
+//! metallic = 0.0
+//! roughness = 0.5
+//! specular = 0.5          # dielectric reflectance at normal incidence, 0.5 is 4%
+//! specular_tint = 0.0
+//! sheen = 0.0
+//! sheen_tint = 0.5
+//! clearcoat = 0.0
+//! clearcoat_gloss = 1.0
+//! transmission = 0.0      # 1 for glass
+//! ior = 1.5
+//! subsurface = 0.0
+//!
+//! [materials.lamp]
+//! type = "emissive"       # emits from the front face, absorbs everything; objects with this
+//!                         # material are sampled directly as lights
+//! radiance = [4.0, 4.0, 4.0]
+//!
+//! [materials.fog]
+//! type = "isotropic"      # phase function for media, scatters equally in all directions
+//! albedo = [0.9, 0.9, 0.9]
+//!
+//! [materials.smoke]
+//! type = "henyey-greenstein"
+//! albedo = [0.2, 0.2, 0.2]
+//! g = 0.6                 # mean cosine, positive values scatter forward
+//!
+//! [[spheres]]
+//! center = [0.0, 1.0, 0.0]
+//! radius = 1.0
+//! material = "glass"
+//! velocity = [0.0, 0.5, 0.0]  # optional, the center at time t is center + t * velocity, not
+//!                             # for emissive materials
+//!
+//! [[planes]]              # infinite, the front faces along the normal
+//! point = [0.0, 0.0, 0.0]
+//! normal = [0.0, 1.0, 0.0]
+//! material = "ground"
+//!
+//! [[quads]]               # parallelogram with edges u and v, the front faces along u × v
+//! corner = [-1.0, 3.0, -1.0]
+//! u = [2.0, 0.0, 0.0]
+//! v = [0.0, 0.0, 2.0]
+//! material = "lamp"
+//!
+//! [[disks]]
+//! center = [3.0, 0.01, 0.0]
+//! normal = [0.0, 1.0, 0.0]
+//! radius = 0.5
+//! material = "brass"
+//!
+//! [[boxes]]               # axis-aligned between two opposite corners, never sampled as a light
+//! min = [-3.0, 0.0, -1.0]
+//! max = [-2.0, 1.0, 0.0]
+//! material = "brass"
+//! transform = { rotate = [0.0, 30.0, 0.0] }  # optional, as for meshes below
+//!
+//! [[media]]               # homogeneous fog or smoke inside a closed, convex boundary
+//! boundary = { type = "sphere", center = [0.0, 1.0, 0.0], radius = 1.0 }
+//! density = 2.0           # mean free path is 1 / density
+//! material = "smoke"      # isotropic or henyey-greenstein
+//!
+//! [[media]]
+//! boundary = { type = "box", min = [-20.0, 0.0, -20.0], max = [20.0, 0.5, 20.0] }
+//! density = 0.2
+//! material = "fog"
+//!
+//! [[triangles]]           # counter-clockwise vertices face the viewer
+//! vertices = [[-1.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.5, 0.0]]
+//! material = "brass"
+//!
+//! [[meshes]]
+//! file = "models/teapot.obj"  # Wavefront OBJ, relative to the scene file
+//! material = "brass"          # optional, replaces the materials from the MTL files
+//! smooth = true               # optional, smooth vertices without normals (default true)
+//! # Optional placement: scale, then rotate about x, y and z (degrees), then translate. Meshes
+//! # listed several times are loaded once and shared between the instances.
+//! transform = { scale = 2.0, rotate = [0.0, 45.0, 0.0], translate = [1.0, 0.0, -2.0] }
+//! # Transforms of meshes and boxes may also move and spin the object during the shutter
+//! # interval: `velocity` is added per unit of time and `spin` turns it about an axis through
+//! # its translated origin, with the speed in degrees per unit of time as the vector's length.
+//! # Moving objects cannot be emissive.
+//! # transform = { velocity = [0.0, 0.0, 1.0], spin = [0.0, 720.0, 0.0] }
+//! ```
+//!
+//! Errors report the line and column of the offending value.
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::Deserialize;
+use toml::Spanned;
+
+use crate::bvh::Bvh;
+use crate::camera::CameraSettings;
+use crate::color::Color;
+use crate::conductor::{Conductor, CONDUCTORS};
+use crate::constant_medium::ConstantMedium;
+use crate::cuboid::Cuboid;
+use crate::daylight::DaylightSettings;
+use crate::disk::Disk;
+use crate::environment::{Environment, EnvironmentMap};
+use crate::hittable::HittableList;
+use crate::light::LightList;
+use crate::material_variants::MaterialVariants;
+use crate::moving_sphere::MovingSphere;
+use crate::obj::{load_obj, MissingNormals, ObjOptions};
+use crate::perlin::Perlin;
+use crate::plane::Plane;
+use crate::principled::Principled;
+use crate::quad::Quad;
+use crate::scenes::Scene;
+use crate::sphere::Sphere;
+use crate::texture::{ImageTexture, NoiseKind, TextureVariants};
+use crate::transform::{AnimatedTransform, Transform};
+use crate::transformed::{Animated, Transformed};
+use crate::triangle::Triangle;
+use crate::triangle_mesh::{MeshTriangle, TriangleMesh};
+use crate::vec3::Vec3;
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    Invalid {
+        message: String,
+        line: usize,
+        column: usize,
+    },
+}
+
+impl SceneError {
+    /// Returns the 1-based line and column the error refers to, if any.
+    pub fn line_col(&self) -> Option<(usize, usize)> {
+        match self {
+            SceneError::Io(_) => None,
+            SceneError::Parse(error) => error.line_col().map(|(line, col)| (line + 1, col + 1)),
+            SceneError::Invalid { line, column, .. } => Some((*line, *column)),
+        }
+    }
+}
+
+impl std::fmt::Display for SceneError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SceneError::Io(error) => write!(f, "{}", error),
+            SceneError::Parse(error) => write!(f, "{}", error),
+            SceneError::Invalid {
+                message,
+                line,
+                column,
+            } => write!(f, "{} at line {} column {}", message, line, column),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+impl From<std::io::Error> for SceneError {
+    fn from(error: std::io::Error) -> SceneError {
+        SceneError::Io(error)
+    }
+}
+
+impl From<toml::de::Error> for SceneError {
+    fn from(error: toml::de::Error) -> SceneError {
+        SceneError::Parse(error)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneDescription {
+    camera: CameraDescription,
+    #[serde(alias = "background")]
+    environment: Option<EnvironmentDescription>,
+    #[serde(default)]
+    textures: HashMap<String, TextureDescription>,
+    #[serde(default)]
+    materials: HashMap<String, MaterialDescription>,
+    #[serde(default)]
+    spheres: Vec<SphereDescription>,
+    #[serde(default)]
+    planes: Vec<PlaneDescription>,
+    #[serde(default)]
+    quads: Vec<QuadDescription>,
+    #[serde(default)]
+    disks: Vec<DiskDescription>,
+    #[serde(default)]
+    boxes: Vec<BoxDescription>,
+    #[serde(default)]
+    media: Vec<MediumDescription>,
+    #[serde(default)]
+    triangles: Vec<TriangleDescription>,
+    #[serde(default)]
+    meshes: Vec<MeshDescription>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraDescription {
+    look_from: [f64; 3],
+    look_at: [f64; 3],
+    #[serde(default = "default_vup")]
+    vup: [f64; 3],
+    vfov: f64,
+    #[serde(default)]
+    aperture: f64,
+    focus_dist: Option<f64>,
+    #[serde(default)]
+    shutter: [f64; 2],
+}
+
+fn default_vup() -> [f64; 3] {
+    [0.0, 1.0, 0.0]
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+enum MaterialDescription {
+    Lambertian {
+        albedo: TextureReference,
+    },
+    Metal {
+        albedo: TextureReference,
+        fuzz: f64,
+    },
+    Dielectric {
+        ref_idx: f64,
+        #[serde(default)]
+        roughness: f64,
+    },
+    Conductor {
+        metal: Option<String>,
+        eta: Option<[f64; 3]>,
+        k: Option<[f64; 3]>,
+        #[serde(default)]
+        roughness: f64,
+    },
+    Principled(PrincipledDescription),
+    Emissive {
+        radiance: TextureReference,
+    },
+    Isotropic {
+        albedo: TextureReference,
+    },
+    #[serde(rename = "henyey-greenstein")]
+    HenyeyGreenstein {
+        albedo: TextureReference,
+        g: f64,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct PrincipledDescription {
+    base_color: TextureReference,
+    metallic: f64,
+    roughness: f64,
+    specular: f64,
+    specular_tint: f64,
+    sheen: f64,
+    sheen_tint: f64,
+    clearcoat: f64,
+    clearcoat_gloss: f64,
+    transmission: f64,
+    ior: f64,
+    subsurface: f64,
+}
+
+impl Default for PrincipledDescription {
+    fn default() -> PrincipledDescription {
+        let defaults = Principled::default();
+        PrincipledDescription {
+            base_color: TextureReference::Color([0.8, 0.8, 0.8]),
+            metallic: defaults.metallic,
+            roughness: defaults.roughness,
+            specular: defaults.specular,
+            specular_tint: defaults.specular_tint,
+            sheen: defaults.sheen,
+            sheen_tint: defaults.sheen_tint,
+            clearcoat: defaults.clearcoat,
+            clearcoat_gloss: defaults.clearcoat_gloss,
+            transmission: defaults.transmission,
+            ior: defaults.ior,
+            subsurface: defaults.subsurface,
+        }
+    }
+}
+
+/// A constant color or the name of a texture.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TextureReference {
+    Color([f64; 3]),
+    Named(String),
+}
+
+/// Kept flat instead of as a tagged enum so that values can carry their position.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TextureDescription {
+    #[serde(rename = "type")]
+    kind: Spanned<String>,
+    size: Option<f64>,
+    even: Option<TextureReference>,
+    odd: Option<TextureReference>,
+    #[serde(default = "default_scale")]
+    scale: f64,
+    #[serde(default)]
+    seed: u64,
+    file: Option<Spanned<String>>,
+}
+
+fn default_scale() -> f64 {
+    1.0
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EnvironmentDescription {
+    #[serde(rename = "type")]
+    kind: Spanned<String>,
+    color: Option<[f64; 3]>,
+    file: Option<Spanned<String>>,
+    #[serde(default = "default_intensity")]
+    intensity: f64,
+    #[serde(default)]
+    rotation: f64,
+    sun_elevation: Option<f64>,
+    sun_azimuth: Option<f64>,
+    turbidity: Option<f64>,
+    ground_albedo: Option<f64>,
+}
+
+fn default_intensity() -> f64 {
+    1.0
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SphereDescription {
+    center: [f64; 3],
+    radius: f64,
+    material: Spanned<String>,
+    velocity: Option<[f64; 3]>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PlaneDescription {
+    point: [f64; 3],
+    normal: [f64; 3],
+    material: Spanned<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct QuadDescription {
+    corner: [f64; 3],
+    u: [f64; 3],
+    v: [f64; 3],
+    material: Spanned<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DiskDescription {
+    center: [f64; 3],
+    normal: [f64; 3],
+    radius: f64,
+    material: Spanned<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BoxDescription {
+    min: [f64; 3],
+    max: [f64; 3],
+    material: Spanned<String>,
+    transform: Option<TransformDescription>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MediumDescription {
+    boundary: BoundaryDescription,
+    density: f64,
+    material: Spanned<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+enum BoundaryDescription {
+    Sphere { center: [f64; 3], radius: f64 },
+    Box { min: [f64; 3], max: [f64; 3] },
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TriangleDescription {
+    vertices: [[f64; 3]; 3],
+    material: Spanned<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MeshDescription {
+    file: Spanned<String>,
+    material: Option<Spanned<String>>,
+    #[serde(default = "default_smooth")]
+    smooth: bool,
+    transform: Option<TransformDescription>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TransformDescription {
+    scale: Option<ScaleDescription>,
+    rotate: Option<[f64; 3]>,
+    translate: Option<[f64; 3]>,
+    velocity: Option<[f64; 3]>,
+    spin: Option<[f64; 3]>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ScaleDescription {
+    Uniform(f64),
+    PerAxis([f64; 3]),
+}
+
+impl TransformDescription {
+    /// `None` if a scale factor is zero.
+    fn to_transform(&self) -> Option<Transform> {
+        let factors = match self.scale {
+            Some(ScaleDescription::Uniform(s)) => Vec3::new(s, s, s),
+            Some(ScaleDescription::PerAxis(s)) => vec3(s),
+            None => Vec3::new(1.0, 1.0, 1.0),
+        };
+        if factors.x * factors.y * factors.z == 0.0 {
+            return None;
+        }
+        let [x, y, z] = self.rotate.unwrap_or([0.0; 3]);
+        Some(
+            Transform::translate(vec3(self.translate.unwrap_or([0.0; 3])))
+                * Transform::rotate_z(z)
+                * Transform::rotate_y(y)
+                * Transform::rotate_x(x)
+                * Transform::scale(factors),
+        )
+    }
+
+    /// `None` unless the transform moves or spins.
+    fn motion(&self, transform: Transform) -> Option<AnimatedTransform> {
+        if self.velocity.is_none() && self.spin.is_none() {
+            return None;
+        }
+        Some(AnimatedTransform::new(
+            transform,
+            vec3(self.velocity.unwrap_or([0.0; 3])),
+            vec3(self.spin.unwrap_or([0.0; 3])),
+        ))
+    }
+}
+
+/// The parts of a loaded OBJ file, shared by all instances of it.
+struct LoadedMesh {
+    hierarchy: Arc<Bvh<MeshTriangle>>,
+    /// The triangles again if the part is emissive, so they can be sampled as lights.
+    emitters: Vec<MeshTriangle>,
+}
+
+fn default_smooth() -> bool {
+    true
+}
+
+fn vec3(v: [f64; 3]) -> Vec3 {
+    Vec3::new(v[0], v[1], v[2])
+}
+
+fn color(c: [f64; 3]) -> Color {
+    Color::new(c[0], c[1], c[2])
+}
+
+/// Converts a byte offset into `source` to a 1-based line and column.
+fn line_col(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before.chars().rev().take_while(|c| *c != '\n').count() + 1;
+    (line, column)
+}
+
+impl From<CameraDescription> for CameraSettings {
+    fn from(camera: CameraDescription) -> CameraSettings {
+        let look_from = vec3(camera.look_from);
+        let look_at = vec3(camera.look_at);
+        CameraSettings {
+            look_from,
+            look_at,
+            vup: vec3(camera.vup),
+            vfov: camera.vfov,
+            aperture: camera.aperture,
+            shutter_open: camera.shutter[0],
+            shutter_close: camera.shutter[1],
+            focus_dist: camera
+                .focus_dist
+                .unwrap_or_else(|| (look_from - look_at).length()),
+        }
+    }
+}
+
+fn invalid(source: &str, span_start: usize, message: String) -> SceneError {
+    let (line, column) = line_col(source, span_start);
+    SceneError::Invalid {
+        message,
+        line,
+        column,
+    }
+}
+
+/// Lights are sampled without regard to the ray time, so moving objects cannot be among them.
+fn moving_emitter(source: &str, span_start: usize) -> SceneError {
+    invalid(
+        source,
+        span_start,
+        "moving objects cannot be emissive".to_string(),
+    )
+}
+
+/// Builds named textures on first use, following references between them.
+struct Textures<'a> {
+    descriptions: &'a HashMap<String, TextureDescription>,
+    source: &'a str,
+    base_dir: &'a Path,
+    built: HashMap<String, TextureVariants>,
+    in_progress: Vec<String>,
+}
+
+impl<'a> Textures<'a> {
+    /// Resolves `reference`; an unknown name is reported at `span_start`.
+    fn resolve(
+        &mut self,
+        reference: &TextureReference,
+        span_start: usize,
+    ) -> Result<TextureVariants, SceneError> {
+        match reference {
+            TextureReference::Color(c) => Ok(color(*c).into()),
+            TextureReference::Named(name) => self.named(name, span_start),
+        }
+    }
+
+    fn named(&mut self, name: &str, span_start: usize) -> Result<TextureVariants, SceneError> {
+        if let Some(texture) = self.built.get(name) {
+            return Ok(texture.clone());
+        }
+        let description = match self.descriptions.get(name) {
+            Some(description) => description,
+            None => {
+                return Err(invalid(
+                    self.source,
+                    span_start,
+                    format!("unknown texture `{}`", name),
+                ))
+            }
+        };
+        if self.in_progress.iter().any(|n| n == name) {
+            return Err(invalid(
+                self.source,
+                description.kind.start(),
+                format!("texture `{}` refers to itself", name),
+            ));
+        }
+        self.in_progress.push(name.to_string());
+        let texture = self.build(description)?;
+        self.in_progress.pop();
+        self.built.insert(name.to_string(), texture.clone());
+        Ok(texture)
+    }
+
+    fn build(&mut self, description: &TextureDescription) -> Result<TextureVariants, SceneError> {
+        let kind = &description.kind;
+        let source = self.source;
+        let missing =
+            |field: &str| invalid(source, kind.start(), format!("missing field `{}`", field));
+        let noise = |noise_kind: NoiseKind| TextureVariants::Noise {
+            perlin: Arc::new(Perlin::new(&mut StdRng::seed_from_u64(description.seed))),
+            kind: noise_kind,
+            scale: description.scale,
+        };
+        match kind.get_ref().as_str() {
+            "checker" => {
+                let size = description.size.ok_or_else(|| missing("size"))?;
+                let even = description.even.as_ref().ok_or_else(|| missing("even"))?;
+                let odd = description.odd.as_ref().ok_or_else(|| missing("odd"))?;
+                Ok(TextureVariants::Checker {
+                    size,
+                    even: Arc::new(self.resolve(even, kind.start())?),
+                    odd: Arc::new(self.resolve(odd, kind.start())?),
+                })
+            }
+            "perlin" => Ok(noise(NoiseKind::Perlin)),
+            "turbulence" => Ok(noise(NoiseKind::Turbulence)),
+            "marble" => Ok(noise(NoiseKind::Marble)),
+            "image" => {
+                let file = description.file.as_ref().ok_or_else(|| missing("file"))?;
+                let image =
+                    ImageTexture::load(self.base_dir.join(file.get_ref())).map_err(|error| {
+                        invalid(
+                            source,
+                            file.start(),
+                            format!("could not load `{}`: {}", file.get_ref(), error),
+                        )
+                    })?;
+                Ok(TextureVariants::Image(Arc::new(image)))
+            }
+            other => Err(invalid(
+                source,
+                kind.start(),
+                format!("unknown texture type `{}`", other),
+            )),
+        }
+    }
+
+    /// Builds `material`; unknown texture names are reported at `span_start`.
+    fn material(
+        &mut self,
+        material: &MaterialDescription,
+        span_start: usize,
+    ) -> Result<MaterialVariants, SceneError> {
+        Ok(match material {
+            MaterialDescription::Lambertian { albedo } => {
+                MaterialVariants::Lambertian(self.resolve(albedo, span_start)?)
+            }
+            MaterialDescription::Metal { albedo, fuzz } => {
+                MaterialVariants::Metal(self.resolve(albedo, span_start)?, *fuzz)
+            }
+            MaterialDescription::Dielectric { ref_idx, roughness } => {
+                MaterialVariants::Dielectric(*ref_idx, *roughness)
+            }
+            MaterialDescription::Conductor {
+                metal,
+                eta,
+                k,
+                roughness,
+            } => {
+                let conductor = match (metal, eta, k) {
+                    (Some(metal), None, None) => {
+                        Conductor::preset(metal, *roughness).ok_or_else(|| {
+                            invalid(
+                                self.source,
+                                span_start,
+                                format!(
+                                    "unknown metal `{}`, expected one of {}",
+                                    metal,
+                                    CONDUCTORS.join(", ")
+                                ),
+                            )
+                        })?
+                    }
+                    (None, Some(eta), Some(k)) => {
+                        Conductor::new(color(*eta), color(*k), *roughness)
+                    }
+                    _ => {
+                        return Err(invalid(
+                            self.source,
+                            span_start,
+                            "a conductor takes either `metal` or both `eta` and `k`".to_string(),
+                        ))
+                    }
+                };
+                MaterialVariants::Conductor(conductor)
+            }
+            MaterialDescription::Principled(principled) => {
+                MaterialVariants::Principled(Principled {
+                    base_color: self.resolve(&principled.base_color, span_start)?,
+                    metallic: principled.metallic,
+                    roughness: principled.roughness,
+                    specular: principled.specular,
+                    specular_tint: principled.specular_tint,
+                    sheen: principled.sheen,
+                    sheen_tint: principled.sheen_tint,
+                    clearcoat: principled.clearcoat,
+                    clearcoat_gloss: principled.clearcoat_gloss,
+                    transmission: principled.transmission,
+                    ior: principled.ior,
+                    subsurface: principled.subsurface,
+                })
+            }
+            MaterialDescription::Emissive { radiance } => {
+                MaterialVariants::Emissive(self.resolve(radiance, span_start)?)
+            }
+            MaterialDescription::Isotropic { albedo } => {
+                MaterialVariants::Isotropic(self.resolve(albedo, span_start)?)
+            }
+            MaterialDescription::HenyeyGreenstein { albedo, g } => {
+                MaterialVariants::HenyeyGreenstein(self.resolve(albedo, span_start)?, *g)
+            }
+        })
+    }
+}
+
+/// Parses a scene description from a string. Files it refers to are resolved against `base_dir`.
+pub fn parse_scene(source: &str, base_dir: &Path) -> Result<Scene, SceneError> {
+    let description: SceneDescription = toml::from_str(source)?;
+
+    let mut textures = Textures {
+        descriptions: &description.textures,
+        source,
+        base_dir,
+        built: HashMap::new(),
+        in_progress: Vec::new(),
+    };
+    let mut lookup_material = |name: &Spanned<String>| -> Result<MaterialVariants, SceneError> {
+        match description.materials.get(name.get_ref()) {
+            Some(material) => textures.material(material, name.start()),
+            None => Err(invalid(
+                source,
+                name.start(),
+                format!("unknown material `{}`", name.get_ref()),
+            )),
+        }
+    };
+
+    let mut world = HittableList::new();
+    let mut lights = LightList::new();
+    let [shutter_open, shutter_close] = description.camera.shutter;
+    for sphere in &description.spheres {
+        if let Some(velocity) = sphere.velocity {
+            let material = lookup_material(&sphere.material)?;
+            if material.is_emissive() {
+                return Err(moving_emitter(source, sphere.material.start()));
+            }
+            let at = |time: f64| vec3(sphere.center) + time * vec3(velocity);
+            world.add(Box::new(MovingSphere::new(
+                at(shutter_open),
+                at(shutter_close),
+                shutter_open,
+                shutter_close,
+                sphere.radius,
+                material,
+            )));
+            continue;
+        }
+        let sphere = Sphere::new(
+            vec3(sphere.center),
+            sphere.radius,
+            lookup_material(&sphere.material)?,
+        );
+        if sphere.material.is_emissive() {
+            lights.add(Box::new(sphere.clone()));
+        }
+        world.add(Box::new(sphere));
+    }
+    for plane in &description.planes {
+        world.add(Box::new(Plane::new(
+            vec3(plane.point),
+            vec3(plane.normal),
+            lookup_material(&plane.material)?,
+        )));
+    }
+    for quad in &description.quads {
+        let quad = Quad::new(
+            vec3(quad.corner),
+            vec3(quad.u),
+            vec3(quad.v),
+            lookup_material(&quad.material)?,
+        );
+        if quad.material.is_emissive() {
+            lights.add(Box::new(quad.clone()));
+        }
+        world.add(Box::new(quad));
+    }
+    for disk in &description.disks {
+        let disk = Disk::new(
+            vec3(disk.center),
+            vec3(disk.normal),
+            disk.radius,
+            lookup_material(&disk.material)?,
+        );
+        if disk.material.is_emissive() {
+            lights.add(Box::new(disk.clone()));
+        }
+        world.add(Box::new(disk));
+    }
+    for cuboid in &description.boxes {
+        let material = lookup_material(&cuboid.material)?;
+        let emissive = material.is_emissive();
+        let object = Cuboid::new(vec3(cuboid.min), vec3(cuboid.max), material);
+        let description = match &cuboid.transform {
+            Some(description) => description,
+            None => {
+                world.add(Box::new(object));
+                continue;
+            }
+        };
+        let transform = description.to_transform().ok_or_else(|| {
+            invalid(
+                source,
+                cuboid.material.start(),
+                "transform scales by zero".to_string(),
+            )
+        })?;
+        match description.motion(transform) {
+            Some(_) if emissive => return Err(moving_emitter(source, cuboid.material.start())),
+            Some(motion) => world.add(Box::new(Animated::new(
+                object,
+                motion,
+                shutter_open,
+                shutter_close,
+            ))),
+            None => world.add(Box::new(Transformed::new(object, transform))),
+        }
+    }
+    for medium in &description.media {
+        let phase = lookup_material(&medium.material)?;
+        if !phase.is_phase_function() {
+            return Err(invalid(
+                source,
+                medium.material.start(),
+                format!(
+                    "material `{}` is not a phase function",
+                    medium.material.get_ref()
+                ),
+            ));
+        }
+        // The boundary is only used for its shape, it never shows up in a hit record.
+        let unused = MaterialVariants::Dielectric(1.0, 0.0);
+        match medium.boundary {
+            BoundaryDescription::Sphere { center, radius } => {
+                world.add(Box::new(ConstantMedium::new(
+                    Sphere::new(vec3(center), radius, unused),
+                    medium.density,
+                    phase,
+                )))
+            }
+            BoundaryDescription::Box { min, max } => world.add(Box::new(ConstantMedium::new(
+                Cuboid::new(vec3(min), vec3(max), unused),
+                medium.density,
+                phase,
+            ))),
+        }
+    }
+    for triangle in &description.triangles {
+        let [p0, p1, p2] = triangle.vertices;
+        let triangle = Triangle::new(
+            vec3(p0),
+            vec3(p1),
+            vec3(p2),
+            lookup_material(&triangle.material)?,
+        );
+        if triangle.material.is_emissive() {
+            lights.add(Box::new(triangle.clone()));
+        }
+        world.add(Box::new(triangle));
+    }
+    let mut loaded_meshes: HashMap<(&str, Option<&str>, bool), Vec<LoadedMesh>> = HashMap::new();
+    for mesh in &description.meshes {
+        let transform = match &mesh.transform {
+            Some(transform) => Some(transform.to_transform().ok_or_else(|| {
+                invalid(
+                    source,
+                    mesh.file.start(),
+                    "transform scales by zero".to_string(),
+                )
+            })?),
+            None => None,
+        };
+        let motion = match (&mesh.transform, transform) {
+            (Some(description), Some(transform)) => description.motion(transform),
+            _ => None,
+        };
+        let key = (
+            mesh.file.get_ref().as_str(),
+            mesh.material.as_ref().map(|name| name.get_ref().as_str()),
+            mesh.smooth,
+        );
+        let parts = match loaded_meshes.entry(key) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let mut options = ObjOptions::default();
+                if !mesh.smooth {
+                    options.missing_normals = MissingNormals::Flat;
+                }
+                let material = match &mesh.material {
+                    Some(name) => Some(lookup_material(name)?),
+                    None => None,
+                };
+                let meshes = load_obj(base_dir.join(mesh.file.get_ref()), &options)
+                    .map_err(|error| invalid(source, mesh.file.start(), error.to_string()))?;
+                let parts = meshes
+                    .into_iter()
+                    .map(|mut triangle_mesh| {
+                        if let Some(material) = &material {
+                            triangle_mesh.material = material.clone();
+                        }
+                        let emissive = triangle_mesh.material.is_emissive();
+                        let triangles = TriangleMesh::triangles(&Arc::new(triangle_mesh));
+                        LoadedMesh {
+                            emitters: if emissive {
+                                triangles.clone()
+                            } else {
+                                Vec::new()
+                            },
+                            hierarchy: Arc::new(Bvh::new(triangles)),
+                        }
+                    })
+                    .collect();
+                entry.insert(parts)
+            }
+        };
+
+        for part in parts.iter() {
+            let hierarchy = Arc::clone(&part.hierarchy);
+            if let Some(motion) = motion {
+                if !part.emitters.is_empty() {
+                    let span = mesh
+                        .material
+                        .as_ref()
+                        .map_or(mesh.file.start(), |m| m.start());
+                    return Err(moving_emitter(source, span));
+                }
+                world.add(Box::new(Animated::new(
+                    hierarchy,
+                    motion,
+                    shutter_open,
+                    shutter_close,
+                )));
+                continue;
+            }
+            match transform {
+                Some(transform) => {
+                    for triangle in &part.emitters {
+                        lights.add(Box::new(Transformed::new(triangle.clone(), transform)));
+                    }
+                    world.add(Box::new(Transformed::new(hierarchy, transform)));
+                }
+                None => {
+                    for triangle in &part.emitters {
+                        lights.add(Box::new(triangle.clone()));
+                    }
+                    world.add(Box::new(hierarchy));
+                }
+            }
+        }
+    }
+
+    let environment = match &description.environment {
+        Some(environment_description) => environment(environment_description, source, base_dir)?,
+        None => Environment::default(),
+    };
+
+    Ok(Scene {
+        camera: description.camera.into(),
+        world,
+        lights,
+        environment,
+    })
+}
+
+fn environment(
+    description: &EnvironmentDescription,
+    source: &str,
+    base_dir: &Path,
+) -> Result<Environment, SceneError> {
+    let kind = &description.kind;
+    let missing = |field: &str| invalid(source, kind.start(), format!("missing field `{}`", field));
+    match kind.get_ref().as_str() {
+        // `[background]` scenes still name the gradient `sky`.
+        "gradient" | "sky" => Ok(Environment::Gradient),
+        "constant" => {
+            let c = description.color.ok_or_else(|| missing("color"))?;
+            Ok(Environment::Constant(color(c)))
+        }
+        "image" => {
+            let file = description.file.as_ref().ok_or_else(|| missing("file"))?;
+            let map = EnvironmentMap::load(base_dir.join(file.get_ref())).map_err(|error| {
+                invalid(
+                    source,
+                    file.start(),
+                    format!("could not load `{}`: {}", file.get_ref(), error),
+                )
+            })?;
+            Ok(Environment::Image(Arc::new(
+                map.with_intensity(description.intensity)
+                    .with_rotation(description.rotation),
+            )))
+        }
+        "daylight" => {
+            let defaults = DaylightSettings::default();
+            let settings = DaylightSettings {
+                sun_elevation: description.sun_elevation.unwrap_or(defaults.sun_elevation),
+                sun_azimuth: description.sun_azimuth.unwrap_or(defaults.sun_azimuth),
+                turbidity: description.turbidity.unwrap_or(defaults.turbidity),
+                ground_albedo: description.ground_albedo.unwrap_or(defaults.ground_albedo),
+                intensity: description.intensity,
+            };
+            Ok(Environment::Daylight(Arc::new(settings.build())))
+        }
+        other => Err(invalid(
+            source,
+            kind.start(),
+            format!("unknown environment type `{}`", other),
+        )),
+    }
+}
+
+/// Reads and parses a scene description file.
+pub fn load_scene<P: AsRef<Path>>(path: P) -> Result<Scene, SceneError> {
+    let path = path.as_ref();
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+    parse_scene(&std::fs::read_to_string(path)?, base_dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{parse_scene, SceneError};
+    use crate::color::Color;
+    use crate::conductor::Conductor;
+    use crate::environment::Environment;
+    use crate::hittable::{Face, Hittable};
+    use crate::material_variants::MaterialVariants;
+    use crate::ray::Ray;
+    use crate::sample_rng::sample_rng;
+    use crate::texture::TextureVariants;
+    use crate::vec3::Vec3;
+
+    const CAMERA: &str =
+        "[camera]\nlook_from = [0.0, 0.0, 1.0]\nlook_at = [0.0, 0.0, 0.0]\nvfov = 40.0\n";
+
+    #[test]
+    fn parses_objects_and_defaults() {
+        let source = format!(
+            "{}\n[materials.red]\ntype = \"lambertian\"\nalbedo = [1.0, 0.0, 0.0]\n\n\
+             [[spheres]]\ncenter = [0.0, 0.0, -1.0]\nradius = 0.5\nmaterial = \"red\"\n\n\
+             [[triangles]]\nvertices = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]\n\
+             material = \"red\"\n",
+            CAMERA
+        );
+        let scene = parse_scene(&source, Path::new("")).unwrap();
+        assert_eq!(scene.world.objects.len(), 2);
+        assert_eq!(scene.camera.focus_dist, 1.0);
+        assert_eq!(scene.camera.aperture, 0.0);
+        assert!(matches!(scene.environment, Environment::Gradient));
+    }
+
+    #[test]
+    fn unknown_material_reports_position() {
+        let source = format!(
+            "{}\n[[spheres]]\ncenter = [0.0, 0.0, -1.0]\nradius = 0.5\nmaterial = \"missing\"\n",
+            CAMERA
+        );
+        match parse_scene(&source, Path::new("")) {
+            Err(error @ SceneError::Invalid { .. }) => assert_eq!(error.line_col(), Some((9, 12))),
+            _ => panic!("expected an invalid scene error"),
+        }
+    }
+
+    #[test]
+    fn syntax_error_reports_position() {
+        let error = parse_scene("[camera]\nvfov = = 3\n", Path::new(""))
+            .err()
+            .unwrap();
+        assert_eq!(error.line_col().map(|(line, _)| line), Some(2));
+    }
+
+    #[test]
+    fn missing_mesh_file_reports_position() {
+        let source = format!("{}\n[[meshes]]\nfile = \"does/not/exist.obj\"\n", CAMERA);
+        let error = parse_scene(&source, Path::new("")).err().unwrap();
+        assert_eq!(error.line_col(), Some((7, 8)));
+    }
+
+    #[test]
+    fn emissive_material_and_black_background() {
+        let source = format!(
+            "{}\n[background]\ntype = \"constant\"\ncolor = [0.0, 0.0, 0.0]\n\n\
+             [materials.lamp]\ntype = \"emissive\"\nradiance = [4.0, 4.0, 4.0]\n\n\
+             [[spheres]]\ncenter = [0.0, 0.0, -2.0]\nradius = 0.5\nmaterial = \"lamp\"\n",
+            CAMERA
+        );
+        let scene = parse_scene(&source, Path::new("")).unwrap();
+        let r = Ray::new(Vec3::origin(), Vec3::new(0.0, 0.0, -1.0));
+        assert!(matches!(scene.environment, Environment::Constant(c) if c == Color::new_black()));
+        assert!(scene.world.hit(&r, 0.0, f64::INFINITY).is_some());
+        assert_eq!(scene.lights.len(), 1);
+    }
+
+    #[test]
+    fn textures_are_resolved_by_name() {
+        let source = format!(
+            "{}\n[textures.checks]\ntype = \"checker\"\nsize = 1.0\n\
+             even = \"stone\"\nodd = [0.0, 0.0, 1.0]\n\n\
+             [textures.stone]\ntype = \"marble\"\nscale = 4.0\n\n\
+             [materials.floor]\ntype = \"lambertian\"\nalbedo = \"checks\"\n\n\
+             [[spheres]]\ncenter = [0.0, 0.0, -2.0]\nradius = 0.5\nmaterial = \"floor\"\n",
+            CAMERA
+        );
+        let scene = parse_scene(&source, Path::new("")).unwrap();
+        let r = Ray::new(Vec3::origin(), Vec3::new(0.0, 0.0, -1.0));
+        let hit = scene.world.hit(&r, 0.0, f64::INFINITY).unwrap();
+        match hit.material {
+            MaterialVariants::Lambertian(TextureVariants::Checker { even, .. }) => {
+                assert!(matches!(**even, TextureVariants::Noise { scale, .. } if scale == 4.0))
+            }
+            _ => panic!("expected a checker texture"),
+        }
+    }
+
+    #[test]
+    fn texture_errors_report_position() {
+        let unknown = format!(
+            "{}\n[materials.floor]\ntype = \"lambertian\"\nalbedo = \"missing\"\n\n\
+             [[spheres]]\ncenter = [0.0, 0.0, -2.0]\nradius = 0.5\nmaterial = \"floor\"\n",
+            CAMERA
+        );
+        let error = parse_scene(&unknown, Path::new("")).err().unwrap();
+        assert_eq!(error.line_col(), Some((13, 12)));
+
+        let cycle = format!(
+            "{}\n[textures.a]\ntype = \"checker\"\nsize = 1.0\neven = \"a\"\n\
+             odd = [0.0, 0.0, 0.0]\n\n[materials.floor]\ntype = \"lambertian\"\n\
+             albedo = \"a\"\n\n[[spheres]]\ncenter = [0.0, 0.0, -2.0]\nradius = 0.5\n\
+             material = \"floor\"\n",
+            CAMERA
+        );
+        let error = parse_scene(&cycle, Path::new("")).err().unwrap();
+        assert_eq!(error.line_col(), Some((7, 8)));
+    }
+
+    #[test]
+    fn planes_quads_disks_and_boxes() {
+        let source = format!(
+            "{}\n[materials.white]\ntype = \"lambertian\"\nalbedo = [0.7, 0.7, 0.7]\n\n\
+             [materials.lamp]\ntype = \"emissive\"\nradiance = [4.0, 4.0, 4.0]\n\n\
+             [[planes]]\npoint = [0.0, -1.0, 0.0]\nnormal = [0.0, 1.0, 0.0]\n\
+             material = \"white\"\n\n\
+             [[quads]]\ncorner = [-1.0, 3.0, -1.0]\nu = [2.0, 0.0, 0.0]\nv = [0.0, 0.0, 2.0]\n\
+             material = \"lamp\"\n\n\
+             [[disks]]\ncenter = [5.0, 3.0, 0.0]\nnormal = [0.0, -1.0, 0.0]\nradius = 0.5\n\
+             material = \"lamp\"\n\n\
+             [[boxes]]\nmin = [-0.5, -1.0, -0.5]\nmax = [0.5, 0.0, 0.5]\nmaterial = \"white\"\n\
+             transform = {{ rotate = [0.0, 45.0, 0.0] }}\n",
+            CAMERA
+        );
+        let scene = parse_scene(&source, Path::new("")).unwrap();
+        assert_eq!(scene.world.objects.len(), 4);
+        assert_eq!(scene.lights.len(), 2);
+
+        let down = |x: f64| Ray::new(Vec3::new(x, 2.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        // The rotated box reaches out to sqrt(2) / 2 along x, beyond that is the plane.
+        let hit = scene.world.hit(&down(0.6), 0.0, f64::INFINITY).unwrap();
+        assert!((hit.p.y - 0.0).abs() < 1e-12);
+        let hit = scene.world.hit(&down(0.8), 0.0, f64::INFINITY).unwrap();
+        assert!((hit.p.y + 1.0).abs() < 1e-12);
+
+        let up = |x: f64| Ray::new(Vec3::new(x, 2.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        for x in &[0.0, 5.0] {
+            let hit = scene.world.hit(&up(*x), 0.0, f64::INFINITY).unwrap();
+            assert_eq!(hit.face, Face::Outside);
+            assert!(hit.material.is_emissive());
+        }
+    }
+
+    #[test]
+    fn media_need_phase_functions() {
+        let source = format!(
+            "{}\n[materials.fog]\ntype = \"henyey-greenstein\"\nalbedo = [1.0, 1.0, 1.0]\n\
+             g = 0.5\n\n[[media]]\n\
+             boundary = {{ type = \"box\", min = [-1.0, -1.0, -3.0], max = [1.0, 1.0, -1.0] }}\n\
+             density = 1000.0\nmaterial = \"fog\"\n",
+            CAMERA
+        );
+        let scene = parse_scene(&source, Path::new("")).unwrap();
+        let r = Ray::new(Vec3::origin(), Vec3::new(0.0, 0.0, -1.0));
+        let mut rng = sample_rng(16, 0, 0);
+        let hit = scene
+            .world
+            .sample_hit(&r, 0.0, f64::INFINITY, &mut rng)
+            .unwrap();
+        assert!(hit.t > 1.0 && hit.t < 1.1);
+        assert!(matches!(hit.material, MaterialVariants::HenyeyGreenstein(_, g) if *g == 0.5));
+
+        let surface = format!(
+            "{}\n[materials.red]\ntype = \"lambertian\"\nalbedo = [1.0, 0.0, 0.0]\n\n\
+             [[media]]\n\
+             boundary = {{ type = \"sphere\", center = [0.0, 0.0, 0.0], radius = 1.0 }}\n\
+             density = 1.0\nmaterial = \"red\"\n",
+            CAMERA
+        );
+        let error = parse_scene(&surface, Path::new("")).err().unwrap();
+        assert_eq!(error.line_col(), Some((13, 12)));
+    }
+
+    #[test]
+    fn conductors_from_presets_or_complex_indices() {
+        let conductor = |fields: &str| {
+            let source = format!(
+                "{}\n[materials.metal]\ntype = \"conductor\"\n{}\n\n\
+                 [[spheres]]\ncenter = [0.0, 0.0, -1.0]\nradius = 0.5\nmaterial = \"metal\"\n",
+                CAMERA, fields
+            );
+            parse_scene(&source, Path::new(""))
+        };
+        let r = Ray::new(Vec3::origin(), Vec3::new(0.0, 0.0, -1.0));
+
+        let scene = conductor("metal = \"copper\"\nroughness = 0.2").unwrap();
+        let hit = scene.world.hit(&r, 0.0, f64::INFINITY).unwrap();
+        assert!(matches!(hit.material, MaterialVariants::Conductor(c)
+            if c.roughness == 0.2 && c.eta == Conductor::preset("copper", 0.0).unwrap().eta));
+
+        let scene = conductor("eta = [1.0, 2.0, 3.0]\nk = [4.0, 5.0, 6.0]").unwrap();
+        let hit = scene.world.hit(&r, 0.0, f64::INFINITY).unwrap();
+        assert!(matches!(hit.material, MaterialVariants::Conductor(c)
+            if c.roughness == 0.0 && c.k == Color::new(4.0, 5.0, 6.0)));
+
+        for fields in &["metal = \"brass\"", "eta = [1.0, 2.0, 3.0]"] {
+            let error = conductor(fields).err().unwrap();
+            assert_eq!(error.line_col(), Some((13, 12)), "{}", fields);
+        }
+    }
+
+    #[test]
+    fn principled_materials_default_every_slider() {
+        let source = format!(
+            "{}\n[textures.checks]\ntype = \"checker\"\nsize = 1.0\neven = [0.0, 0.0, 0.0]\n\
+             odd = [1.0, 1.0, 1.0]\n\n\
+             [materials.paint]\ntype = \"principled\"\nbase_color = \"checks\"\n\
+             metallic = 0.3\nclearcoat = 1.0\n\n\
+             [[spheres]]\ncenter = [0.0, 0.0, -1.0]\nradius = 0.5\nmaterial = \"paint\"\n",
+            CAMERA
+        );
+        let scene = parse_scene(&source, Path::new("")).unwrap();
+        let r = Ray::new(Vec3::origin(), Vec3::new(0.0, 0.0, -1.0));
+        let hit = scene.world.hit(&r, 0.0, f64::INFINITY).unwrap();
+        match hit.material {
+            MaterialVariants::Principled(principled) => {
+                assert_eq!((principled.metallic, principled.clearcoat), (0.3, 1.0));
+                assert_eq!((principled.roughness, principled.ior), (0.5, 1.5));
+                assert!(matches!(
+                    principled.base_color,
+                    TextureVariants::Checker { .. }
+                ));
+            }
+            _ => panic!("expected a principled material"),
+        }
+
+        let typo = source.replace("clearcoat = 1.0", "clear_coat = 1.0");
+        assert!(parse_scene(&typo, Path::new("")).is_err());
+    }
+
+    #[test]
+    fn moving_objects_during_the_shutter_interval() {
+        let source = "[camera]\nlook_from = [0.0, 0.0, 1.0]\nlook_at = [0.0, 0.0, 0.0]\n\
+                      vfov = 40.0\nshutter = [0.0, 1.0]\n\n\
+                      [materials.red]\ntype = \"lambertian\"\nalbedo = [1.0, 0.0, 0.0]\n\n\
+                      [[spheres]]\ncenter = [0.0, 0.0, -2.0]\nradius = 0.5\nmaterial = \"red\"\n\
+                      velocity = [2.0, 0.0, 0.0]\n\n\
+                      [[boxes]]\nmin = [-1.0, -0.1, -0.1]\nmax = [1.0, 0.1, 0.1]\n\
+                      material = \"red\"\n\
+                      transform = { translate = [0.0, 0.0, -10.0], spin = [0.0, 0.0, 90.0] }\n";
+        let scene = parse_scene(source, Path::new("")).unwrap();
+        assert_eq!(
+            (scene.camera.shutter_open, scene.camera.shutter_close),
+            (0.0, 1.0)
+        );
+        assert_eq!(scene.world.objects.len(), 2);
+
+        let at = |x: f64, y: f64, time: f64| {
+            let r = Ray::with_time(Vec3::new(x, y, 0.0), Vec3::new(0.0, 0.0, -1.0), time);
+            scene.world.hit(&r, 0.0, f64::INFINITY).map(|hit| hit.t)
+        };
+        assert_eq!(at(0.0, 0.0, 0.0), Some(1.5));
+        assert_eq!(at(2.0, 0.0, 1.0), Some(1.5));
+        // The bar turns from along x to along y.
+        assert!(matches!(at(0.9, 0.0, 0.0), Some(t) if (t - 9.9).abs() < 1e-9));
+        assert_eq!(at(0.9, 0.0, 1.0), None);
+        assert!(matches!(at(0.0, 0.9, 1.0), Some(t) if (t - 9.9).abs() < 1e-9));
+    }
+
+    #[test]
+    fn moving_objects_cannot_be_emissive() {
+        let scenes = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes");
+        let objects = [
+            "[[spheres]]\ncenter = [0.0, 0.0, -2.0]\nradius = 0.5\nmaterial = \"lamp\"\n\
+             velocity = [1.0, 0.0, 0.0]\n",
+            "[[boxes]]\nmin = [-1.0, -1.0, -3.0]\nmax = [1.0, 1.0, -2.0]\nmaterial = \"lamp\"\n\
+             transform = { velocity = [1.0, 0.0, 0.0] }\n",
+            "[[meshes]]\nfile = \"models/cube.obj\"\nmaterial = \"lamp\"\n\
+             transform = { spin = [0.0, 90.0, 0.0] }\n",
+        ];
+        for (object, line) in objects.iter().zip(&[13, 13, 12]) {
+            let source = format!(
+                "{}\n[materials.lamp]\ntype = \"emissive\"\nradiance = [4.0, 4.0, 4.0]\n\n{}",
+                CAMERA, object
+            );
+            let error = parse_scene(&source, &scenes).err().unwrap();
+            assert_eq!(error.line_col(), Some((*line, 12)), "{}", object);
+        }
+    }
+
+    #[test]
+    fn environment_images_are_loaded_relative_to_the_scene() {
+        let scenes = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes");
+        let source = format!(
+            "{}\n[environment]\ntype = \"image\"\nfile = \"maps/sunset.hdr\"\nintensity = 2.0\n",
+            CAMERA
+        );
+        let scene = parse_scene(&source, &scenes).unwrap();
+        match &scene.environment {
+            Environment::Image(map) => assert_eq!((map.width, map.height), (128, 64)),
+            _ => panic!("expected an environment image"),
+        }
+        assert!(scene.environment.is_sampled());
+        // Straight down sees the ground of the image, at twice its radiance.
+        let ground = scene.environment.radiance(&Vec3::new(0.0, -1.0, 0.0));
+        assert!((ground.x - 0.24).abs() < 0.01);
+
+        let missing = format!(
+            "{}\n[environment]\ntype = \"image\"\nfile = \"maps/missing.hdr\"\n",
+            CAMERA
+        );
+        let error = parse_scene(&missing, &scenes).err().unwrap();
+        assert_eq!(error.line_col(), Some((8, 8)));
+        let daylight = format!(
+            "{}\n[environment]\ntype = \"daylight\"\nsun_elevation = 10.0\n",
+            CAMERA
+        );
+        let scene = parse_scene(&daylight, &scenes).unwrap();
+        match &scene.environment {
+            Environment::Daylight(daylight) => {
+                assert!((daylight.sun_direction().y - 10.0_f64.to_radians().sin()).abs() < 1e-12)
+            }
+            _ => panic!("expected a daylight sky"),
+        }
+
+        let sky = format!("{}\n[background]\ntype = \"sky\"\n", CAMERA);
+        let scene = parse_scene(&sky, &scenes).unwrap();
+        assert!(matches!(scene.environment, Environment::Gradient));
+
+        let unknown = format!("{}\n[background]\ntype = \"starfield\"\n", CAMERA);
+        let error = parse_scene(&unknown, &scenes).err().unwrap();
+        assert_eq!(error.line_col(), Some((7, 8)));
+    }
+
+    #[test]
+    fn mesh_instances_with_transforms() {
+        let source = format!(
+            "{}\n[[meshes]]\nfile = \"models/cube.obj\"\n\
+             transform = {{ scale = 0.5, translate = [10.0, 0.0, 0.0] }}\n\n\
+             [[meshes]]\nfile = \"models/cube.obj\"\n\
+             transform = {{ rotate = [0.0, 45.0, 0.0], translate = [-10.0, 0.0, 0.0] }}\n",
+            CAMERA
+        );
+        let scenes = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes");
+        let scene = parse_scene(&source, &scenes).unwrap();
+        assert_eq!(scene.world.objects.len(), 2);
+
+        let down = |x: f64| Ray::new(Vec3::new(x, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let hit = scene.world.hit(&down(10.4), 0.0, f64::INFINITY).unwrap();
+        assert!((hit.p.y - 0.5).abs() < 1e-12);
+        assert!(scene.world.hit(&down(10.6), 0.0, f64::INFINITY).is_none());
+        // The corners of the rotated cube reach out to sqrt(2) along x.
+        assert!(scene.world.hit(&down(-8.7), 0.0, f64::INFINITY).is_some());
+
+        let zero = format!(
+            "{}\n[[meshes]]\nfile = \"models/cube.obj\"\ntransform = {{ scale = 0.0 }}\n",
+            CAMERA
+        );
+        let error = parse_scene(&zero, &scenes).err().unwrap();
+        assert_eq!(error.line_col(), Some((7, 8)));
+    }
+}
@@ -0,0 +1,419 @@
+use std::sync::Arc;
+
+use rand::distributions::{Distribution, Uniform};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::camera::CameraSettings;
+use crate::color::Color;
+use crate::constant_medium::ConstantMedium;
+use crate::cuboid::Cuboid;
+use crate::environment::Environment;
+use crate::hittable::HittableList;
+use crate::light::LightList;
+use crate::material_variants::MaterialVariants;
+use crate::perlin::Perlin;
+use crate::plane::Plane;
+use crate::quad::Quad;
+use crate::sphere::Sphere;
+use crate::texture::{NoiseKind, TextureVariants};
+use crate::transform::Transform;
+use crate::transformed::Transformed;
+use crate::vec3::Vec3;
+
+/// Names accepted by [`builtin_scene`].
+pub const BUILTIN_SCENES: [&str; 6] = [
+    "random",
+    "old-world",
+    "simple-light",
+    "textures",
+    "cornell",
+    "cornell-smoke",
+];
+
+/// A world together with the camera it is meant to be viewed from.
+pub struct Scene {
+    pub camera: CameraSettings,
+    pub world: HittableList,
+    /// Emitters of `world` that are sampled directly; they are part of `world` as well.
+    pub lights: LightList,
+    pub environment: Environment,
+}
+
+/// Builds one of the scenes listed in [`BUILTIN_SCENES`]; `seed` drives any random placement.
+pub fn builtin_scene(name: &str, seed: u64) -> Option<Scene> {
+    match name {
+        "random" => Some(Scene {
+            camera: CameraSettings {
+                look_from: Vec3::new(13.0, 2.0, 3.0),
+                look_at: Vec3::new(0.0, 0.0, 0.0),
+                vup: Vec3::new(0.0, 1.0, 0.0),
+                vfov: 20.0,
+                aperture: 0.1,
+                focus_dist: 10.0,
+                shutter_open: 0.0,
+                shutter_close: 0.0,
+            },
+            world: random_scene(&mut StdRng::seed_from_u64(seed)),
+            lights: LightList::new(),
+            environment: Environment::Gradient,
+        }),
+        "old-world" => Some(Scene {
+            camera: CameraSettings {
+                look_from: Vec3::new(0.0, 0.5, 1.0),
+                look_at: Vec3::new(0.0, 0.0, -3.0),
+                vup: Vec3::new(0.0, 1.0, 0.0),
+                vfov: 50.0,
+                aperture: 0.0,
+                focus_dist: 4.0,
+                shutter_open: 0.0,
+                shutter_close: 0.0,
+            },
+            world: old_world(),
+            lights: LightList::new(),
+            environment: Environment::Gradient,
+        }),
+        "simple-light" => {
+            let (world, lights) = simple_light();
+            Some(Scene {
+                camera: CameraSettings {
+                    look_from: Vec3::new(26.0, 3.0, 6.0),
+                    look_at: Vec3::new(0.0, 2.0, 0.0),
+                    vup: Vec3::new(0.0, 1.0, 0.0),
+                    vfov: 20.0,
+                    aperture: 0.0,
+                    focus_dist: 10.0,
+                    shutter_open: 0.0,
+                    shutter_close: 0.0,
+                },
+                world,
+                lights,
+                environment: Environment::Constant(Color::new_black()),
+            })
+        }
+        "textures" => Some(Scene {
+            camera: CameraSettings {
+                look_from: Vec3::new(13.0, 2.0, 3.0),
+                look_at: Vec3::new(0.0, 0.5, 1.0),
+                vup: Vec3::new(0.0, 1.0, 0.0),
+                vfov: 30.0,
+                aperture: 0.0,
+                focus_dist: 10.0,
+                shutter_open: 0.0,
+                shutter_close: 0.0,
+            },
+            world: textures(&mut StdRng::seed_from_u64(seed)),
+            lights: LightList::new(),
+            environment: Environment::Gradient,
+        }),
+        "cornell" | "cornell-smoke" => {
+            let (world, lights) = if name == "cornell" {
+                cornell_box()
+            } else {
+                cornell_smoke()
+            };
+            Some(Scene {
+                camera: CameraSettings {
+                    look_from: Vec3::new(278.0, 278.0, -800.0),
+                    look_at: Vec3::new(278.0, 278.0, 0.0),
+                    vup: Vec3::new(0.0, 1.0, 0.0),
+                    vfov: 40.0,
+                    aperture: 0.0,
+                    focus_dist: 10.0,
+                    shutter_open: 0.0,
+                    shutter_close: 0.0,
+                },
+                world,
+                lights,
+                environment: Environment::Constant(Color::new_black()),
+            })
+        }
+        _ => None,
+    }
+}
+
+pub fn old_world() -> HittableList {
+    let material_ground = MaterialVariants::Lambertian(Color::new(0.8, 0.8, 0.0).into());
+    let material_center = MaterialVariants::Lambertian(Color::new(0.7, 0.3, 0.3).into());
+    let material_metal1 = MaterialVariants::Metal(Color::new(0.8, 0.8, 0.8).into(), 0.0);
+    let material_metal2 = MaterialVariants::Metal(Color::new(0.8, 0.8, 0.8).into(), 0.1);
+    let material_metal3 = MaterialVariants::Metal(Color::new(0.8, 0.6, 0.2).into(), 0.1);
+
+    let material_dielectrical = MaterialVariants::Dielectric(1.5, 0.0);
+
+    // Scene
+    let mut scene = HittableList::new();
+
+    scene.add(Box::new(Sphere::new(
+        Vec3::new(1.0, 0.5, -1.0),
+        0.1,
+        material_center.clone(),
+    )));
+
+    scene.add(Box::new(Sphere::new(
+        Vec3::new(-1.9, 0.0, -5.0),
+        1.0,
+        material_metal1,
+    )));
+    scene.add(Box::new(Sphere::new(
+        Vec3::new(-0.7, 0.5, -4.0),
+        0.2,
+        material_center,
+    )));
+
+    scene.add(Box::new(Sphere::new(
+        Vec3::new(0.9, 0.2, -5.0),
+        1.0,
+        material_metal3,
+    )));
+
+    scene.add(Box::new(Sphere::new(
+        Vec3::new(0.0, 0.0, -3.0),
+        0.5,
+        material_dielectrical.clone(),
+    )));
+    scene.add(Box::new(Sphere::new(
+        Vec3::new(0.0, 0.0, -3.0),
+        -0.4,
+        material_dielectrical.clone(),
+    )));
+
+    scene.add(Box::new(Sphere::new(
+        Vec3::new(0.0, 1.4, -3.0),
+        0.75,
+        material_dielectrical,
+    )));
+
+    scene.add(Box::new(Sphere::new(
+        Vec3::new(1.7, -0.2, -4.0),
+        0.5,
+        material_metal2,
+    )));
+
+    // Ground
+    scene.add(Box::new(Sphere::new(
+        Vec3::new(0.0, -202.0, -1.0),
+        200.0,
+        material_ground,
+    )));
+
+    scene
+}
+
+pub fn random_scene<R: Rng + ?Sized>(rng: &mut R) -> HittableList {
+    let mut world: HittableList = HittableList::new();
+
+    let uniform_dist = Uniform::new_inclusive(0.0, 1.0);
+    let uniform_dist_0_5 = Uniform::new_inclusive(0.0, 0.5);
+
+    let material_ground = MaterialVariants::Lambertian(Color::new(0.5, 0.5, 0.5).into());
+    world.add(Box::new(Plane::new(
+        Vec3::origin(),
+        Vec3::new(0.0, 1.0, 0.0),
+        material_ground,
+    )));
+
+    for a in -11..11 {
+        for b in -11..11 {
+            let rand = uniform_dist.sample(rng);
+            let center = Vec3::new(
+                a as f64 + 0.9 * uniform_dist.sample(rng),
+                0.2,
+                b as f64 + 0.9 * uniform_dist.sample(rng),
+            );
+
+            if (center - Vec3::new(4.0, 0.2, 0.0)).length() > 0.9 {
+                if rand < 0.6 {
+                    let albedo: Color = Vec3::random_with_bounds_from(rng, 0.2, 1.0)
+                        * Vec3::random_with_bounds_from(rng, 0.2, 1.0);
+                    let material = MaterialVariants::Lambertian(albedo.into());
+                    world.add(Box::new(Sphere::new(center, 0.2, material)));
+                } else if rand < 0.8 {
+                    let albedo: Color = Vec3::random_with_bounds_from(rng, 0.0, 0.5);
+                    let fuzz = uniform_dist_0_5.sample(rng);
+                    let material = MaterialVariants::Metal(albedo.into(), fuzz);
+                    world.add(Box::new(Sphere::new(center, 0.2, material)));
+                } else {
+                    let material = MaterialVariants::Dielectric(1.5, 0.0);
+                    world.add(Box::new(Sphere::new(center, 0.2, material)));
+                }
+            }
+        }
+    }
+
+    world.add(Box::new(Sphere::new(
+        Vec3::new(0.0, 1.0, 0.0),
+        1.0,
+        MaterialVariants::Dielectric(1.5, 0.0),
+    )));
+
+    world.add(Box::new(Sphere::new(
+        Vec3::new(-4.0, 1.0, 0.0),
+        1.0,
+        MaterialVariants::Lambertian(Color::new(0.4, 0.2, 1.0).into()),
+    )));
+
+    world.add(Box::new(Sphere::new(
+        Vec3::new(4.0, 1.0, 0.0),
+        1.0,
+        MaterialVariants::Metal(Color::new(0.7, 0.6, 0.5).into(), 0.0),
+    )));
+
+    world
+}
+
+/// Two spheres lit only by a small emissive sphere above them.
+pub fn simple_light() -> (HittableList, LightList) {
+    let mut world = HittableList::new();
+    let mut lights = LightList::new();
+
+    world.add(Box::new(Plane::new(
+        Vec3::origin(),
+        Vec3::new(0.0, 1.0, 0.0),
+        MaterialVariants::Lambertian(Color::new(0.5, 0.5, 0.5).into()),
+    )));
+    world.add(Box::new(Sphere::new(
+        Vec3::new(0.0, 2.0, 0.0),
+        2.0,
+        MaterialVariants::Lambertian(Color::new(0.4, 0.2, 0.1).into()),
+    )));
+    let lamp = Sphere::new(
+        Vec3::new(0.0, 7.0, 0.0),
+        1.0,
+        MaterialVariants::Emissive(Color::new(4.0, 4.0, 4.0).into()),
+    );
+    lights.add(Box::new(lamp.clone()));
+    world.add(Box::new(lamp));
+
+    (world, lights)
+}
+
+/// A checkered ground with a marble and a turbulent sphere; `rng` seeds the noise.
+pub fn textures<R: Rng + ?Sized>(rng: &mut R) -> HittableList {
+    let mut world = HittableList::new();
+    let perlin = Arc::new(Perlin::new(rng));
+
+    let checker = TextureVariants::Checker {
+        size: 1.0,
+        even: Arc::new(Color::new(0.2, 0.3, 0.1).into()),
+        odd: Arc::new(Color::new(0.9, 0.9, 0.9).into()),
+    };
+    world.add(Box::new(Plane::new(
+        // Keep the surface away from the cell boundary at y = 0.
+        Vec3::new(0.0, -0.5, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        MaterialVariants::Lambertian(checker),
+    )));
+    world.add(Box::new(Sphere::new(
+        Vec3::new(0.0, 0.5, 0.0),
+        1.0,
+        MaterialVariants::Lambertian(TextureVariants::Noise {
+            perlin: Arc::clone(&perlin),
+            kind: NoiseKind::Marble,
+            scale: 4.0,
+        }),
+    )));
+    world.add(Box::new(Sphere::new(
+        Vec3::new(0.0, 0.5, 2.5),
+        1.0,
+        MaterialVariants::Metal(
+            TextureVariants::Noise {
+                perlin,
+                kind: NoiseKind::Turbulence,
+                scale: 2.0,
+            },
+            0.2,
+        ),
+    )));
+
+    world
+}
+
+/// The Cornell box with two rotated blocks, lit by a square lamp in the ceiling; 555 units wide.
+pub fn cornell_box() -> (HittableList, LightList) {
+    let (mut world, lights) = cornell_room();
+    let white = MaterialVariants::Lambertian(Color::new(0.73, 0.73, 0.73).into());
+    let (tall, short) = cornell_blocks(white);
+    world.add(Box::new(tall));
+    world.add(Box::new(short));
+    (world, lights)
+}
+
+/// The Cornell box with the blocks made of dark and light smoke.
+pub fn cornell_smoke() -> (HittableList, LightList) {
+    let (mut world, lights) = cornell_room();
+    // Only the shape of the blocks matters.
+    let (tall, short) = cornell_blocks(MaterialVariants::Dielectric(1.0, 0.0));
+    world.add(Box::new(ConstantMedium::new(
+        tall,
+        0.01,
+        MaterialVariants::Isotropic(Color::new(0.1, 0.1, 0.1).into()),
+    )));
+    world.add(Box::new(ConstantMedium::new(
+        short,
+        0.01,
+        MaterialVariants::HenyeyGreenstein(Color::new(0.9, 0.9, 0.9).into(), 0.5),
+    )));
+    (world, lights)
+}
+
+/// The walls and the lamp of the Cornell box.
+fn cornell_room() -> (HittableList, LightList) {
+    let mut world = HittableList::new();
+    let mut lights = LightList::new();
+
+    let red = MaterialVariants::Lambertian(Color::new(0.65, 0.05, 0.05).into());
+    let white = MaterialVariants::Lambertian(Color::new(0.73, 0.73, 0.73).into());
+    let green = MaterialVariants::Lambertian(Color::new(0.12, 0.45, 0.15).into());
+
+    // The walls face into the box.
+    let x = |length: f64| Vec3::new(length, 0.0, 0.0);
+    let y = |length: f64| Vec3::new(0.0, length, 0.0);
+    let z = |length: f64| Vec3::new(0.0, 0.0, length);
+    world.add(Box::new(Quad::new(x(555.0), z(555.0), y(555.0), green)));
+    world.add(Box::new(Quad::new(Vec3::origin(), y(555.0), z(555.0), red)));
+    world.add(Box::new(Quad::new(
+        Vec3::origin(),
+        z(555.0),
+        x(555.0),
+        white.clone(),
+    )));
+    world.add(Box::new(Quad::new(
+        y(555.0),
+        x(555.0),
+        z(555.0),
+        white.clone(),
+    )));
+    world.add(Box::new(Quad::new(z(555.0), y(555.0), x(555.0), white)));
+
+    let lamp = Quad::new(
+        Vec3::new(213.0, 554.0, 227.0),
+        x(130.0),
+        z(105.0),
+        MaterialVariants::Emissive(Color::new(15.0, 15.0, 15.0).into()),
+    );
+    lights.add(Box::new(lamp.clone()));
+    world.add(Box::new(lamp));
+
+    (world, lights)
+}
+
+/// The tall and the short block of the Cornell box.
+fn cornell_blocks(material: MaterialVariants) -> (Transformed<Cuboid>, Transformed<Cuboid>) {
+    let tall = Cuboid::new(
+        Vec3::origin(),
+        Vec3::new(165.0, 330.0, 165.0),
+        material.clone(),
+    );
+    let short = Cuboid::new(Vec3::origin(), Vec3::new(165.0, 165.0, 165.0), material);
+    (
+        Transformed::new(
+            tall,
+            Transform::translate(Vec3::new(265.0, 0.0, 295.0)) * Transform::rotate_y(15.0),
+        ),
+        Transformed::new(
+            short,
+            Transform::translate(Vec3::new(130.0, 0.0, 65.0)) * Transform::rotate_y(-18.0),
+        ),
+    )
+}
@@ -1,8 +1,13 @@
-use crate::hittable::{Face, HitRecord, Hittable};
+use crate::aabb::Aabb;
+use crate::hittable::{get_face_normal, HitRecord, Hittable};
+use crate::light::Light;
 use crate::material_variants::MaterialVariants;
+use crate::onb::Onb;
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 use crate::vec3::Vec3;
 
+#[derive(Clone)]
 pub struct Sphere {
     pub center: Vec3,
     pub radius: f64,
@@ -19,60 +24,143 @@ impl Sphere {
     }
 }
 
-#[inline]
-fn get_face_normal(r: &Ray, outward_normal: &Vec3) -> (Face, Vec3) {
-    let face = if r.direction.dot(outward_normal) < 0.0 {
-        Face::Outside
+/// Closest intersection of `r` with the sphere around `center` within `(t_min, t_max)`; shared
+/// with moving spheres.
+pub(crate) fn hit_sphere<'a>(
+    center: &Vec3,
+    radius: f64,
+    material: &'a MaterialVariants,
+    r: &Ray,
+    t_min: f64,
+    t_max: f64,
+) -> Option<HitRecord<'a>> {
+    let oc = r.origin - *center;
+    let a = r.direction.squared_length();
+    let half_b = oc.dot(&r.direction);
+    let c = oc.squared_length() - radius.powi(2);
+    let discriminant = half_b.powi(2) - a * c;
+
+    if discriminant < 0.0 {
+        return None;
+    }
+
+    let discriminant_root = discriminant.sqrt();
+
+    let first_root = (-half_b - discriminant_root) / a;
+    let second_root = (-half_b + discriminant_root) / a;
+    let t = if (first_root < t_max) && (first_root > t_min) {
+        first_root
+    } else if (second_root < t_max) && (second_root > t_min) {
+        second_root
     } else {
-        Face::Inside
-    };
-    let normal = match face {
-        Face::Outside => *outward_normal,
-        Face::Inside => -(*outward_normal),
+        return None;
     };
-    (face, normal)
+
+    let p = r.at(t);
+    let outward_normal = (p - *center) / radius;
+    let (face, normal) = get_face_normal(r, &outward_normal);
+    let (u, v) = sphere_uv(&((p - *center) / radius.abs()));
+    Some(HitRecord {
+        p,
+        t,
+        u,
+        v,
+        normal,
+        face,
+        material,
+    })
+}
+
+/// Maps a point on the unit sphere to longitude `u` and latitude `v` in [0, 1], with `v = 0` at
+/// -y and `u = 0` at -x, increasing towards +z.
+pub fn sphere_uv(p: &Vec3) -> (f64, f64) {
+    let theta = (-p.y).clamp(-1.0, 1.0).acos();
+    let phi = (-p.z).atan2(p.x) + std::f64::consts::PI;
+    (
+        phi / (2.0 * std::f64::consts::PI),
+        theta / std::f64::consts::PI,
+    )
 }
 
 impl Hittable for Sphere {
-    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
-        let oc = r.origin - self.center;
-        let a = r.direction.squared_length();
-        let half_b = oc.dot(&r.direction);
-        let c = oc.squared_length() - self.radius.powi(2);
-        let discriminant = half_b.powi(2) - a * c;
-
-        if discriminant < 0.0 {
-            return None;
-        }
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        hit_sphere(&self.center, self.radius, &self.material, r, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        // The radius is negative for the inner surface of hollow spheres.
+        let extent = Vec3::new(self.radius.abs(), self.radius.abs(), self.radius.abs());
+        Some(Aabb::new(self.center - extent, self.center + extent))
+    }
+}
+
+impl Light for Sphere {
+    /// Samples the cone of directions subtended by the sphere, or all directions from inside.
+    fn sample_direction(&self, origin: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        let (r1, r2) = sampler.get_2d();
+        let to_center = self.center - *origin;
+        let distance_squared = to_center.squared_length();
+        let radius_squared = self.radius * self.radius;
+        let phi = 2.0 * std::f64::consts::PI * r1;
 
-        let discriminant_root = discriminant.sqrt();
-
-        let first_root = (-half_b - discriminant_root) / a;
-        if (first_root < t_max) && (first_root > t_min) {
-            let outward_normal = (r.at(first_root) - self.center) / self.radius;
-            let (face, normal) = get_face_normal(r, &outward_normal);
-            return Some(HitRecord {
-                p: r.at(first_root),
-                t: first_root,
-                normal,
-                face,
-                material: self.material,
-            });
+        if distance_squared <= radius_squared {
+            let z = 1.0 - 2.0 * r2;
+            let r = (1.0 - z * z).sqrt();
+            return Vec3::new(r * phi.cos(), r * phi.sin(), z);
         }
 
-        let second_root = (-half_b + discriminant_root) / a;
-        if (second_root < t_max) && (second_root > t_min) {
-            let outward_normal = (r.at(second_root) - self.center) / self.radius;
-            let (face, normal) = get_face_normal(r, &outward_normal);
-            return Some(HitRecord {
-                p: r.at(second_root),
-                t: second_root,
-                normal,
-                face,
-                material: self.material,
-            });
+        let cos_theta_max = (1.0 - radius_squared / distance_squared).sqrt();
+        let z = 1.0 + r2 * (cos_theta_max - 1.0);
+        let r = (1.0 - z * z).sqrt();
+        Onb::from_w(&to_center).local(r * phi.cos(), r * phi.sin(), z)
+    }
+
+    fn pdf_value(&self, origin: &Vec3, direction: &Vec3) -> f64 {
+        if self
+            .hit(&Ray::new(*origin, *direction), 0.001, f64::INFINITY)
+            .is_none()
+        {
+            return 0.0;
+        }
+        let distance_squared = (self.center - *origin).squared_length();
+        let radius_squared = self.radius * self.radius;
+        if distance_squared <= radius_squared {
+            return 1.0 / (4.0 * std::f64::consts::PI);
         }
+        let cos_theta_max = (1.0 - radius_squared / distance_squared).sqrt();
+        1.0 / (2.0 * std::f64::consts::PI * (1.0 - cos_theta_max))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sphere_uv, Sphere};
+    use crate::color::Color;
+    use crate::hittable::Hittable;
+    use crate::material_variants::MaterialVariants;
+    use crate::ray::Ray;
+    use crate::vec3::Vec3;
+
+    #[test]
+    fn uv_at_poles_and_equator() {
+        let close = |(u, v): (f64, f64), expected: (f64, f64)| {
+            (u - expected.0).abs() < 1e-12 && (v - expected.1).abs() < 1e-12
+        };
+        assert!(close(sphere_uv(&Vec3::new(1.0, 0.0, 0.0)), (0.5, 0.5)));
+        assert!(close(sphere_uv(&Vec3::new(0.0, 0.0, 1.0)), (0.25, 0.5)));
+        assert!(close(sphere_uv(&Vec3::new(0.0, 0.0, -1.0)), (0.75, 0.5)));
+        assert!(sphere_uv(&Vec3::new(0.0, 1.0, 0.0)).1 == 1.0);
+        assert!(sphere_uv(&Vec3::new(0.0, -1.0, 0.0)).1 == 0.0);
+    }
 
-        None
+    #[test]
+    fn hollow_sphere_uses_outward_uv() {
+        let material = MaterialVariants::Lambertian(Color::new_white().into());
+        let solid = Sphere::new(Vec3::origin(), 2.0, material.clone());
+        let hollow = Sphere::new(Vec3::origin(), -2.0, material);
+        let r = Ray::new(Vec3::new(5.0, 0.3, 0.2), Vec3::new(-1.0, 0.0, 0.0));
+        let a = solid.hit(&r, 0.0, f64::INFINITY).unwrap();
+        let b = hollow.hit(&r, 0.0, f64::INFINITY).unwrap();
+        assert_eq!((a.u, a.v), (b.u, b.v));
     }
 }
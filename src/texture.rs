@@ -0,0 +1,186 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::color::Color;
+use crate::perlin::Perlin;
+use crate::vec3::Vec3;
+
+pub trait Texture {
+    /// Color at surface coordinates `(u, v)` and world position `p`.
+    fn value(&self, u: f64, v: f64, p: &Vec3) -> Color;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NoiseKind {
+    /// Plain gradient noise mapped to [0, 1].
+    Perlin,
+    /// Several octaves of absolute noise.
+    Turbulence,
+    /// Stripes along z distorted by turbulence.
+    Marble,
+}
+
+#[derive(Debug, Clone)]
+pub enum TextureVariants {
+    Solid(Color),
+    /// Alternates between two textures in cubes with an edge length of `size`.
+    Checker {
+        size: f64,
+        even: Arc<TextureVariants>,
+        odd: Arc<TextureVariants>,
+    },
+    /// Grey noise with a feature size of about `1 / scale`.
+    Noise {
+        perlin: Arc<Perlin>,
+        kind: NoiseKind,
+        scale: f64,
+    },
+    Image(Arc<ImageTexture>),
+}
+
+impl From<Color> for TextureVariants {
+    fn from(color: Color) -> TextureVariants {
+        TextureVariants::Solid(color)
+    }
+}
+
+impl Texture for TextureVariants {
+    fn value(&self, u: f64, v: f64, p: &Vec3) -> Color {
+        match self {
+            TextureVariants::Solid(color) => *color,
+            TextureVariants::Checker { size, even, odd } => {
+                let cell = |x: f64| (x / size).floor() as i64;
+                if (cell(p.x) + cell(p.y) + cell(p.z)).rem_euclid(2) == 0 {
+                    even.value(u, v, p)
+                } else {
+                    odd.value(u, v, p)
+                }
+            }
+            TextureVariants::Noise {
+                perlin,
+                kind,
+                scale,
+            } => {
+                let scaled = *scale * *p;
+                let intensity = match kind {
+                    NoiseKind::Perlin => 0.5 * (1.0 + perlin.noise(&scaled)),
+                    NoiseKind::Turbulence => perlin.turbulence(&scaled, 7),
+                    NoiseKind::Marble => {
+                        0.5 * (1.0 + (scaled.z + 10.0 * perlin.turbulence(p, 7)).sin())
+                    }
+                };
+                intensity * Color::new_white()
+            }
+            TextureVariants::Image(image) => image.value(u, v, p),
+        }
+    }
+}
+
+/// Bitmap texture holding linear colors, addressed with `(0, 0)` at the bottom left and repeating
+/// outside the unit square.
+#[derive(Debug)]
+pub struct ImageTexture {
+    pub width: usize,
+    pub height: usize,
+    /// Rows from top to bottom.
+    pub pixels: Vec<Color>,
+}
+
+impl ImageTexture {
+    pub fn from_pixels(width: usize, height: usize, pixels: Vec<Color>) -> ImageTexture {
+        assert_eq!(pixels.len(), width * height);
+        ImageTexture {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Loads an 8 or 16 bit PNG file. Stored values are converted to linear radiance with the
+    /// same gamma of 2 that is used for 8-bit output.
+    pub fn load<P: AsRef<Path>>(path: P) -> std::io::Result<ImageTexture> {
+        let mut decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+        decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+        let (info, mut reader) = decoder.read_info()?;
+        let mut buffer = vec![0; info.buffer_size()];
+        reader.next_frame(&mut buffer)?;
+
+        let channels = match info.color_type {
+            png::ColorType::Grayscale => 1,
+            png::ColorType::GrayscaleAlpha => 2,
+            png::ColorType::RGB => 3,
+            png::ColorType::RGBA => 4,
+            png::ColorType::Indexed => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "indexed PNG was not expanded",
+                ))
+            }
+        };
+        let width = info.width as usize;
+        let height = info.height as usize;
+        let linear = |byte: u8| (byte as f64 / 255.0).powi(2);
+        let pixels = buffer
+            .chunks(info.line_size)
+            .take(height)
+            .flat_map(|row| row[..width * channels].chunks(channels))
+            .map(|pixel| {
+                if channels < 3 {
+                    linear(pixel[0]) * Color::new_white()
+                } else {
+                    Color::new(linear(pixel[0]), linear(pixel[1]), linear(pixel[2]))
+                }
+            })
+            .collect();
+        Ok(ImageTexture::from_pixels(width, height, pixels))
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _p: &Vec3) -> Color {
+        if self.pixels.is_empty() {
+            return Color::new_black();
+        }
+        let wrap = |x: f64| x - x.floor();
+        let i = ((wrap(u) * self.width as f64) as usize).min(self.width - 1);
+        let j = (((1.0 - wrap(v)) * self.height as f64) as usize).min(self.height - 1);
+        self.pixels[j * self.width + i]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{ImageTexture, Texture, TextureVariants};
+    use crate::color::Color;
+    use crate::vec3::Vec3;
+
+    #[test]
+    fn checker_alternates_between_cells() {
+        let checker = TextureVariants::Checker {
+            size: 0.5,
+            even: Arc::new(Color::new_white().into()),
+            odd: Arc::new(Color::new_black().into()),
+        };
+        let at = |x, y, z| checker.value(0.0, 0.0, &Vec3::new(x, y, z));
+        assert_eq!(at(0.1, 0.1, 0.1), Color::new_white());
+        assert_eq!(at(0.6, 0.1, 0.1), Color::new_black());
+        assert_eq!(at(-0.1, 0.1, 0.1), Color::new_black());
+        assert_eq!(at(0.6, 0.6, 0.1), Color::new_white());
+    }
+
+    #[test]
+    fn image_lookup_starts_bottom_left_and_repeats() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let blue = Color::new(0.0, 0.0, 1.0);
+        let image = ImageTexture::from_pixels(2, 1, vec![red, blue]);
+        let p = Vec3::origin();
+        assert_eq!(image.value(0.25, 0.5, &p), red);
+        assert_eq!(image.value(0.75, 0.5, &p), blue);
+        assert_eq!(image.value(1.25, 0.5, &p), red);
+        assert_eq!(image.value(1.0, 1.0, &p), red);
+    }
+}
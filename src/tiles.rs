@@ -0,0 +1,89 @@
+use std::ops::Range;
+
+/// Edge length of the square tiles an image is rendered in, in pixels.
+pub const TILE_SIZE: usize = 32;
+
+/// Position of the `d`-th cell along a Hilbert curve filling an `n` × `n` grid, where `n` is a
+/// power of two. Consecutive cells are always neighbours.
+pub fn hilbert_position(n: usize, mut d: usize) -> (usize, usize) {
+    let (mut x, mut y) = (0, 0);
+    let mut s = 1;
+    while s < n {
+        let rx = 1 & (d / 2);
+        let ry = 1 & (d ^ rx);
+        // Rotate the quadrant so the sub-curves connect.
+        if ry == 0 {
+            if rx == 1 {
+                x = s - 1 - x;
+                y = s - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        x += s * rx;
+        y += s * ry;
+        d /= 4;
+        s *= 2;
+    }
+    (x, y)
+}
+
+/// The tiles covering a `width` × `height` image as `(columns, rows)` pixel ranges, in the order
+/// of a Hilbert curve so that tiles rendered one after another lie close together. Tiles at the
+/// right and bottom edges may be smaller.
+///
+/// The tiles are generated on the fly by walking the curve over the smallest power of two grid
+/// that holds them all and skipping the cells outside the image.
+pub fn tiles(width: usize, height: usize) -> impl Iterator<Item = (Range<usize>, Range<usize>)> {
+    let columns = width.div_ceil(TILE_SIZE);
+    let rows = height.div_ceil(TILE_SIZE);
+    let n = columns.max(rows).next_power_of_two();
+    (0..n * n)
+        .map(move |d| hilbert_position(n, d))
+        .filter(move |&(x, y)| x < columns && y < rows)
+        .map(move |(x, y)| {
+            (
+                x * TILE_SIZE..((x + 1) * TILE_SIZE).min(width),
+                y * TILE_SIZE..((y + 1) * TILE_SIZE).min(height),
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hilbert_position, tiles, TILE_SIZE};
+
+    #[test]
+    fn hilbert_curve_visits_every_cell_through_neighbours() {
+        let n = 16;
+        let mut visited = vec![false; n * n];
+        let mut previous: Option<(usize, usize)> = None;
+        for d in 0..n * n {
+            let (x, y) = hilbert_position(n, d);
+            assert!(!visited[y * n + x]);
+            visited[y * n + x] = true;
+            if let Some((px, py)) = previous {
+                assert_eq!(
+                    (x as isize - px as isize).abs() + (y as isize - py as isize).abs(),
+                    1
+                );
+            }
+            previous = Some((x, y));
+        }
+    }
+
+    #[test]
+    fn tiles_cover_the_image_once() {
+        let (width, height) = (3 * TILE_SIZE + 5, TILE_SIZE - 1);
+        let mut covered = vec![0; width * height];
+        for (columns, rows) in tiles(width, height) {
+            assert!(columns.len() <= TILE_SIZE && rows.len() <= TILE_SIZE);
+            for y in rows {
+                for x in columns.clone() {
+                    covered[y * width + x] += 1;
+                }
+            }
+        }
+        assert!(covered.iter().all(|&count| count == 1));
+        assert_eq!(tiles(width, height).count(), 4);
+    }
+}
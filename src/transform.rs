@@ -0,0 +1,412 @@
+use std::ops::Mul;
+
+use crate::aabb::Aabb;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+/// Row-major 4x4 matrix acting on column vectors.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Matrix4 {
+    pub m: [[f64; 4]; 4],
+}
+
+impl Matrix4 {
+    pub fn new(m: [[f64; 4]; 4]) -> Matrix4 {
+        Matrix4 { m }
+    }
+
+    pub fn identity() -> Matrix4 {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            row[i] = 1.0;
+        }
+        Matrix4 { m }
+    }
+
+    pub fn transpose(&self) -> Matrix4 {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = self.m[j][i];
+            }
+        }
+        Matrix4 { m }
+    }
+
+    /// Inverse by Gauss-Jordan elimination with partial pivoting, `None` if the matrix is
+    /// singular.
+    pub fn inverse(&self) -> Option<Matrix4> {
+        let mut a = self.m;
+        let mut inverse = Matrix4::identity().m;
+        for column in 0..4 {
+            let pivot = (column..4)
+                .max_by(|&i, &j| a[i][column].abs().partial_cmp(&a[j][column].abs()).unwrap())
+                .unwrap();
+            if a[pivot][column] == 0.0 {
+                return None;
+            }
+            a.swap(column, pivot);
+            inverse.swap(column, pivot);
+
+            let scale = 1.0 / a[column][column];
+            for j in 0..4 {
+                a[column][j] *= scale;
+                inverse[column][j] *= scale;
+            }
+            for row in 0..4 {
+                if row == column {
+                    continue;
+                }
+                let factor = a[row][column];
+                for j in 0..4 {
+                    a[row][j] -= factor * a[column][j];
+                    inverse[row][j] -= factor * inverse[column][j];
+                }
+            }
+        }
+        Some(Matrix4 { m: inverse })
+    }
+
+    /// Determinant of the upper left 3x3 block, i.e. of the linear part of an affine transform.
+    pub fn determinant3(&self) -> f64 {
+        let m = &self.m;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+
+    /// Applies the matrix to a point, dividing by the homogeneous coordinate if it is not 1.
+    pub fn transform_point(&self, p: &Vec3) -> Vec3 {
+        let m = &self.m;
+        let x = m[0][0] * p.x + m[0][1] * p.y + m[0][2] * p.z + m[0][3];
+        let y = m[1][0] * p.x + m[1][1] * p.y + m[1][2] * p.z + m[1][3];
+        let z = m[2][0] * p.x + m[2][1] * p.y + m[2][2] * p.z + m[2][3];
+        let w = m[3][0] * p.x + m[3][1] * p.y + m[3][2] * p.z + m[3][3];
+        if w == 1.0 {
+            Vec3::new(x, y, z)
+        } else {
+            Vec3::new(x / w, y / w, z / w)
+        }
+    }
+
+    /// Applies the linear part of the matrix, ignoring the translation.
+    pub fn transform_vector(&self, v: &Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+            m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+            m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z,
+        )
+    }
+}
+
+impl Mul for Matrix4 {
+    type Output = Matrix4;
+
+    fn mul(self, other: Matrix4) -> Matrix4 {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.m[i][k] * other.m[k][j]).sum();
+            }
+        }
+        Matrix4 { m }
+    }
+}
+
+/// An invertible affine transform from object to world space, stored with its inverse.
+///
+/// Transforms compose like functions: `a * b` applies `b` first.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub matrix: Matrix4,
+    pub inverse: Matrix4,
+}
+
+impl Default for Transform {
+    fn default() -> Transform {
+        Transform::identity()
+    }
+}
+
+impl Transform {
+    /// Returns `None` if `matrix` is singular.
+    pub fn from_matrix(matrix: Matrix4) -> Option<Transform> {
+        let inverse = matrix.inverse()?;
+        Some(Transform { matrix, inverse })
+    }
+
+    pub fn identity() -> Transform {
+        Transform {
+            matrix: Matrix4::identity(),
+            inverse: Matrix4::identity(),
+        }
+    }
+
+    pub fn translate(offset: Vec3) -> Transform {
+        let matrix = |d: Vec3| {
+            Matrix4::new([
+                [1.0, 0.0, 0.0, d.x],
+                [0.0, 1.0, 0.0, d.y],
+                [0.0, 0.0, 1.0, d.z],
+                [0.0, 0.0, 0.0, 1.0],
+            ])
+        };
+        Transform {
+            matrix: matrix(offset),
+            inverse: matrix(-offset),
+        }
+    }
+
+    /// Scales by a factor per axis; all factors must be non-zero.
+    pub fn scale(factors: Vec3) -> Transform {
+        let matrix = |s: Vec3| {
+            Matrix4::new([
+                [s.x, 0.0, 0.0, 0.0],
+                [0.0, s.y, 0.0, 0.0],
+                [0.0, 0.0, s.z, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ])
+        };
+        Transform {
+            matrix: matrix(factors),
+            inverse: matrix(Vec3::new(1.0 / factors.x, 1.0 / factors.y, 1.0 / factors.z)),
+        }
+    }
+
+    /// Counter-clockwise rotation by `degrees` around `axis` when looking against the axis.
+    pub fn rotate(axis: Vec3, degrees: f64) -> Transform {
+        let a = axis.make_unit_vector();
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let matrix = Matrix4::new([
+            [
+                a.x * a.x + (1.0 - a.x * a.x) * cos,
+                a.x * a.y * (1.0 - cos) - a.z * sin,
+                a.x * a.z * (1.0 - cos) + a.y * sin,
+                0.0,
+            ],
+            [
+                a.x * a.y * (1.0 - cos) + a.z * sin,
+                a.y * a.y + (1.0 - a.y * a.y) * cos,
+                a.y * a.z * (1.0 - cos) - a.x * sin,
+                0.0,
+            ],
+            [
+                a.x * a.z * (1.0 - cos) - a.y * sin,
+                a.y * a.z * (1.0 - cos) + a.x * sin,
+                a.z * a.z + (1.0 - a.z * a.z) * cos,
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+        // Rotations are orthogonal.
+        Transform {
+            matrix,
+            inverse: matrix.transpose(),
+        }
+    }
+
+    pub fn rotate_x(degrees: f64) -> Transform {
+        Transform::rotate(Vec3::new(1.0, 0.0, 0.0), degrees)
+    }
+
+    pub fn rotate_y(degrees: f64) -> Transform {
+        Transform::rotate(Vec3::new(0.0, 1.0, 0.0), degrees)
+    }
+
+    pub fn rotate_z(degrees: f64) -> Transform {
+        Transform::rotate(Vec3::new(0.0, 0.0, 1.0), degrees)
+    }
+
+    pub fn inverse(&self) -> Transform {
+        Transform {
+            matrix: self.inverse,
+            inverse: self.matrix,
+        }
+    }
+
+    pub fn point(&self, p: &Vec3) -> Vec3 {
+        self.matrix.transform_point(p)
+    }
+
+    pub fn vector(&self, v: &Vec3) -> Vec3 {
+        self.matrix.transform_vector(v)
+    }
+
+    /// Transforms a surface normal with the inverse transpose; the result is not normalized.
+    pub fn normal(&self, n: &Vec3) -> Vec3 {
+        self.inverse.transpose().transform_vector(n)
+    }
+
+    /// Transforms the ray without normalizing its direction, so ray parameters stay valid.
+    pub fn ray(&self, r: &Ray) -> Ray {
+        Ray::with_time(self.point(&r.origin), self.vector(&r.direction), r.time)
+    }
+
+    /// Box around the transformed corners of `bounds`.
+    pub fn bounding_box(&self, bounds: &Aabb) -> Aabb {
+        let corners: Vec<Vec3> = (0..8)
+            .map(|i| {
+                let pick = |bit: usize, axis: usize| {
+                    if i & bit == 0 {
+                        bounds.min[axis]
+                    } else {
+                        bounds.max[axis]
+                    }
+                };
+                self.point(&Vec3::new(pick(1, 0), pick(2, 1), pick(4, 2)))
+            })
+            .collect();
+        Aabb::from_points(&corners)
+    }
+}
+
+impl Mul for Transform {
+    type Output = Transform;
+
+    fn mul(self, other: Transform) -> Transform {
+        Transform {
+            matrix: self.matrix * other.matrix,
+            inverse: other.inverse * self.inverse,
+        }
+    }
+}
+
+/// A transform in motion: `transform` at time 0, moving on with a constant `velocity` while it
+/// spins about an axis through the placed origin. The direction of `spin` is the axis and its
+/// length the speed in degrees per unit of time, so parts can turn any number of times.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnimatedTransform {
+    pub transform: Transform,
+    pub velocity: Vec3,
+    pub spin: Vec3,
+}
+
+impl AnimatedTransform {
+    pub fn new(transform: Transform, velocity: Vec3, spin: Vec3) -> AnimatedTransform {
+        AnimatedTransform {
+            transform,
+            velocity,
+            spin,
+        }
+    }
+
+    pub fn at(&self, time: f64) -> Transform {
+        let pivot = self.transform.point(&Vec3::origin());
+        let speed = self.spin.length();
+        let rotation = if speed > 0.0 {
+            Transform::rotate(self.spin, speed * time)
+        } else {
+            Transform::identity()
+        };
+        Transform::translate(pivot + time * self.velocity)
+            * rotation
+            * Transform::translate(-pivot)
+            * self.transform
+    }
+
+    /// Box around `bounds` at all times from `time0` to `time1`.
+    pub fn bounding_box(&self, bounds: &Aabb, time0: f64, time1: f64) -> Aabb {
+        if self.spin.squared_length() == 0.0 {
+            // Moving in a straight line sweeps out the box around both ends.
+            return self
+                .at(time0)
+                .bounding_box(bounds)
+                .union(&self.at(time1).bounding_box(bounds));
+        }
+        // Spinning keeps every point within the same distance of the pivot.
+        let placed = self.transform.bounding_box(bounds);
+        let pivot = self.transform.point(&Vec3::origin());
+        let radius = (0..8)
+            .map(|i| {
+                let pick = |bit: usize, axis: usize| {
+                    if i & bit == 0 {
+                        placed.min[axis]
+                    } else {
+                        placed.max[axis]
+                    }
+                };
+                (Vec3::new(pick(1, 0), pick(2, 1), pick(4, 2)) - pivot).length()
+            })
+            .fold(0.0, f64::max);
+        let extent = Vec3::new(radius, radius, radius);
+        let at = |time: f64| {
+            let center = pivot + time * self.velocity;
+            Aabb::new(center - extent, center + extent)
+        };
+        at(time0).union(&at(time1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AnimatedTransform, Matrix4, Transform};
+    use crate::vec3::Vec3;
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-12, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn compose_applies_right_first() {
+        let t = Transform::translate(Vec3::new(1.0, 2.0, 3.0)) * Transform::rotate_z(90.0);
+        assert_close(t.point(&Vec3::new(1.0, 0.0, 0.0)), Vec3::new(1.0, 3.0, 3.0));
+        assert_close(
+            t.vector(&Vec3::new(1.0, 0.0, 0.0)),
+            Vec3::new(0.0, 1.0, 0.0),
+        );
+        assert_close(
+            t.inverse().point(&Vec3::new(1.0, 3.0, 3.0)),
+            Vec3::new(1.0, 0.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn general_inverse() {
+        let m = Matrix4::new([
+            [2.0, 0.5, 0.0, 1.0],
+            [0.0, 1.0, 3.0, -2.0],
+            [1.0, 0.0, 1.0, 0.5],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+        let product = m * m.inverse().unwrap();
+        for i in 0..4 {
+            for j in 0..4 {
+                let expected = if i == j { 1.0 } else { 0.0 };
+                assert!((product.m[i][j] - expected).abs() < 1e-12);
+            }
+        }
+        assert!(Matrix4::new([[0.0; 4]; 4]).inverse().is_none());
+    }
+
+    #[test]
+    fn spinning_and_moving() {
+        let motion = AnimatedTransform::new(
+            Transform::translate(Vec3::new(0.0, 0.0, -5.0)),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(0.0, 360.0, 0.0),
+        );
+        let arm = Vec3::new(1.0, 0.0, 0.0);
+        assert_close(motion.at(0.0).point(&arm), Vec3::new(1.0, 0.0, -5.0));
+        // A quarter turn counter-clockwise about +y takes +x to -z.
+        assert_close(motion.at(0.25).point(&arm), Vec3::new(0.5, 0.0, -6.0));
+        assert_close(motion.at(1.0).point(&arm), Vec3::new(3.0, 0.0, -5.0));
+
+        let bounds = crate::aabb::Aabb::new(Vec3::new(-1.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let swept = motion.bounding_box(&bounds, 0.0, 1.0);
+        for i in 0..=100 {
+            let p = motion.at(i as f64 / 100.0).point(&arm);
+            for axis in 0..3 {
+                assert!(swept.min[axis] <= p[axis] && p[axis] <= swept.max[axis]);
+            }
+        }
+    }
+
+    #[test]
+    fn normals_stay_perpendicular_under_scaling() {
+        let t = Transform::scale(Vec3::new(4.0, 1.0, 1.0));
+        let tangent = Vec3::new(1.0, -1.0, 0.0);
+        let normal = Vec3::new(1.0, 1.0, 0.0);
+        assert!(t.vector(&tangent).dot(&t.normal(&normal)).abs() < 1e-12);
+    }
+}
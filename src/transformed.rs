@@ -0,0 +1,287 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::light::Light;
+use crate::ray::Ray;
+use crate::sample_rng::SampleRng;
+use crate::sampler::Sampler;
+use crate::transform::{AnimatedTransform, Transform};
+use crate::vec3::Vec3;
+
+/// Places an object in the world with an affine transform.
+///
+/// Rays are mapped into object space and hits back out, so the object itself is never modified.
+/// Wrapping an `Arc` lets many instances share one object, e.g. a mesh hierarchy.
+pub struct Transformed<H: Hittable> {
+    pub object: H,
+    pub transform: Transform,
+    bounds: Option<Aabb>,
+}
+
+impl<H: Hittable> Transformed<H> {
+    pub fn new(object: H, transform: Transform) -> Transformed<H> {
+        let bounds = object
+            .bounding_box()
+            .map(|bounds| transform.bounding_box(&bounds));
+        Transformed {
+            object,
+            transform,
+            bounds,
+        }
+    }
+}
+
+/// Hits an object placed with `transform`, with `hit_object` finding the hit in object space.
+fn hit_transformed<'a, F>(transform: &Transform, r: &Ray, hit_object: F) -> Option<HitRecord<'a>>
+where
+    F: FnOnce(&Ray) -> Option<HitRecord<'a>>,
+{
+    // The direction is not renormalized, so the ray parameter is the same in both spaces.
+    let object_ray = transform.inverse().ray(r);
+    let mut hit_record = hit_object(&object_ray)?;
+    hit_record.p = r.at(hit_record.t);
+    // The inverse transpose keeps the normal on the side the ray came from.
+    hit_record.normal = transform.normal(&hit_record.normal).make_unit_vector();
+    Some(hit_record)
+}
+
+impl<H: Hittable> Hittable for Transformed<H> {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        hit_transformed(&self.transform, r, |r| self.object.hit(r, t_min, t_max))
+    }
+
+    fn sample_hit(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        rng: &mut SampleRng,
+    ) -> Option<HitRecord<'_>> {
+        hit_transformed(&self.transform, r, |r| {
+            self.object.sample_hit(r, t_min, t_max, rng)
+        })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.bounds
+    }
+}
+
+impl<H: Light> Light for Transformed<H> {
+    fn sample_direction(&self, origin: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        let object_origin = self.transform.inverse().point(origin);
+        self.transform
+            .vector(&self.object.sample_direction(&object_origin, sampler))
+    }
+
+    fn pdf_value(&self, origin: &Vec3, direction: &Vec3) -> f64 {
+        let inverse = self.transform.inverse();
+        let object_direction = inverse.vector(direction).make_unit_vector();
+        let object_pdf = self
+            .object
+            .pdf_value(&inverse.point(origin), &object_direction);
+        // A linear map A takes the solid angle around the unit direction w to
+        // |det A| / |A w|^3 times as much solid angle.
+        let stretch = self.transform.vector(&object_direction).length();
+        object_pdf * stretch.powi(3) / self.transform.matrix.determinant3().abs()
+    }
+}
+
+/// Places an object with a transform that changes over time, for motion blur.
+pub struct Animated<H: Hittable> {
+    pub object: H,
+    pub motion: AnimatedTransform,
+    bounds: Option<Aabb>,
+}
+
+impl<H: Hittable> Animated<H> {
+    /// The bounding box covers the object while the shutter is open from `shutter_open` to
+    /// `shutter_close`.
+    pub fn new(
+        object: H,
+        motion: AnimatedTransform,
+        shutter_open: f64,
+        shutter_close: f64,
+    ) -> Animated<H> {
+        let bounds = object
+            .bounding_box()
+            .map(|bounds| motion.bounding_box(&bounds, shutter_open, shutter_close));
+        Animated {
+            object,
+            motion,
+            bounds,
+        }
+    }
+}
+
+impl<H: Hittable> Hittable for Animated<H> {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        hit_transformed(&self.motion.at(r.time), r, |r| {
+            self.object.hit(r, t_min, t_max)
+        })
+    }
+
+    fn sample_hit(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        rng: &mut SampleRng,
+    ) -> Option<HitRecord<'_>> {
+        hit_transformed(&self.motion.at(r.time), r, |r| {
+            self.object.sample_hit(r, t_min, t_max, rng)
+        })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.bounds
+    }
+}
+
+impl<T: Hittable + Send + ?Sized> Hittable for Arc<T> {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        (**self).hit(r, t_min, t_max)
+    }
+
+    fn sample_hit(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        rng: &mut SampleRng,
+    ) -> Option<HitRecord<'_>> {
+        (**self).sample_hit(r, t_min, t_max, rng)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        (**self).bounding_box()
+    }
+}
+
+impl<T: Light + Send + ?Sized> Light for Arc<T> {
+    fn sample_direction(&self, origin: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        (**self).sample_direction(origin, sampler)
+    }
+
+    fn pdf_value(&self, origin: &Vec3, direction: &Vec3) -> f64 {
+        (**self).pdf_value(origin, direction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::{Animated, Transformed};
+    use crate::color::Color;
+    use crate::hittable::{Face, Hittable};
+    use crate::light::Light;
+    use crate::material_variants::MaterialVariants;
+    use crate::ray::Ray;
+    use crate::sphere::Sphere;
+    use crate::transform::{AnimatedTransform, Transform};
+    use crate::vec3::Vec3;
+
+    fn unit_sphere() -> Sphere {
+        Sphere::new(
+            Vec3::origin(),
+            1.0,
+            MaterialVariants::Lambertian(Color::new_white().into()),
+        )
+    }
+
+    #[test]
+    fn scaled_and_translated_sphere() {
+        let ellipsoid = Transformed::new(
+            Arc::new(unit_sphere()),
+            Transform::translate(Vec3::new(0.0, 0.0, -5.0))
+                * Transform::scale(Vec3::new(2.0, 1.0, 1.0)),
+        );
+        let bounds = ellipsoid.bounding_box().unwrap();
+        assert_eq!(bounds.min, Vec3::new(-2.0, -1.0, -6.0));
+        assert_eq!(bounds.max, Vec3::new(2.0, 1.0, -4.0));
+
+        let r = Ray::new(Vec3::new(10.0, 0.0, -5.0), Vec3::new(-1.0, 0.0, 0.0));
+        let hit = ellipsoid.hit(&r, 0.001, f64::INFINITY).unwrap();
+        assert!((hit.t - 8.0).abs() < 1e-12);
+        assert!((hit.p - Vec3::new(2.0, 0.0, -5.0)).length() < 1e-12);
+        assert!((hit.normal - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-12);
+        assert_eq!(hit.face, Face::Outside);
+    }
+
+    #[test]
+    fn instances_share_one_object() {
+        let shared = Arc::new(unit_sphere());
+        let instances: Vec<_> = (0..10)
+            .map(|i| {
+                Transformed::new(
+                    Arc::clone(&shared),
+                    Transform::translate(Vec3::new(3.0 * i as f64, 0.0, 0.0)),
+                )
+            })
+            .collect();
+        assert_eq!(Arc::strong_count(&shared), 11);
+        let r = Ray::new(Vec3::new(27.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        assert!(instances[9].hit(&r, 0.001, f64::INFINITY).is_some());
+        assert!(instances[8].hit(&r, 0.001, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn animated_instance_is_hit_where_it_is_at_ray_time() {
+        let moving = Animated::new(
+            unit_sphere(),
+            AnimatedTransform::new(
+                Transform::translate(Vec3::new(0.0, 0.0, -5.0)),
+                Vec3::new(4.0, 0.0, 0.0),
+                Vec3::new(0.0, 0.0, 0.0),
+            ),
+            0.0,
+            1.0,
+        );
+        let bounds = moving.bounding_box().unwrap();
+        assert_eq!(bounds.min, Vec3::new(-1.0, -1.0, -6.0));
+        assert_eq!(bounds.max, Vec3::new(5.0, 1.0, -4.0));
+
+        let at =
+            |time: f64| Ray::with_time(Vec3::new(4.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0), time);
+        assert!(moving.hit(&at(0.0), 0.001, f64::INFINITY).is_none());
+        let hit = moving.hit(&at(1.0), 0.001, f64::INFINITY).unwrap();
+        assert!((hit.t - 4.0).abs() < 1e-12);
+        assert!((hit.normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-12);
+    }
+
+    #[test]
+    fn light_pdf_integrates_to_one() {
+        let light = Transformed::new(
+            unit_sphere(),
+            Transform::translate(Vec3::new(0.0, 4.0, 0.0))
+                * Transform::scale(Vec3::new(2.0, 0.5, 1.0)),
+        );
+        // Integrate the density over the sphere of directions by uniform sampling.
+        let mut rng = StdRng::seed_from_u64(5);
+        let samples = 200_000;
+        let integral: f64 = (0..samples)
+            .map(|_| {
+                let direction = Vec3::random_with_bounds_from(&mut rng, -1.0, 1.0);
+                if direction.squared_length() > 1.0 {
+                    return 0.0;
+                }
+                light.pdf_value(&Vec3::origin(), &direction)
+            })
+            .sum::<f64>()
+            / samples as f64
+            // Uniform samples in the cube [-1, 1]^3 are accepted with probability π/6 and
+            // the directions are then uniform with density 1 / (4π).
+            * 4.0
+            * std::f64::consts::PI
+            / (std::f64::consts::PI / 6.0);
+        assert!((integral - 1.0).abs() < 0.02, "{}", integral);
+
+        let direction = light.sample_direction(&Vec3::origin(), &mut rng);
+        assert!(light.pdf_value(&Vec3::origin(), &direction) > 0.0);
+    }
+}
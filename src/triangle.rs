@@ -0,0 +1,260 @@
+use crate::aabb::Aabb;
+use crate::hittable::{get_face_normal, HitRecord, Hittable};
+use crate::light::Light;
+use crate::material_variants::MaterialVariants;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::vec3::Vec3;
+
+/// Ray parameter and barycentric weights of `p0`, `p1` and `p2` at an intersection.
+pub struct TriangleIntersection {
+    pub t: f64,
+    pub barycentrics: [f64; 3],
+}
+
+/// Watertight ray/triangle intersection after Woop, Benthin and Wald, "Watertight Ray/Triangle
+/// Intersection", JCGT 2013. Rays through shared edges or vertices never slip between triangles.
+pub fn intersect_triangle(
+    r: &Ray,
+    p0: &Vec3,
+    p1: &Vec3,
+    p2: &Vec3,
+    t_min: f64,
+    t_max: f64,
+) -> Option<TriangleIntersection> {
+    // Permute the axes so that the ray travels mostly along z.
+    let d = r.direction;
+    let kz = if d.x.abs() > d.y.abs() {
+        if d.x.abs() > d.z.abs() {
+            0
+        } else {
+            2
+        }
+    } else if d.y.abs() > d.z.abs() {
+        1
+    } else {
+        2
+    };
+    let mut kx = (kz + 1) % 3;
+    let mut ky = (kx + 1) % 3;
+    // Keep the winding of the triangle when the ray points along -z.
+    if d[kz] < 0.0 {
+        std::mem::swap(&mut kx, &mut ky);
+    }
+
+    // Shear the triangle into the ray's coordinate system, where the ray is the +z axis.
+    let shear_x = d[kx] / d[kz];
+    let shear_y = d[ky] / d[kz];
+    let shear_z = 1.0 / d[kz];
+
+    let a = *p0 - r.origin;
+    let b = *p1 - r.origin;
+    let c = *p2 - r.origin;
+    let ax = a[kx] - shear_x * a[kz];
+    let ay = a[ky] - shear_y * a[kz];
+    let bx = b[kx] - shear_x * b[kz];
+    let by = b[ky] - shear_y * b[kz];
+    let cx = c[kx] - shear_x * c[kz];
+    let cy = c[ky] - shear_y * c[kz];
+
+    // Scaled barycentrics as 2D edge functions; a hit needs all of them to share a sign.
+    let u = cx * by - cy * bx;
+    let v = ax * cy - ay * cx;
+    let w = bx * ay - by * ax;
+    if (u < 0.0 || v < 0.0 || w < 0.0) && (u > 0.0 || v > 0.0 || w > 0.0) {
+        return None;
+    }
+
+    let det = u + v + w;
+    if det == 0.0 {
+        return None;
+    }
+
+    let az = shear_z * a[kz];
+    let bz = shear_z * b[kz];
+    let cz = shear_z * c[kz];
+    let t = (u * az + v * bz + w * cz) / det;
+    if t <= t_min || t >= t_max {
+        return None;
+    }
+
+    Some(TriangleIntersection {
+        t,
+        barycentrics: [u / det, v / det, w / det],
+    })
+}
+
+/// Maps two uniform numbers in [0, 1) to a uniformly distributed point on the triangle.
+pub fn sample_triangle(p0: &Vec3, p1: &Vec3, p2: &Vec3, r1: f64, r2: f64) -> Vec3 {
+    let s = r1.sqrt();
+    (1.0 - s) * *p0 + s * (1.0 - r2) * *p1 + s * r2 * *p2
+}
+
+/// Solid angle density of sampling the triangle uniformly by area, as seen along `direction`
+/// from `origin`; zero if the direction misses it.
+pub fn triangle_solid_angle_pdf(
+    origin: &Vec3,
+    direction: &Vec3,
+    p0: &Vec3,
+    p1: &Vec3,
+    p2: &Vec3,
+) -> f64 {
+    let r = Ray::new(*origin, *direction);
+    let intersection = match intersect_triangle(&r, p0, p1, p2, 0.001, f64::INFINITY) {
+        Some(intersection) => intersection,
+        None => return 0.0,
+    };
+    let area_normal = (*p1 - *p0).cross(&(*p2 - *p0));
+    let area = 0.5 * area_normal.length();
+    let direction_length = direction.length();
+    let distance_squared = (intersection.t * direction_length).powi(2);
+    let cosine = (direction.dot(&area_normal) / (direction_length * 2.0 * area)).abs();
+    if cosine == 0.0 {
+        return 0.0;
+    }
+    distance_squared / (cosine * area)
+}
+
+/// Bounds of three points.
+pub fn triangle_bounding_box(p0: &Vec3, p1: &Vec3, p2: &Vec3) -> Aabb {
+    Aabb::from_points(&[*p0, *p1, *p2])
+}
+
+/// A single flat-shaded triangle. Its front face is the one seen with the vertices in
+/// counter-clockwise order. The surface coordinates are the barycentric weights of the second
+/// and third vertex.
+#[derive(Clone)]
+pub struct Triangle {
+    pub vertices: [Vec3; 3],
+    pub material: MaterialVariants,
+}
+
+impl Triangle {
+    pub fn new(p0: Vec3, p1: Vec3, p2: Vec3, material: MaterialVariants) -> Triangle {
+        Triangle {
+            vertices: [p0, p1, p2],
+            material,
+        }
+    }
+
+    pub fn geometric_normal(&self) -> Vec3 {
+        let [p0, p1, p2] = self.vertices;
+        (p1 - p0).cross(&(p2 - p0)).make_unit_vector()
+    }
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let [p0, p1, p2] = &self.vertices;
+        let intersection = intersect_triangle(r, p0, p1, p2, t_min, t_max)?;
+        let (face, normal) = get_face_normal(r, &self.geometric_normal());
+        let [_, b1, b2] = intersection.barycentrics;
+        Some(HitRecord {
+            p: r.at(intersection.t),
+            t: intersection.t,
+            u: b1,
+            v: b2,
+            normal,
+            face,
+            material: &self.material,
+        })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let [p0, p1, p2] = &self.vertices;
+        Some(triangle_bounding_box(p0, p1, p2))
+    }
+}
+
+impl Light for Triangle {
+    fn sample_direction(&self, origin: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        let [p0, p1, p2] = &self.vertices;
+        let (u1, u2) = sampler.get_2d();
+        sample_triangle(p0, p1, p2, u1, u2) - *origin
+    }
+
+    fn pdf_value(&self, origin: &Vec3, direction: &Vec3) -> f64 {
+        let [p0, p1, p2] = &self.vertices;
+        triangle_solid_angle_pdf(origin, direction, p0, p1, p2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{intersect_triangle, Triangle};
+    use crate::color::Color;
+    use crate::hittable::{Face, Hittable};
+    use crate::material_variants::MaterialVariants;
+    use crate::ray::Ray;
+    use crate::vec3::Vec3;
+
+    fn unit_triangle() -> Triangle {
+        Triangle::new(
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            MaterialVariants::Lambertian(Color::new_white().into()),
+        )
+    }
+
+    #[test]
+    fn hit_front_and_back() {
+        let triangle = unit_triangle();
+        let front = Ray::new(Vec3::new(0.25, 0.25, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = triangle.hit(&front, 0.0, f64::INFINITY).unwrap();
+        assert_eq!(hit.t, 1.0);
+        assert_eq!(hit.normal, Vec3::new(0.0, 0.0, 1.0));
+        assert!(matches!(hit.face, Face::Outside));
+
+        let back = Ray::new(Vec3::new(0.25, 0.25, -1.0), Vec3::new(0.0, 0.0, 1.0));
+        let hit = triangle.hit(&back, 0.0, f64::INFINITY).unwrap();
+        assert_eq!(hit.normal, Vec3::new(0.0, 0.0, -1.0));
+        assert!(matches!(hit.face, Face::Inside));
+    }
+
+    #[test]
+    fn miss_outside_and_beyond_t_max() {
+        let triangle = unit_triangle();
+        let outside = Ray::new(Vec3::new(0.75, 0.75, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(triangle.hit(&outside, 0.0, f64::INFINITY).is_none());
+        let inside = Ray::new(Vec3::new(0.25, 0.25, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(triangle.hit(&inside, 0.0, 0.5).is_none());
+    }
+
+    #[test]
+    fn shared_edge_is_watertight() {
+        // Two triangles sharing the diagonal of the unit square; rays aimed exactly at the
+        // diagonal must hit at least one of them.
+        let p0 = Vec3::new(0.0, 0.0, 0.0);
+        let p1 = Vec3::new(1.0, 0.0, 0.0);
+        let p2 = Vec3::new(1.0, 1.0, 0.0);
+        let p3 = Vec3::new(0.0, 1.0, 0.0);
+        let direction = Vec3::new(0.1, -0.3, -1.0);
+        for i in 1..1000 {
+            let s = i as f64 / 1000.0;
+            let r = Ray::new(Vec3::new(s, s, 0.0) - direction * 3.7, direction);
+            assert!(
+                intersect_triangle(&r, &p0, &p1, &p2, 0.0, f64::INFINITY).is_some()
+                    || intersect_triangle(&r, &p0, &p2, &p3, 0.0, f64::INFINITY).is_some()
+            );
+        }
+    }
+
+    #[test]
+    fn barycentrics_sum_to_one() {
+        let r = Ray::new(Vec3::new(0.2, 0.3, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        let intersection = intersect_triangle(
+            &r,
+            &Vec3::new(0.0, 0.0, 0.0),
+            &Vec3::new(1.0, 0.0, 0.0),
+            &Vec3::new(0.0, 1.0, 0.0),
+            0.0,
+            f64::INFINITY,
+        )
+        .unwrap();
+        let [b0, b1, b2] = intersection.barycentrics;
+        assert!((b0 + b1 + b2 - 1.0).abs() < 1e-12);
+        assert!((b1 - 0.2).abs() < 1e-12);
+        assert!((b2 - 0.3).abs() < 1e-12);
+    }
+}
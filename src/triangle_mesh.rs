@@ -0,0 +1,237 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::bvh::Bvh;
+use crate::hittable::{get_face_normal, HitRecord, Hittable};
+use crate::light::Light;
+use crate::material_variants::MaterialVariants;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::triangle::{
+    intersect_triangle, sample_triangle, triangle_bounding_box, triangle_solid_angle_pdf,
+};
+use crate::vec3::Vec3;
+
+/// Indexed triangle mesh with shared vertex attributes.
+///
+/// `normals` and `uvs` are either empty or hold one entry per position. With per-vertex normals
+/// the surface is smooth shaded, otherwise every triangle uses its geometric normal. Without
+/// texture coordinates every triangle is parameterized like a single [`Triangle`].
+///
+/// [`Triangle`]: crate::triangle::Triangle
+pub struct TriangleMesh {
+    pub positions: Vec<Vec3>,
+    pub normals: Vec<Vec3>,
+    pub uvs: Vec<(f64, f64)>,
+    /// Counter-clockwise vertex indices of every triangle.
+    pub indices: Vec<[usize; 3]>,
+    pub material: MaterialVariants,
+}
+
+impl TriangleMesh {
+    pub fn new(
+        positions: Vec<Vec3>,
+        indices: Vec<[usize; 3]>,
+        material: MaterialVariants,
+    ) -> TriangleMesh {
+        TriangleMesh {
+            positions,
+            normals: Vec::new(),
+            uvs: Vec::new(),
+            indices,
+            material,
+        }
+    }
+
+    pub fn with_normals(mut self, normals: Vec<Vec3>) -> TriangleMesh {
+        assert_eq!(normals.len(), self.positions.len());
+        self.normals = normals;
+        self
+    }
+
+    pub fn with_uvs(mut self, uvs: Vec<(f64, f64)>) -> TriangleMesh {
+        assert_eq!(uvs.len(), self.positions.len());
+        self.uvs = uvs;
+        self
+    }
+
+    /// Replaces the normals with area-weighted averages of the adjacent face normals.
+    pub fn compute_smooth_normals(&mut self) {
+        let mut normals = vec![Vec3::origin(); self.positions.len()];
+        for [i0, i1, i2] in &self.indices {
+            let p0 = self.positions[*i0];
+            // The unnormalized cross product is proportional to the triangle area.
+            let face_normal = (self.positions[*i1] - p0).cross(&(self.positions[*i2] - p0));
+            for i in &[*i0, *i1, *i2] {
+                normals[*i] += face_normal;
+            }
+        }
+        self.normals = normals
+            .into_iter()
+            .map(|n| {
+                if n.squared_length() > 0.0 {
+                    n.make_unit_vector()
+                } else {
+                    n
+                }
+            })
+            .collect();
+    }
+
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    fn vertices(&self, triangle: usize) -> (&Vec3, &Vec3, &Vec3) {
+        let [i0, i1, i2] = self.indices[triangle];
+        (
+            &self.positions[i0],
+            &self.positions[i1],
+            &self.positions[i2],
+        )
+    }
+
+    /// Splits the mesh into triangles that keep referring to the shared vertex buffers.
+    pub fn triangles(mesh: &Arc<TriangleMesh>) -> Vec<MeshTriangle> {
+        (0..mesh.len())
+            .map(|index| MeshTriangle {
+                mesh: Arc::clone(mesh),
+                index,
+            })
+            .collect()
+    }
+
+    /// Builds a hierarchy over the triangles of the mesh.
+    pub fn into_bvh(self) -> Bvh<MeshTriangle> {
+        Bvh::new(TriangleMesh::triangles(&Arc::new(self)))
+    }
+}
+
+/// One triangle of a [`TriangleMesh`].
+#[derive(Clone)]
+pub struct MeshTriangle {
+    mesh: Arc<TriangleMesh>,
+    index: usize,
+}
+
+impl Hittable for MeshTriangle {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let mesh = &*self.mesh;
+        let (p0, p1, p2) = mesh.vertices(self.index);
+        let intersection = intersect_triangle(r, p0, p1, p2, t_min, t_max)?;
+
+        let geometric_normal = (*p1 - *p0).cross(&(*p2 - *p0)).make_unit_vector();
+        let (face, geometric_normal) = get_face_normal(r, &geometric_normal);
+        let [i0, i1, i2] = mesh.indices[self.index];
+        let [b0, b1, b2] = intersection.barycentrics;
+        let (u, v) = if mesh.uvs.is_empty() {
+            (b1, b2)
+        } else {
+            let (uv0, uv1, uv2) = (mesh.uvs[i0], mesh.uvs[i1], mesh.uvs[i2]);
+            (
+                b0 * uv0.0 + b1 * uv1.0 + b2 * uv2.0,
+                b0 * uv0.1 + b1 * uv1.1 + b2 * uv2.1,
+            )
+        };
+        let normal = if mesh.normals.is_empty() {
+            geometric_normal
+        } else {
+            let shading_normal =
+                b0 * mesh.normals[i0] + b1 * mesh.normals[i1] + b2 * mesh.normals[i2];
+            if shading_normal.squared_length() == 0.0 {
+                geometric_normal
+            } else if shading_normal.dot(&geometric_normal) < 0.0 {
+                // Keep the shading normal on the side of the surface the ray arrived from.
+                -shading_normal.make_unit_vector()
+            } else {
+                shading_normal.make_unit_vector()
+            }
+        };
+
+        Some(HitRecord {
+            p: r.at(intersection.t),
+            t: intersection.t,
+            u,
+            v,
+            normal,
+            face,
+            material: &mesh.material,
+        })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let (p0, p1, p2) = self.mesh.vertices(self.index);
+        Some(triangle_bounding_box(p0, p1, p2))
+    }
+}
+
+impl Light for MeshTriangle {
+    fn sample_direction(&self, origin: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        let (p0, p1, p2) = self.mesh.vertices(self.index);
+        let (u1, u2) = sampler.get_2d();
+        sample_triangle(p0, p1, p2, u1, u2) - *origin
+    }
+
+    fn pdf_value(&self, origin: &Vec3, direction: &Vec3) -> f64 {
+        let (p0, p1, p2) = self.mesh.vertices(self.index);
+        triangle_solid_angle_pdf(origin, direction, p0, p1, p2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TriangleMesh;
+    use crate::color::Color;
+    use crate::hittable::Hittable;
+    use crate::material_variants::MaterialVariants;
+    use crate::ray::Ray;
+    use crate::vec3::Vec3;
+
+    /// A tent of two triangles folded along the z axis.
+    fn tent() -> TriangleMesh {
+        TriangleMesh::new(
+            vec![
+                Vec3::new(-1.0, 0.0, -1.0),
+                Vec3::new(0.0, 1.0, -1.0),
+                Vec3::new(0.0, 1.0, 1.0),
+                Vec3::new(-1.0, 0.0, 1.0),
+                Vec3::new(1.0, 0.0, -1.0),
+                Vec3::new(1.0, 0.0, 1.0),
+            ],
+            vec![[0, 2, 1], [0, 3, 2], [4, 1, 2], [4, 2, 5]],
+            MaterialVariants::Lambertian(Color::new_white().into()),
+        )
+    }
+
+    #[test]
+    fn flat_and_smooth_normals() {
+        let r = Ray::new(Vec3::new(-0.1, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+
+        let flat = tent().into_bvh();
+        let flat_normal = flat.hit(&r, 0.0, f64::INFINITY).unwrap().normal;
+        let expected = Vec3::new(-1.0, 1.0, 0.0).make_unit_vector();
+        assert!((flat_normal - expected).length() < 1e-12);
+
+        let mut mesh = tent();
+        mesh.compute_smooth_normals();
+        assert!((mesh.normals[1] - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-12);
+        let smooth = mesh.into_bvh();
+        let smooth_normal = smooth.hit(&r, 0.0, f64::INFINITY).unwrap().normal;
+        // Close to the ridge the interpolated normal leans towards +y.
+        assert!(smooth_normal.y > flat_normal.y);
+        assert!((smooth_normal.length() - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn bvh_contains_every_triangle() {
+        let bvh = tent().into_bvh();
+        assert_eq!(bvh.len(), 4);
+        let bounds = bvh.bounding_box().unwrap();
+        assert_eq!(bounds.min, Vec3::new(-1.0, 0.0, -1.0));
+        assert_eq!(bounds.max, Vec3::new(1.0, 1.0, 1.0));
+    }
+}
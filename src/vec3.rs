@@ -1,6 +1,9 @@
 use rand::distributions::{Distribution, Uniform};
+use rand::Rng;
 use std::ops::{Add, AddAssign, Div, DivAssign, Index, Mul, MulAssign, Neg, Sub, SubAssign};
 
+use crate::sampler::Sampler;
+
 #[derive(PartialEq, Debug, Clone, Copy)]
 pub struct Vec3 {
     pub x: f64,
@@ -45,38 +48,27 @@ impl Vec3 {
         (*self) / self.length()
     }
 
-    pub fn random() -> Vec3 {
-        Vec3::random_with_bounds(-1.0, 1.0)
-    }
-
-    pub fn random_with_bounds(min: f64, max: f64) -> Vec3 {
-        let mut rng = rand::thread_rng();
+    pub fn random_with_bounds_from<R: Rng + ?Sized>(rng: &mut R, min: f64, max: f64) -> Vec3 {
         let uniform_dist = Uniform::new_inclusive(min, max);
 
         Vec3 {
-            x: uniform_dist.sample(&mut rng),
-            y: uniform_dist.sample(&mut rng),
-            z: uniform_dist.sample(&mut rng),
+            x: uniform_dist.sample(rng),
+            y: uniform_dist.sample(rng),
+            z: uniform_dist.sample(rng),
         }
     }
 
-    pub fn random_in_unit_sphere() -> Vec3 {
-        loop {
-            let p = Vec3::random_with_bounds(-1.0, 1.0);
-            if p.squared_length() <= 1.0 {
-                return p;
-            }
-        }
+    /// Uniformly distributed point inside the unit ball.
+    pub fn random_in_unit_sphere<S: Sampler + ?Sized>(sampler: &mut S) -> Vec3 {
+        Vec3::random_unit_vector(sampler) * sampler.get_1d().cbrt()
     }
 
-    pub fn random_unit_vector() -> Vec3 {
-        let mut rng = rand::thread_rng();
-        let uniform_a = Uniform::new(0.0_f64, 2.0_f64 * std::f64::consts::PI);
-        let uniform_z = Uniform::new_inclusive(-1.0_f64, 1.0_f64);
-
-        let a = uniform_a.sample(&mut rng);
-        let z = uniform_z.sample(&mut rng);
-        let r = (1.0_f64 - z * z).sqrt();
+    /// Uniformly distributed direction.
+    pub fn random_unit_vector<S: Sampler + ?Sized>(sampler: &mut S) -> Vec3 {
+        let (u1, u2) = sampler.get_2d();
+        let a = 2.0 * std::f64::consts::PI * u2;
+        let z = 1.0 - 2.0 * u1;
+        let r = (1.0_f64 - z * z).max(0.0).sqrt();
 
         Vec3 {
             x: r * a.cos(),
@@ -89,17 +81,21 @@ impl Vec3 {
         *self - 2.0 * self.dot(n) * (*n)
     }
 
-    pub fn random_in_unit_disk() -> Vec3 {
-        let mut rng = rand::thread_rng();
-        let uniform = Uniform::new(-1.0_f64, 1.0_f64);
-
-        loop {
-            let p = Vec3::new(uniform.sample(&mut rng), uniform.sample(&mut rng), 0.0);
-            if p.squared_length() >= 1.0 {
-                continue;
-            };
-            return p;
+    /// Uniformly distributed point on the unit disk in the xy plane, using the concentric
+    /// mapping of the square so that stratified samples stay stratified.
+    pub fn random_in_unit_disk<S: Sampler + ?Sized>(sampler: &mut S) -> Vec3 {
+        let (u1, u2) = sampler.get_2d();
+        let (a, b) = (2.0 * u1 - 1.0, 2.0 * u2 - 1.0);
+        if a == 0.0 && b == 0.0 {
+            return Vec3::origin();
         }
+        let quarter = std::f64::consts::FRAC_PI_4;
+        let (r, phi) = if a.abs() > b.abs() {
+            (a, quarter * (b / a))
+        } else {
+            (b, 2.0 * quarter - quarter * (a / b))
+        };
+        Vec3::new(r * phi.cos(), r * phi.sin(), 0.0)
     }
 }
 